@@ -0,0 +1,90 @@
+// Compile-time benchmark for Luma
+//
+// Generates a large straight-line program and times each frontend stage.
+// Its constant pool stays small; `benches/constants.rs` times lookups
+// against a nearly full one.
+// Run with `cargo bench --bench compile`.
+use luma::backend::vm::{BufferOutput, VM};
+use luma::frontend::compiler::Compiler;
+use luma::frontend::lexer::Lexer;
+use luma::frontend::parser::Parser;
+use luma::shared::value::Value;
+use std::time::{Duration, Instant};
+
+const STATEMENTS: usize = 50_000;
+const ITERATIONS: u32 = 5;
+
+// Each group of four statements defines, updates and shows one variable.
+// Names and literals cycle through small pools so the constant pool sees
+// both hits and misses while staying within the 256 constants a chunk can
+// address.
+fn generate_program(statements: usize) -> String {
+    let mut source = String::with_capacity(statements * 32);
+    for i in 0..statements {
+        let group = i / 4;
+        let v = group % 64;
+        match i % 4 {
+            0 => source.push_str(&format!("let v{} be {} + {}\n", v, v, group % 32)),
+            1 => source.push_str(&format!("let s{} be \"label {}\"\n", group % 32, v)),
+            2 => source.push_str(&format!("v{} = v{} * {}.5\n", v, v, i % 7)),
+            _ => source.push_str(&format!("show v{} - 0.25\n", v)),
+        }
+    }
+    source
+}
+
+// What the generated program shows, so a miscompiled chunk is caught
+// before it is timed
+fn expected_output(statements: usize) -> String {
+    let mut output = String::new();
+    for group in 0..statements / 4 {
+        let v = (group % 64 + group % 32) as f64;
+        let factor = ((group * 4 + 2) % 7) as f64 + 0.5;
+        output.push_str(&format!("{}\n", Value::Number(v * factor - 0.25)));
+    }
+    output
+}
+
+fn check_output(source: &str) {
+    let tokens = Lexer::new(source).tokenize().expect("lexing failed");
+    let statements = Parser::new(tokens).parse().expect("parsing failed");
+    let chunk = Compiler::new().compile(&statements).expect("compilation failed");
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    vm.interpret(chunk).expect("generated program failed");
+    assert!(output.contents() == expected_output(STATEMENTS), "generated program showed the wrong values");
+}
+
+fn main() {
+    let source = generate_program(STATEMENTS);
+    check_output(&source);
+
+    let mut lex_time = Duration::ZERO;
+    let mut parse_time = Duration::ZERO;
+    let mut compile_time = Duration::ZERO;
+    let mut constants = 0;
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let tokens = Lexer::new(&source).tokenize().expect("lexing failed");
+        lex_time += start.elapsed();
+
+        let start = Instant::now();
+        let statements = Parser::new(tokens).parse().expect("parsing failed");
+        parse_time += start.elapsed();
+
+        let start = Instant::now();
+        let chunk = Compiler::new().compile(&statements).expect("compilation failed");
+        compile_time += start.elapsed();
+
+        constants = chunk.constants.len();
+    }
+
+    let per_run = |total: Duration| total.as_secs_f64() * 1000.0 / ITERATIONS as f64;
+    println!("=== Compile benchmark: {} statements, {} runs ===", STATEMENTS, ITERATIONS);
+    println!("{:<10} {:>10.3}ms", "lex", per_run(lex_time));
+    println!("{:<10} {:>10.3}ms", "parse", per_run(parse_time));
+    println!("{:<10} {:>10.3}ms", "compile", per_run(compile_time));
+    println!("{:<10} {:>10}", "constants", constants);
+}
@@ -0,0 +1,99 @@
+// Constant interning benchmark for Luma
+//
+// Fills a chunk's constant pool close to the 256 entries it can address,
+// then times repeated lookups of constants already in it: directly through
+// `Chunk`, against a linear scan of the pool, and through the compiler for
+// a program that only uses those constants.
+// Run with `cargo bench --bench constants`.
+use luma::frontend::compiler::Compiler;
+use luma::frontend::lexer::Lexer;
+use luma::frontend::parser::Parser;
+use luma::shared::chunk::Chunk;
+use luma::shared::value::Value;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const POOL: usize = 250;
+const LOOKUPS: usize = 1_000_000;
+const STATEMENTS: usize = 50_000;
+const ITERATIONS: u32 = 5;
+
+// Half numbers, half strings, all distinct
+fn pool() -> Vec<Value> {
+    (0..POOL)
+        .map(|i| if i.is_multiple_of(2) { Value::Number(i as f64 + 0.5) } else { Value::string(format!("item {}", i)) })
+        .collect()
+}
+
+// Every statement shows a constant from the pool, so each literal the
+// compiler sees is a hit against a nearly full pool
+fn generate_program(statements: usize) -> String {
+    let mut source = String::with_capacity(statements * 16);
+    for i in 0..statements {
+        let k = (i * 7) % POOL;
+        if k.is_multiple_of(2) {
+            source.push_str(&format!("show {}.5\n", k));
+        } else {
+            source.push_str(&format!("show \"item {}\"\n", k));
+        }
+    }
+    source
+}
+
+// What the pre-interning compiler did for each literal
+fn linear_index(constants: &[Value], value: &Value) -> Option<usize> {
+    constants.iter().position(|constant| match (constant, value) {
+        (Value::Number(a), Value::Number(b)) => a.to_bits() == b.to_bits(),
+        (a, b) => a == b,
+    })
+}
+
+fn main() {
+    let values = pool();
+    let mut chunk = Chunk::new();
+    for (i, value) in values.iter().enumerate() {
+        assert_eq!(chunk.add_constant(value.clone()), i);
+    }
+
+    let mut interned_time = Duration::ZERO;
+    let mut linear_time = Duration::ZERO;
+    let mut compile_time = Duration::ZERO;
+    let source = generate_program(STATEMENTS);
+    let tokens = Lexer::new(&source).tokenize().expect("lexing failed");
+    let statements = Parser::new(tokens).parse().expect("parsing failed");
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        for i in 0..LOOKUPS {
+            let k = (i * 7) % POOL;
+            let index = match &values[k] {
+                Value::String(s) => chunk.intern_string(s),
+                value => chunk.add_constant(value.clone()),
+            };
+            assert_eq!(black_box(index), k);
+        }
+        interned_time += start.elapsed();
+
+        let start = Instant::now();
+        for i in 0..LOOKUPS {
+            let k = (i * 7) % POOL;
+            assert_eq!(black_box(linear_index(&chunk.constants, &values[k])), Some(k));
+        }
+        linear_time += start.elapsed();
+
+        let start = Instant::now();
+        let compiled = Compiler::new().compile(&statements).expect("compilation failed");
+        compile_time += start.elapsed();
+        assert_eq!(compiled.constants.len(), POOL, "the program should only use the pool's constants");
+    }
+    assert_eq!(chunk.constants.len(), POOL, "lookups must not add constants");
+
+    let per_run = |total: Duration| total.as_secs_f64() * 1000.0 / ITERATIONS as f64;
+    println!(
+        "=== Constant benchmark: {} constants, {} lookups, {} statements, {} runs ===",
+        POOL, LOOKUPS, STATEMENTS, ITERATIONS
+    );
+    println!("{:<10} {:>10.3}ms", "interned", per_run(interned_time));
+    println!("{:<10} {:>10.3}ms", "linear", per_run(linear_time));
+    println!("{:<10} {:>10.3}ms", "compile", per_run(compile_time));
+}
@@ -0,0 +1,79 @@
+// Execution benchmark: stack VM vs register VM
+//
+// Runs the same programs on both backends and reports the time per run.
+// Run with `cargo bench --bench vm`.
+use luma::backend::register::{RegisterCompiler, RegisterVM};
+use luma::backend::vm::vm::VM;
+use luma::frontend::ast::Statement;
+use luma::frontend::compiler::Compiler;
+use luma::frontend::lexer::Lexer;
+use luma::frontend::parser::Parser;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5;
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "arithmetic loop",
+        "let i be 0
+let total be 0
+while i < 200000 then
+    total = total + (i * 2) - (i / 3) + i % 7
+    i = i + 1",
+    ),
+    (
+        "repeat counter",
+        "let total be 0
+repeat 200000 times then
+    total = total + 1",
+    ),
+    (
+        "fibonacci",
+        "let a be 0
+let b be 1
+let i be 0
+while i < 100000 then
+    let next be a + b
+    a = b
+    b = next % 1000000
+    i = i + 1",
+    ),
+];
+
+fn parse(source: &str) -> Vec<Statement> {
+    let tokens = Lexer::new(source).tokenize().expect("lexing failed");
+    Parser::new(tokens).parse().expect("parsing failed")
+}
+
+fn main() {
+    println!("=== VM benchmark: {} runs each ===", ITERATIONS);
+    println!("{:<18} {:>12} {:>12} {:>9}", "program", "stack", "register", "speedup");
+
+    for (name, source) in PROGRAMS {
+        let statements = parse(source);
+
+        let mut stack_time = Duration::ZERO;
+        let mut register_time = Duration::ZERO;
+
+        for _ in 0..ITERATIONS {
+            let chunk = Compiler::new().compile(&statements).expect("compilation failed");
+            let start = Instant::now();
+            VM::new().interpret(chunk).expect("stack VM failed");
+            stack_time += start.elapsed();
+
+            let chunk = RegisterCompiler::new().compile(&statements).expect("compilation failed");
+            let start = Instant::now();
+            RegisterVM::new().interpret(&chunk).expect("register VM failed");
+            register_time += start.elapsed();
+        }
+
+        let per_run = |total: Duration| total.as_secs_f64() * 1000.0 / ITERATIONS as f64;
+        println!(
+            "{:<18} {:>10.3}ms {:>10.3}ms {:>8.2}x",
+            name,
+            per_run(stack_time),
+            per_run(register_time),
+            stack_time.as_secs_f64() / register_time.as_secs_f64()
+        );
+    }
+}
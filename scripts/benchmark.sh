@@ -45,4 +45,16 @@ echo "----------------------------------------"
 # Cleanup
 rm -f benchmark_temp.luma
 
+echo
+echo "Running compile-time benchmark..."
+echo "----------------------------------------"
+cargo bench --bench compile
+echo "----------------------------------------"
+
+echo
+echo "Running stack vs register VM benchmark..."
+echo "----------------------------------------"
+cargo bench --bench vm
+echo "----------------------------------------"
+
 echo "Benchmark completed!"
\ No newline at end of file
@@ -92,7 +92,7 @@ impl PerformanceAnalyzer {
     #[allow(dead_code)]
     pub fn get_hotspots(&self) -> Vec<&HotSpot> {
         let mut hotspots: Vec<_> = self.hotspots.values().collect();
-        hotspots.sort_by(|a, b| b.execution_count.cmp(&a.execution_count));
+        hotspots.sort_by_key(|h| std::cmp::Reverse(h.execution_count));
         hotspots
     }
 
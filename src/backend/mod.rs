@@ -1,4 +1,5 @@
 pub mod vm;
+pub mod register;
 pub mod jit;
 
 // Core VM functionality
@@ -6,4 +7,26 @@ pub mod jit;
 pub use vm::*;
 // JIT components (for future use)
 #[allow(unused_imports)]
-pub use jit::*;
\ No newline at end of file
+pub use jit::*;
+
+/// Which virtual machine executes compiled programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// The bytecode stack machine (`vm::VM`).
+    #[default]
+    Stack,
+    /// The three-address register machine (`register::RegisterVM`).
+    Register,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "stack" => Ok(Backend::Stack),
+            "register" => Ok(Backend::Register),
+            other => Err(format!("unknown backend '{}' (expected 'stack' or 'register')", other)),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::backend::register::{Instruction, Register, RegisterChunk};
+use crate::frontend::ast::*;
+use crate::shared::{Chunk, LumaError, Result, Span, Value};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Compiles the AST to three-address code for the register machine.
+///
+/// Registers are allocated like a stack: every expression evaluates into
+/// the next free register, and temporaries are released as soon as the
+/// enclosing expression has consumed them.
+pub struct RegisterCompiler {
+    chunk: RegisterChunk,
+    pool: Chunk, // Only used for its constant deduplication
+    global_index: HashMap<Rc<str>, u32>,
+    next_register: usize,
+}
+
+impl RegisterCompiler {
+    pub fn new() -> Self {
+        Self {
+            chunk: RegisterChunk::default(),
+            pool: Chunk::new(),
+            global_index: HashMap::new(),
+            next_register: 0,
+        }
+    }
+
+    pub fn compile(&mut self, statements: &[Statement]) -> Result<RegisterChunk> {
+        for (i, statement) in statements.iter().enumerate() {
+            match &statement.kind {
+                // A trailing `show` returns its value as the program's result
+                StatementKind::Show(expression) if i + 1 == statements.len() => {
+                    let value = self.expression(expression)?;
+                    self.emit(Instruction::Print { src: value }, statement.span);
+                    self.emit(Instruction::Return { src: value }, statement.span);
+                    return Ok(self.finish());
+                }
+                _ => self.statement(statement)?,
+            }
+        }
+
+        let span = statements.last().map(|s| s.span).unwrap_or_default();
+        self.emit(Instruction::ReturnNil, span);
+        Ok(self.finish())
+    }
+
+    fn finish(&mut self) -> RegisterChunk {
+        let mut chunk = std::mem::take(&mut self.chunk);
+        chunk.constants = std::mem::take(&mut self.pool.constants);
+        self.pool = Chunk::new();
+        self.global_index.clear();
+        chunk
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<()> {
+        let span = statement.span;
+        let mark = self.next_register;
+
+        match &statement.kind {
+            StatementKind::Assignment { name, value } => {
+                let value = self.expression(value)?;
+                let global = self.global(name);
+                self.emit(Instruction::SetGlobal { global, src: value }, span);
+            }
+
+            StatementKind::Show(expression) => {
+                let value = self.expression(expression)?;
+                self.emit(Instruction::Print { src: value }, span);
+            }
+
+            StatementKind::If { condition, then_branch, else_ifs, else_branch } => {
+                let mut end_jumps = Vec::new();
+
+                let branches = std::iter::once((condition, then_branch))
+                    .chain(else_ifs.iter().map(|(condition, body)| (condition, body)));
+                for (condition, body) in branches {
+                    let cond = self.expression(condition)?;
+                    let skip = self.emit(Instruction::JumpIfFalse { cond, target: 0 }, condition.span);
+                    self.next_register = mark;
+
+                    self.block(body)?;
+                    end_jumps.push(self.emit(Instruction::Jump { target: 0 }, span));
+                    self.patch_jump(skip);
+                }
+
+                if let Some(else_branch) = else_branch {
+                    self.block(else_branch)?;
+                }
+                for jump in end_jumps {
+                    self.patch_jump(jump);
+                }
+            }
+
+            StatementKind::While { condition, body } => {
+                let loop_start = self.here();
+                let cond = self.expression(condition)?;
+                let exit = self.emit(Instruction::JumpIfFalse { cond, target: 0 }, condition.span);
+                self.next_register = mark;
+
+                self.block(body)?;
+                self.emit(Instruction::Loop { target: loop_start }, span);
+                self.patch_jump(exit);
+            }
+
+            StatementKind::Repeat { count, body } => {
+                // The bound, counter and step stay in registers for the
+                // whole loop; the body allocates above them
+                let limit = self.expression(count)?;
+                let counter = self.load_number(0.0, span)?;
+                let one = self.load_number(1.0, span)?;
+
+                let loop_start = self.here();
+                let cond = self.allocate(span)?;
+                self.emit(Instruction::Less { dst: cond, lhs: counter, rhs: limit }, span);
+                let exit = self.emit(Instruction::JumpIfFalse { cond, target: 0 }, span);
+                self.next_register = cond as usize;
+
+                self.block(body)?;
+                self.emit(Instruction::Add { dst: counter, lhs: counter, rhs: one }, span);
+                self.emit(Instruction::Loop { target: loop_start }, span);
+                self.patch_jump(exit);
+            }
+        }
+
+        self.next_register = mark;
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    // Evaluate an expression into a newly allocated register
+    fn expression(&mut self, expression: &Expression) -> Result<Register> {
+        let span = expression.span;
+        match &expression.kind {
+            ExpressionKind::Literal(value) => self.load_number(*value, span),
+
+            ExpressionKind::StringLiteral(value) => {
+                let constant = self.pool.intern_string(value) as u32;
+                let dst = self.allocate(span)?;
+                self.emit(Instruction::LoadConst { dst, constant }, span);
+                Ok(dst)
+            }
+
+            ExpressionKind::BooleanLiteral(value) => {
+                let dst = self.allocate(span)?;
+                self.emit(Instruction::LoadBool { dst, value: *value }, span);
+                Ok(dst)
+            }
+
+            ExpressionKind::Identifier(name) => {
+                let global = self.global(name);
+                let dst = self.allocate(span)?;
+                self.emit(Instruction::GetGlobal { dst, global }, span);
+                Ok(dst)
+            }
+
+            ExpressionKind::BinaryOp { left, operator, right } => {
+                let lhs = self.expression(left)?;
+                let rhs = self.expression(right)?;
+                let dst = lhs;
+
+                let instruction = match operator {
+                    BinaryOperator::Add => Instruction::Add { dst, lhs, rhs },
+                    BinaryOperator::Subtract => Instruction::Subtract { dst, lhs, rhs },
+                    BinaryOperator::Multiply => Instruction::Multiply { dst, lhs, rhs },
+                    BinaryOperator::Divide => Instruction::Divide { dst, lhs, rhs },
+                    BinaryOperator::Modulo => Instruction::Modulo { dst, lhs, rhs },
+                    BinaryOperator::Equal => Instruction::Equal { dst, lhs, rhs },
+                    BinaryOperator::NotEqual => Instruction::NotEqual { dst, lhs, rhs },
+                    BinaryOperator::Greater | BinaryOperator::GreaterThan => Instruction::Greater { dst, lhs, rhs },
+                    BinaryOperator::GreaterEqual => Instruction::GreaterEqual { dst, lhs, rhs },
+                    BinaryOperator::Less | BinaryOperator::LessThan => Instruction::Less { dst, lhs, rhs },
+                    BinaryOperator::LessEqual => Instruction::LessEqual { dst, lhs, rhs },
+                    BinaryOperator::And => Instruction::And { dst, lhs, rhs },
+                    BinaryOperator::Or => Instruction::Or { dst, lhs, rhs },
+                };
+                self.emit(instruction, span);
+                self.next_register = rhs as usize;
+                Ok(dst)
+            }
+
+            ExpressionKind::UnaryOp { operator, operand } => {
+                let src = self.expression(operand)?;
+                let instruction = match operator {
+                    UnaryOperator::Minus => Instruction::Negate { dst: src, src },
+                    UnaryOperator::Not => Instruction::Not { dst: src, src },
+                };
+                self.emit(instruction, span);
+                Ok(src)
+            }
+
+            ExpressionKind::FunctionCall { .. } => {
+                Err(LumaError::compile_error("Function calls not implemented in JIT-VM".to_string(), span))
+            }
+        }
+    }
+
+    fn load_number(&mut self, value: f64, span: Span) -> Result<Register> {
+        let constant = self.pool.add_constant(Value::Number(value)) as u32;
+        let dst = self.allocate(span)?;
+        self.emit(Instruction::LoadConst { dst, constant }, span);
+        Ok(dst)
+    }
+
+    fn allocate(&mut self, span: Span) -> Result<Register> {
+        if self.next_register > Register::MAX as usize {
+            return Err(LumaError::compile_error(
+                "Expression too complex: ran out of registers".to_string(),
+                span,
+            ));
+        }
+        let register = self.next_register as Register;
+        self.next_register += 1;
+        self.chunk.register_count = self.chunk.register_count.max(self.next_register);
+        Ok(register)
+    }
+
+    fn global(&mut self, name: &str) -> u32 {
+        if let Some(&index) = self.global_index.get(name) {
+            return index;
+        }
+        let name: Rc<str> = name.into();
+        let index = self.chunk.globals.len() as u32;
+        self.chunk.globals.push(name.clone());
+        self.global_index.insert(name, index);
+        index
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Span) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.spans.push(span);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    // Point a forward jump at the next instruction to be emitted
+    fn patch_jump(&mut self, jump: usize) {
+        let here = self.here();
+        match &mut self.chunk.code[jump] {
+            Instruction::Jump { target } | Instruction::JumpIfFalse { target, .. } => *target = here,
+            other => unreachable!("patching non-jump instruction {:?}", other),
+        }
+    }
+}
+
+impl Default for RegisterCompiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
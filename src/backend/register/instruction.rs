@@ -0,0 +1,148 @@
+use crate::shared::{Span, Value};
+use std::fmt;
+use std::rc::Rc;
+
+/// Index of a register in the current frame.
+pub type Register = u8;
+
+/// Three-address instructions for the register machine.
+///
+/// Operands name registers directly, so `a + b * c` runs as three
+/// instructions instead of the stack machine's five pushes and pops.
+/// Jump targets are absolute instruction indexes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // Loads
+    LoadConst { dst: Register, constant: u32 },
+    LoadBool { dst: Register, value: bool },
+
+    // Globals, by index into the chunk's global name table
+    GetGlobal { dst: Register, global: u32 },
+    SetGlobal { global: u32, src: Register },
+
+    // Arithmetic
+    Add { dst: Register, lhs: Register, rhs: Register },
+    Subtract { dst: Register, lhs: Register, rhs: Register },
+    Multiply { dst: Register, lhs: Register, rhs: Register },
+    Divide { dst: Register, lhs: Register, rhs: Register },
+    Modulo { dst: Register, lhs: Register, rhs: Register },
+    Negate { dst: Register, src: Register },
+
+    // Comparison
+    Equal { dst: Register, lhs: Register, rhs: Register },
+    NotEqual { dst: Register, lhs: Register, rhs: Register },
+    Greater { dst: Register, lhs: Register, rhs: Register },
+    GreaterEqual { dst: Register, lhs: Register, rhs: Register },
+    Less { dst: Register, lhs: Register, rhs: Register },
+    LessEqual { dst: Register, lhs: Register, rhs: Register },
+
+    // Logical
+    Not { dst: Register, src: Register },
+    And { dst: Register, lhs: Register, rhs: Register },
+    Or { dst: Register, lhs: Register, rhs: Register },
+
+    // Output
+    Print { src: Register },
+
+    // Control flow
+    Jump { target: u32 },
+    JumpIfFalse { cond: Register, target: u32 },
+    Loop { target: u32 }, // Backward jump closing a loop body
+    Return { src: Register },
+    ReturnNil,
+}
+
+/// Compiled program for the register machine.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegisterChunk {
+    pub code: Vec<Instruction>,
+    pub spans: Vec<Span>, // Source span of each instruction
+    pub constants: Vec<Value>,
+    pub globals: Vec<Rc<str>>, // Names referenced by GetGlobal/SetGlobal
+    pub register_count: usize,
+}
+
+impl RegisterChunk {
+    pub fn get_span(&self, instruction: usize) -> Span {
+        self.spans.get(instruction).copied().unwrap_or_default()
+    }
+
+    /// Render the chunk as text, one instruction per line.
+    ///
+    /// ```text
+    /// == script.luma (3 registers) ==
+    /// 0000     1  LOADK    r0, k0          ; 10
+    /// 0001     |  SETG     g0, r0          ; x
+    /// ```
+    #[allow(dead_code)]
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut result = format!("== {} ({} registers) ==\n", name, self.register_count);
+        for (index, instruction) in self.code.iter().enumerate() {
+            let line = self.get_span(index).line;
+            if index > 0 && self.get_span(index - 1).line == line {
+                result.push_str(&format!("{:04}     |  ", index));
+            } else {
+                result.push_str(&format!("{:04}  {:4}  ", index, line));
+            }
+
+            let text = instruction.to_string();
+            match self.describe(instruction) {
+                Some(detail) => result.push_str(&format!("{:<24}; {}\n", text, detail)),
+                None => result.push_str(&format!("{}\n", text)),
+            }
+        }
+        result
+    }
+
+    // Constant value or global name an instruction refers to
+    fn describe(&self, instruction: &Instruction) -> Option<String> {
+        match *instruction {
+            Instruction::LoadConst { constant, .. } => self.constants.get(constant as usize).map(|value| match value {
+                Value::String(s) => format!("{:?}", s),
+                value => value.to_string(),
+            }),
+            Instruction::GetGlobal { global, .. } | Instruction::SetGlobal { global, .. } => {
+                self.globals.get(global as usize).map(|name| name.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Instruction::*;
+
+        let three = |f: &mut fmt::Formatter, name: &str, dst: &Register, lhs: &Register, rhs: &Register| {
+            write!(f, "{:<8} r{}, r{}, r{}", name, dst, lhs, rhs)
+        };
+
+        match self {
+            LoadConst { dst, constant } => write!(f, "{:<8} r{}, k{}", "LOADK", dst, constant),
+            LoadBool { dst, value } => write!(f, "{:<8} r{}, {}", "LOADBOOL", dst, value),
+            GetGlobal { dst, global } => write!(f, "{:<8} r{}, g{}", "GETG", dst, global),
+            SetGlobal { global, src } => write!(f, "{:<8} g{}, r{}", "SETG", global, src),
+            Add { dst, lhs, rhs } => three(f, "ADD", dst, lhs, rhs),
+            Subtract { dst, lhs, rhs } => three(f, "SUB", dst, lhs, rhs),
+            Multiply { dst, lhs, rhs } => three(f, "MUL", dst, lhs, rhs),
+            Divide { dst, lhs, rhs } => three(f, "DIV", dst, lhs, rhs),
+            Modulo { dst, lhs, rhs } => three(f, "MOD", dst, lhs, rhs),
+            Negate { dst, src } => write!(f, "{:<8} r{}, r{}", "NEG", dst, src),
+            Equal { dst, lhs, rhs } => three(f, "EQ", dst, lhs, rhs),
+            NotEqual { dst, lhs, rhs } => three(f, "NE", dst, lhs, rhs),
+            Greater { dst, lhs, rhs } => three(f, "GT", dst, lhs, rhs),
+            GreaterEqual { dst, lhs, rhs } => three(f, "GE", dst, lhs, rhs),
+            Less { dst, lhs, rhs } => three(f, "LT", dst, lhs, rhs),
+            LessEqual { dst, lhs, rhs } => three(f, "LE", dst, lhs, rhs),
+            Not { dst, src } => write!(f, "{:<8} r{}, r{}", "NOT", dst, src),
+            And { dst, lhs, rhs } => three(f, "AND", dst, lhs, rhs),
+            Or { dst, lhs, rhs } => three(f, "OR", dst, lhs, rhs),
+            Print { src } => write!(f, "{:<8} r{}", "PRINT", src),
+            Jump { target } => write!(f, "{:<8} {:04}", "JMP", target),
+            JumpIfFalse { cond, target } => write!(f, "{:<8} r{}, {:04}", "JMPF", cond, target),
+            Loop { target } => write!(f, "{:<8} {:04}", "LOOP", target),
+            Return { src } => write!(f, "{:<8} r{}", "RET", src),
+            ReturnNil => write!(f, "RETNIL"),
+        }
+    }
+}
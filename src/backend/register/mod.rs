@@ -0,0 +1,7 @@
+pub mod instruction;
+pub mod compiler;
+pub mod vm;
+
+pub use instruction::*;
+pub use compiler::*;
+pub use vm::*;
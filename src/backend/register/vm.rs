@@ -0,0 +1,227 @@
+use crate::backend::register::{Instruction, RegisterChunk};
+use crate::backend::vm::{ops, Output, StdoutOutput};
+use crate::shared::{LumaError, Result, Value};
+use hashbrown::HashMap;
+use std::rc::Rc;
+
+/// Interpreter for `RegisterChunk`s.
+///
+/// Globals live in numbered slots rather than a name-keyed map. Before a
+/// chunk runs, its global name table is linked to slots once, so each
+/// global access is two array lookups instead of a hash. Slots persist
+/// across `interpret` calls, like the stack VM's globals.
+pub struct RegisterVM {
+    registers: Vec<Value>,
+    slots: Vec<Option<Value>>,
+    slot_names: Vec<Rc<str>>,
+    slot_index: HashMap<Rc<str>, usize>,
+    pc: usize, // Index of the next instruction
+    output: Box<dyn Output>,
+}
+
+impl RegisterVM {
+    pub fn new() -> Self {
+        Self {
+            registers: Vec::new(),
+            slots: Vec::new(),
+            slot_names: Vec::new(),
+            slot_index: HashMap::new(),
+            pc: 0,
+            output: Box::new(StdoutOutput),
+        }
+    }
+
+    pub fn interpret(&mut self, chunk: &RegisterChunk) -> Result<Value> {
+        validate(chunk)?;
+
+        let links: Vec<usize> = chunk.globals.iter().map(|name| self.slot_for(name)).collect();
+        self.registers.clear();
+        self.registers.resize(chunk.register_count, Value::Nil);
+        self.pc = 0;
+
+        self.execute(chunk, &links)
+            .map_err(|e| e.with_span(chunk.get_span(self.pc.saturating_sub(1))))
+    }
+
+    fn execute(&mut self, chunk: &RegisterChunk, links: &[usize]) -> Result<Value> {
+        let code = &chunk.code;
+        let registers = &mut self.registers;
+
+        macro_rules! binary {
+            ($dst:expr, $lhs:expr, $rhs:expr, $op:expr) => {{
+                let result = $op(&registers[$lhs as usize], &registers[$rhs as usize])?;
+                registers[$dst as usize] = result;
+            }};
+        }
+
+        while self.pc < code.len() {
+            let instruction = code[self.pc];
+            self.pc += 1;
+
+            match instruction {
+                Instruction::LoadConst { dst, constant } => {
+                    registers[dst as usize] = chunk.constants[constant as usize].clone();
+                }
+                Instruction::LoadBool { dst, value } => registers[dst as usize] = Value::Boolean(value),
+
+                Instruction::GetGlobal { dst, global } => {
+                    let slot = links[global as usize];
+                    match &self.slots[slot] {
+                        Some(value) => registers[dst as usize] = value.clone(),
+                        None => {
+                            return Err(LumaError::UndefinedVariable {
+                                name: self.slot_names[slot].to_string(),
+                                span: None,
+                            });
+                        }
+                    }
+                }
+                Instruction::SetGlobal { global, src } => {
+                    self.slots[links[global as usize]] = Some(registers[src as usize].clone());
+                }
+
+                Instruction::Add { dst, lhs, rhs } => binary!(dst, lhs, rhs, ops::add),
+                Instruction::Subtract { dst, lhs, rhs } => binary!(dst, lhs, rhs, ops::subtract),
+                Instruction::Multiply { dst, lhs, rhs } => binary!(dst, lhs, rhs, ops::multiply),
+                Instruction::Divide { dst, lhs, rhs } => binary!(dst, lhs, rhs, ops::divide),
+                Instruction::Modulo { dst, lhs, rhs } => binary!(dst, lhs, rhs, ops::modulo),
+                Instruction::Negate { dst, src } => {
+                    registers[dst as usize] = ops::negate(&registers[src as usize])?;
+                }
+
+                Instruction::Equal { dst, lhs, rhs } => {
+                    registers[dst as usize] = Value::Boolean(registers[lhs as usize] == registers[rhs as usize]);
+                }
+                Instruction::NotEqual { dst, lhs, rhs } => {
+                    registers[dst as usize] = Value::Boolean(registers[lhs as usize] != registers[rhs as usize]);
+                }
+                Instruction::Greater { dst, lhs, rhs } => {
+                    binary!(dst, lhs, rhs, |a, b| ops::compare(a, b, ">", |a, b| a > b))
+                }
+                Instruction::GreaterEqual { dst, lhs, rhs } => {
+                    binary!(dst, lhs, rhs, |a, b| ops::compare(a, b, ">=", |a, b| a >= b))
+                }
+                Instruction::Less { dst, lhs, rhs } => {
+                    binary!(dst, lhs, rhs, |a, b| ops::compare(a, b, "<", |a, b| a < b))
+                }
+                Instruction::LessEqual { dst, lhs, rhs } => {
+                    binary!(dst, lhs, rhs, |a, b| ops::compare(a, b, "<=", |a, b| a <= b))
+                }
+
+                Instruction::Not { dst, src } => registers[dst as usize] = ops::not(&registers[src as usize]),
+                Instruction::And { dst, lhs, rhs } => {
+                    registers[dst as usize] = ops::and(&registers[lhs as usize], &registers[rhs as usize]);
+                }
+                Instruction::Or { dst, lhs, rhs } => {
+                    registers[dst as usize] = ops::or(&registers[lhs as usize], &registers[rhs as usize]);
+                }
+
+                Instruction::Print { src } => self.output.write_line(&registers[src as usize].to_string())?,
+
+                Instruction::Jump { target } | Instruction::Loop { target } => self.pc = target as usize,
+                Instruction::JumpIfFalse { cond, target } => {
+                    if !registers[cond as usize].is_truthy() {
+                        self.pc = target as usize;
+                    }
+                }
+                Instruction::Return { src } => return Ok(registers[src as usize].clone()),
+                Instruction::ReturnNil => return Ok(Value::Nil),
+            }
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn slot_for(&mut self, name: &Rc<str>) -> usize {
+        if let Some(&slot) = self.slot_index.get(name) {
+            return slot;
+        }
+        let slot = self.slots.len();
+        self.slots.push(None);
+        self.slot_names.push(name.clone());
+        self.slot_index.insert(name.clone(), slot);
+        slot
+    }
+
+    /// Send `show` output somewhere other than stdout.
+    #[allow(dead_code)]
+    pub fn set_output(&mut self, output: impl Output + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Value of a global, if it has been assigned.
+    #[allow(dead_code)]
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        let slot = *self.slot_index.get(name)?;
+        self.slots[slot].as_ref()
+    }
+
+    /// Names of all currently defined globals.
+    #[allow(dead_code)]
+    pub fn global_names(&self) -> impl Iterator<Item = &str> {
+        self.slot_names
+            .iter()
+            .zip(&self.slots)
+            .filter(|(_, value)| value.is_some())
+            .map(|(name, _)| &**name)
+    }
+}
+
+impl Default for RegisterVM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Check every operand up front so the dispatch loop can index freely
+fn validate(chunk: &RegisterChunk) -> Result<()> {
+    let registers = chunk.register_count;
+    let error = |index: usize, message: String| LumaError::VerifyError { offset: index, message };
+
+    if chunk.spans.len() != chunk.code.len() {
+        return Err(error(0, "span table does not match the code".to_string()));
+    }
+
+    for (index, instruction) in chunk.code.iter().enumerate() {
+        use Instruction::*;
+        let (used, constant, global, target) = match *instruction {
+            LoadConst { dst, constant } => (vec![dst], Some(constant), None, None),
+            LoadBool { dst, .. } => (vec![dst], None, None, None),
+            Negate { dst, src } | Not { dst, src } => (vec![dst, src], None, None, None),
+            GetGlobal { dst, global } => (vec![dst], None, Some(global), None),
+            SetGlobal { global, src } => (vec![src], None, Some(global), None),
+            Add { dst, lhs, rhs }
+            | Subtract { dst, lhs, rhs }
+            | Multiply { dst, lhs, rhs }
+            | Divide { dst, lhs, rhs }
+            | Modulo { dst, lhs, rhs }
+            | Equal { dst, lhs, rhs }
+            | NotEqual { dst, lhs, rhs }
+            | Greater { dst, lhs, rhs }
+            | GreaterEqual { dst, lhs, rhs }
+            | Less { dst, lhs, rhs }
+            | LessEqual { dst, lhs, rhs }
+            | And { dst, lhs, rhs }
+            | Or { dst, lhs, rhs } => (vec![dst, lhs, rhs], None, None, None),
+            Print { src } | Return { src } => (vec![src], None, None, None),
+            Jump { target } | Loop { target } => (vec![], None, None, Some(target)),
+            JumpIfFalse { cond, target } => (vec![cond], None, None, Some(target)),
+            ReturnNil => (vec![], None, None, None),
+        };
+
+        if let Some(register) = used.into_iter().find(|&r| r as usize >= registers) {
+            return Err(error(index, format!("register r{} out of range ({} registers)", register, registers)));
+        }
+        if constant.is_some_and(|k| k as usize >= chunk.constants.len()) {
+            return Err(error(index, format!("constant k{} out of range", constant.unwrap())));
+        }
+        if global.is_some_and(|g| g as usize >= chunk.globals.len()) {
+            return Err(error(index, format!("global g{} out of range", global.unwrap())));
+        }
+        if target.is_some_and(|t| t as usize > chunk.code.len()) {
+            return Err(error(index, format!("jump target {} is outside the chunk", target.unwrap())));
+        }
+    }
+
+    Ok(())
+}
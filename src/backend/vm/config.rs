@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+/// Values the stack has room for before it first has to grow.
+pub const DEFAULT_INITIAL_STACK_SIZE: usize = 256;
+
+/// Most values the stack may hold before execution stops with an overflow.
+pub const DEFAULT_MAX_STACK_SIZE: usize = 64 * 1024;
+
+/// Runtime settings for a `VM`.
+///
+/// ```ignore
+/// let vm = VM::with_config(VmConfig::default().with_profiling(true));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmConfig {
+    /// Count loop iterations and chunk entries for `VM::get_execution_stats`.
+    /// Off by default, since even cheap counting costs something per loop.
+    pub profiling: bool,
+    /// Attribute instructions and wall time to source lines for
+    /// `VM::line_profile`. Costs a counter per instruction and a clock read
+    /// per line change, so it is off by default too.
+    pub line_profiling: bool,
+    /// Record which lines and condition outcomes run, for `VM::coverage`.
+    pub coverage: bool,
+    /// Capacity the value stack starts with.
+    pub initial_stack_size: usize,
+    /// Limit the value stack may grow to.
+    pub max_stack_size: usize,
+    /// Stop with `LumaError::LimitExceeded` after this many instructions.
+    pub max_instructions: Option<u64>,
+    /// Stop with `LumaError::LimitExceeded` once a run takes this long.
+    pub timeout: Option<Duration>,
+    /// Stop with `LumaError::LimitExceeded` once runtime strings take up
+    /// more than this many bytes.
+    pub max_memory: Option<usize>,
+}
+
+impl VmConfig {
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        self
+    }
+
+    pub fn with_line_profiling(mut self, line_profiling: bool) -> Self {
+        self.line_profiling = line_profiling;
+        self
+    }
+
+    pub fn with_coverage(mut self, coverage: bool) -> Self {
+        self.coverage = coverage;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_initial_stack_size(mut self, size: usize) -> Self {
+        self.initial_stack_size = size;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_max_stack_size(mut self, size: usize) -> Self {
+        self.max_stack_size = size;
+        self
+    }
+
+    pub fn with_max_instructions(mut self, limit: Option<u64>) -> Self {
+        self.max_instructions = limit;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_memory(mut self, limit: Option<usize>) -> Self {
+        self.max_memory = limit;
+        self
+    }
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        Self {
+            profiling: false,
+            line_profiling: false,
+            coverage: false,
+            initial_stack_size: DEFAULT_INITIAL_STACK_SIZE,
+            max_stack_size: DEFAULT_MAX_STACK_SIZE,
+            max_instructions: None,
+            timeout: None,
+            max_memory: None,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::backend::vm::OpCode;
+use crate::shared::Chunk;
+use hashbrown::HashMap;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How often one condition (`if`, `else if`, `while` or `repeat`) came out
+/// each way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BranchCoverage {
+    pub line: usize,
+    /// Position among the conditions on the same line, in code order.
+    pub block: usize,
+    /// Times the condition was true, so its body ran.
+    pub taken: u64,
+    /// Times it was false, moving on to the next `else if`, the `else`, or
+    /// past the statement.
+    pub not_taken: u64,
+}
+
+impl BranchCoverage {
+    /// Whether the condition was evaluated at all.
+    pub fn reached(&self) -> bool {
+        self.taken + self.not_taken > 0
+    }
+}
+
+/// Which lines and branches of a run executed. From `VM::coverage`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Every line with code, and how many times it ran.
+    pub lines: BTreeMap<usize, u64>,
+    /// Every condition, in line order.
+    pub branches: Vec<BranchCoverage>,
+}
+
+impl Coverage {
+    /// The coverage as an lcov tracefile (`genhtml`, editor plugins and
+    /// CI services read it) for the script at `path`.
+    ///
+    /// Each condition is an lcov block with two branches: 0 for true and
+    /// 1 for false. A condition that never ran has both counts as `-`.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{}\n", path);
+        for branch in &self.branches {
+            for (index, count) in [branch.taken, branch.not_taken].into_iter().enumerate() {
+                let count = if branch.reached() { count.to_string() } else { "-".to_string() };
+                let _ = writeln!(lcov, "BRDA:{},{},{},{}", branch.line, branch.block, index, count);
+            }
+        }
+        let branches_hit: usize = self.branches.iter().map(|b| (b.taken > 0) as usize + (b.not_taken > 0) as usize).sum();
+        let _ = writeln!(lcov, "BRF:{}\nBRH:{}", self.branches.len() * 2, branches_hit);
+
+        for (line, hits) in &self.lines {
+            let _ = writeln!(lcov, "DA:{},{}", line, hits);
+        }
+        let lines_hit = self.lines.values().filter(|&&hits| hits > 0).count();
+        let _ = writeln!(lcov, "LF:{}\nLH:{}", self.lines.len(), lines_hit);
+        lcov.push_str("end_of_record\n");
+        lcov
+    }
+}
+
+/// Counts executions per instruction and the outcomes of each conditional
+/// jump, for `VmConfig::coverage`.
+#[derive(Debug)]
+pub(crate) struct CoverageRecorder {
+    counts: Vec<u64>, // Executions of the instruction at each offset
+    branches: HashMap<usize, (u64, u64)>, // OpJumpIfFalse offset -> (true, false)
+}
+
+impl CoverageRecorder {
+    pub fn new(code_len: usize) -> Self {
+        Self { counts: vec![0; code_len], branches: HashMap::new() }
+    }
+
+    #[inline]
+    pub fn record(&mut self, offset: usize) {
+        if let Some(count) = self.counts.get_mut(offset) {
+            *count += 1;
+        }
+    }
+
+    /// Note which way the conditional jump at `offset` went.
+    pub fn record_branch(&mut self, offset: usize, condition: bool) {
+        let (taken, not_taken) = self.branches.entry(offset).or_default();
+        if condition {
+            *taken += 1;
+        } else {
+            *not_taken += 1;
+        }
+    }
+
+    pub fn coverage(&self, chunk: &Chunk) -> Coverage {
+        let mut coverage = Coverage::default();
+        let mut blocks: HashMap<usize, usize> = HashMap::new(); // Line -> conditions seen so far
+
+        // Walk instruction by instruction so operand bytes are not counted
+        let mut offset = 0;
+        while offset < chunk.code.len() {
+            let Some(opcode) = OpCode::from_byte(chunk.code[offset]) else { break };
+            let line = chunk.get_span(offset).line;
+            let count = self.counts.get(offset).copied().unwrap_or_default();
+            if line != 0 {
+                let hits = coverage.lines.entry(line).or_default();
+                *hits = (*hits).max(count);
+            }
+            if opcode == OpCode::OpJumpIfFalse {
+                let block = blocks.entry(line).or_default();
+                let (taken, not_taken) = self.branches.get(&offset).copied().unwrap_or_default();
+                coverage.branches.push(BranchCoverage { line, block: *block, taken, not_taken });
+                *block += 1;
+            }
+            offset += 1 + opcode.operand_count();
+        }
+
+        coverage.branches.sort_by_key(|branch| (branch.line, branch.block));
+        coverage
+    }
+}
@@ -0,0 +1,16 @@
+use crate::backend::vm::VM;
+use crate::shared::Result;
+
+/// Called by the VM as execution reaches each new source line.
+///
+/// Install one with `VM::set_debug_hook`. The hook runs before the first
+/// instruction of the line and gets the VM itself, so it can read and
+/// change globals or look at the stack while the program is paused.
+/// Returning an error stops the run with that error.
+///
+/// A line is reported again each time a loop comes back to it, even when
+/// the whole loop sits on one line. Synthesized code with no source line
+/// and plain jumps are not reported.
+pub trait DebugHook {
+    fn on_line(&mut self, vm: &mut VM, line: usize) -> Result<()>;
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[repr(u8)]
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OpCode {
     // Constants and literals
@@ -108,4 +109,21 @@ impl OpCode {
     pub fn to_byte(self) -> u8 {
         self as u8
     }
+
+    /// Number of operand bytes that follow the opcode in the bytecode stream.
+    pub fn operand_count(self) -> usize {
+        match self {
+            OpCode::OpConstant
+            | OpCode::OpDefineGlobal
+            | OpCode::OpGetGlobal
+            | OpCode::OpSetGlobal
+            | OpCode::OpGetLocal
+            | OpCode::OpSetLocal
+            | OpCode::OpJump
+            | OpCode::OpJumpIfFalse
+            | OpCode::OpLoop
+            | OpCode::OpCall => 1,
+            _ => 0,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Asks a running `VM` to stop, from any thread.
+///
+/// Obtained from `VM::interrupt_handle`. The VM checks for a request at
+/// loop back-edges and stops with `LumaError::Interrupted`, leaving its
+/// globals as they were. A request made while the VM is idle stops the
+/// next run at its first safe point unless it is cleared first.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request an interrupt. Only performs an atomic store, so it is safe
+    /// to call from a signal handler.
+    pub fn interrupt(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Withdraw a pending request.
+    pub fn clear(&self) {
+        self.requested.store(false, Ordering::Relaxed);
+    }
+
+    // Consume a pending request
+    pub(crate) fn take(&self) -> bool {
+        self.requested.load(Ordering::Relaxed) && self.requested.swap(false, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::shared::{Limit, LumaError, Result, Value};
+use hashbrown::HashMap;
+use std::rc::Weak;
+
+/// Bytes of string data created at runtime that the VM still holds.
+///
+/// String constants belong to the chunk and are not counted; only the
+/// results of concatenation and other operations that build new strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub current: usize,
+    pub peak: usize,
+}
+
+/// Size of the string table at which `Heap` first looks for strings freed
+/// without a `release`; later sweeps wait for it to double.
+const COLLECT_THRESHOLD: usize = 64;
+
+/// Tracks runtime string allocations against an optional ceiling.
+///
+/// Values are reference counted, so a string is only freed when the VM
+/// lets go of its last reference. `release` is called wherever the VM
+/// discards a value and subtracts a counted string once that was its last
+/// reference. Strings that leave the VM some other way, such as inside an
+/// error the caller later drops, are found by `collect`.
+#[derive(Debug, Default)]
+pub(crate) struct Heap {
+    usage: MemoryUsage,
+    limit: Option<usize>,
+    // Strings counted in `usage` and their lengths, by allocation. The
+    // weak reference keeps each allocation alive, so no other string can
+    // reuse an address while it has an entry.
+    live: HashMap<*const String, (Weak<String>, usize)>,
+    collect_at: usize, // Size of `live` that triggers the next `collect`
+}
+
+impl Heap {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self { limit, collect_at: COLLECT_THRESHOLD, ..Self::default() }
+    }
+
+    pub fn usage(&self) -> MemoryUsage {
+        self.usage
+    }
+
+    /// Count a freshly created value, failing if it would exceed the limit.
+    pub fn allocate(&mut self, value: &Value) -> Result<()> {
+        let Value::String(s) = value else {
+            return Ok(());
+        };
+        if self.live.contains_key(&s.id()) {
+            return Ok(());
+        }
+        if self.live.len() >= self.collect_at {
+            self.collect();
+        }
+        
+        if let Some(limit) = self.limit {
+            if self.usage.current + s.len() > limit {
+                // Strings freed behind our back may still be counted
+                self.collect();
+                if self.usage.current + s.len() > limit {
+                    return Err(LumaError::LimitExceeded { limit: Limit::Memory(limit), span: None });
+                }
+            }
+        }
+        
+        self.live.insert(s.id(), (s.downgrade(), s.len()));
+        self.usage.current += s.len();
+        self.usage.peak = self.usage.peak.max(self.usage.current);
+        Ok(())
+    }
+
+    /// Note that the VM is dropping its reference to `value`.
+    pub fn release(&mut self, value: &Value) {
+        if let Value::String(s) = value {
+            if s.strong_count() == 1 {
+                if let Some((_, len)) = self.live.remove(&s.id()) {
+                    self.usage.current -= len;
+                }
+            }
+        }
+    }
+
+    /// Stop counting strings that were freed without a `release`.
+    pub fn collect(&mut self) {
+        let usage = &mut self.usage;
+        self.live.retain(|_, (string, len)| {
+            let alive = string.strong_count() > 0;
+            if !alive {
+                usage.current -= *len;
+            }
+            alive
+        });
+        self.collect_at = (self.live.len() * 2).max(COLLECT_THRESHOLD);
+    }
+
+    pub fn clear(&mut self) {
+        self.usage = MemoryUsage::default();
+        self.live.clear();
+        self.collect_at = COLLECT_THRESHOLD;
+    }
+}
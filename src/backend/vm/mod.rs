@@ -1,7 +1,31 @@
+#[allow(clippy::module_inception)]
 pub mod vm;
 pub mod stack;
 pub mod instruction;
+pub mod config;
+pub mod verifier;
+pub mod ops;
+pub mod memory;
+pub mod interrupt;
+pub mod output;
+pub mod debug;
+pub mod profile;
+pub mod coverage;
+pub mod tracer;
+pub mod snapshot;
+pub mod replay;
 
 pub use vm::*;
 pub use stack::*;
-pub use instruction::*;
\ No newline at end of file
+pub use instruction::*;
+pub use config::*;
+pub use verifier::*;
+pub use memory::MemoryUsage;
+pub use interrupt::*;
+pub use output::*;
+pub use debug::*;
+pub use profile::*;
+pub use coverage::*;
+pub use tracer::*;
+pub use snapshot::*;
+pub use replay::*;
\ No newline at end of file
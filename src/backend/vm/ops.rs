@@ -0,0 +1,72 @@
+// Value semantics shared by the stack and register machines, so both
+// backends produce the same results and the same errors
+use crate::shared::{LumaError, Result, Value};
+
+// Both operands as numbers, or a type mismatch naming the operator
+fn numbers(op: &'static str, a: &Value, b: &Value) -> Result<(f64, f64)> {
+    match (a.to_number(), b.to_number()) {
+        (Ok(a), Ok(b)) => Ok((a, b)),
+        _ => Err(LumaError::TypeMismatch { op, left: a.type_name(), right: b.type_name(), span: None }),
+    }
+}
+
+/// `+`: concatenation if either side is a string, numeric addition otherwise.
+pub fn add(a: &Value, b: &Value) -> Result<Value> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+        (Value::String(_), _) | (_, Value::String(_)) => Ok(Value::string(format!("{}{}", a, b))),
+        _ => numbers("+", a, b).map(|(a, b)| Value::Number(a + b)),
+    }
+}
+
+pub fn subtract(a: &Value, b: &Value) -> Result<Value> {
+    numbers("-", a, b).map(|(a, b)| Value::Number(a - b))
+}
+
+pub fn multiply(a: &Value, b: &Value) -> Result<Value> {
+    numbers("*", a, b).map(|(a, b)| Value::Number(a * b))
+}
+
+pub fn divide(a: &Value, b: &Value) -> Result<Value> {
+    let (a, b) = numbers("/", a, b)?;
+    if b == 0.0 {
+        return Err(LumaError::DivisionByZero { op: "/", span: None });
+    }
+    Ok(Value::Number(a / b))
+}
+
+pub fn modulo(a: &Value, b: &Value) -> Result<Value> {
+    let (a, b) = numbers("%", a, b)?;
+    if b == 0.0 {
+        return Err(LumaError::DivisionByZero { op: "%", span: None });
+    }
+    Ok(Value::Number(a % b))
+}
+
+pub fn negate(value: &Value) -> Result<Value> {
+    match value.to_number() {
+        Ok(n) => Ok(Value::Number(-n)),
+        Err(_) => Err(LumaError::InvalidOperand { op: "-", operand: value.type_name(), span: None }),
+    }
+}
+
+/// Numeric comparison `op`; both operands must convert to numbers.
+pub fn compare(a: &Value, b: &Value, op: &'static str, test: impl FnOnce(f64, f64) -> bool) -> Result<Value> {
+    numbers(op, a, b).map(|(a, b)| Value::Boolean(test(a, b)))
+}
+
+pub fn not(value: &Value) -> Value {
+    Value::Boolean(!value.is_truthy())
+}
+
+pub fn and(a: &Value, b: &Value) -> Value {
+    Value::Boolean(a.is_truthy() && b.is_truthy())
+}
+
+pub fn or(a: &Value, b: &Value) -> Value {
+    Value::Boolean(a.is_truthy() || b.is_truthy())
+}
+
+pub fn concat(a: &Value, b: &Value) -> Value {
+    Value::string(format!("{}{}", a, b))
+}
@@ -0,0 +1,91 @@
+// Where `show` writes its lines. Both backends print through an `Output`,
+// so embedders can capture program output without spawning a process.
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Destination for the lines a program shows.
+pub trait Output {
+    /// Write one line of program output; `line` has no trailing newline.
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+}
+
+/// Writes to the process's standard output. The default for every VM.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutOutput;
+
+impl Output for StdoutOutput {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{}", line)
+    }
+}
+
+/// Writes to the process's standard error, e.g. for diagnostics that must
+/// not mix with program output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StderrOutput;
+
+impl Output for StderrOutput {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(io::stderr().lock(), "{}", line)
+    }
+}
+
+/// Collects output in memory.
+///
+/// Clones share the same buffer, so keep one to read what a VM wrote:
+///
+/// ```ignore
+/// let buffer = BufferOutput::new();
+/// vm.set_output(buffer.clone());
+/// vm.interpret(chunk)?;
+/// assert_eq!(buffer.contents(), "42\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BufferOutput {
+    buffer: Rc<RefCell<String>>,
+}
+
+#[allow(dead_code)]
+impl BufferOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, one `\n`-terminated line per `show`.
+    pub fn contents(&self) -> String {
+        self.buffer.borrow().clone()
+    }
+
+    /// Return the contents and empty the buffer.
+    pub fn take(&self) -> String {
+        std::mem::take(&mut *self.buffer.borrow_mut())
+    }
+}
+
+impl Output for BufferOutput {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.push_str(line);
+        buffer.push('\n');
+        Ok(())
+    }
+}
+
+/// Hands each line to a closure.
+pub struct CallbackOutput<F: FnMut(&str)> {
+    callback: F,
+}
+
+impl<F: FnMut(&str)> CallbackOutput<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F: FnMut(&str)> Output for CallbackOutput<F> {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        (self.callback)(line);
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::shared::Chunk;
+use hashbrown::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Instructions executed and wall time spent on one source line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineStats {
+    pub line: usize,
+    pub instructions: u64,
+    pub time: Duration,
+}
+
+/// Where a run spent its time, by source line. From `VM::line_profile`.
+#[derive(Debug, Clone, Default)]
+pub struct LineProfile {
+    /// Lines that ran, most instructions first.
+    pub lines: Vec<LineStats>,
+    stacks: Vec<FoldedStack>,
+}
+
+// Instructions run on one line inside one nest of loops
+#[derive(Debug, Clone)]
+struct FoldedStack {
+    loops: Vec<usize>, // Header lines of the enclosing loops, outermost first
+    line: usize,
+    instructions: u64,
+}
+
+impl LineProfile {
+    pub fn total_instructions(&self) -> u64 {
+        self.lines.iter().map(|stats| stats.instructions).sum()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.lines.iter().map(|stats| stats.time).sum()
+    }
+
+    /// A table of the lines that ran, most instructions first, with the
+    /// text of each line taken from `source` (which may be empty).
+    ///
+    /// ```text
+    /// Line profile: 412 instructions in 0.031ms
+    ///   line  instructions       %       time       %  source
+    ///      3           200   48.5%    0.014ms   45.2%  i = i + 1
+    /// ```
+    pub fn report(&self, source: &str) -> String {
+        let source: Vec<&str> = source.lines().collect();
+        let total_instructions = self.total_instructions().max(1) as f64;
+        let total_time = self.total_time().as_secs_f64().max(f64::MIN_POSITIVE);
+
+        let mut report = format!(
+            "Line profile: {} instructions in {:.3}ms\n",
+            self.total_instructions(),
+            self.total_time().as_secs_f64() * 1000.0
+        );
+        report.push_str("  line  instructions       %       time       %  source\n");
+        for stats in &self.lines {
+            let text = stats.line.checked_sub(1).and_then(|index| source.get(index)).map_or("", |text| text.trim());
+            let _ = writeln!(
+                report,
+                "{:>6}  {:>12}  {:>5.1}%  {:>7.3}ms  {:>5.1}%  {}",
+                stats.line,
+                stats.instructions,
+                stats.instructions as f64 / total_instructions * 100.0,
+                stats.time.as_secs_f64() * 1000.0,
+                stats.time.as_secs_f64() / total_time * 100.0,
+                text
+            );
+        }
+        report
+    }
+
+    /// Folded stacks for flamegraph tools (`inferno-flamegraph`,
+    /// `flamegraph.pl`): one `root;loop at line N;line M count` row per
+    /// line and loop nest, weighted by instructions executed.
+    pub fn folded(&self, root: &str) -> String {
+        let mut folded = String::new();
+        for stack in &self.stacks {
+            folded.push_str(root);
+            for header in &stack.loops {
+                let _ = write!(folded, ";loop at line {}", header);
+            }
+            let _ = writeln!(folded, ";line {} {}", stack.line, stack.instructions);
+        }
+        folded
+    }
+}
+
+/// Counts instructions per offset and charges wall time to the line that
+/// was running, for `VmConfig::line_profiling`.
+#[derive(Debug)]
+pub(crate) struct LineProfiler {
+    counts: Vec<u64>, // Executions of the instruction at each offset
+    time: HashMap<usize, Duration>, // Line -> time spent on it
+    line: usize, // Line being timed; 0 before the first
+    since: Instant,
+}
+
+impl LineProfiler {
+    pub fn new(code_len: usize) -> Self {
+        Self { counts: vec![0; code_len], time: HashMap::new(), line: 0, since: Instant::now() }
+    }
+
+    /// Note that the instruction at `offset`, from `line`, is about to run.
+    #[inline]
+    pub fn record(&mut self, offset: usize, line: usize) {
+        if let Some(count) = self.counts.get_mut(offset) {
+            *count += 1;
+        }
+        // Only read the clock when the line changes; generated code (line 0)
+        // counts as part of the line that was running
+        if line != 0 && line != self.line {
+            self.charge(Instant::now());
+            self.line = line;
+        }
+    }
+
+    /// Stop the clock at the end of a run.
+    pub fn finish(&mut self) {
+        self.charge(Instant::now());
+        self.line = 0;
+    }
+
+    fn charge(&mut self, now: Instant) {
+        if self.line != 0 {
+            *self.time.entry(self.line).or_default() += now - self.since;
+        }
+        self.since = now;
+    }
+
+    pub fn profile(&self, chunk: &Chunk) -> LineProfile {
+        let mut lines: HashMap<usize, u64> = HashMap::new();
+        let mut stacks: HashMap<(Vec<usize>, usize), u64> = HashMap::new();
+        for (offset, &count) in self.counts.iter().enumerate().filter(|&(_, &count)| count > 0) {
+            let line = chunk.get_span(offset).line;
+            *lines.entry(line).or_default() += count;
+
+            let loops = chunk
+                .enclosing_loops(offset)
+                .into_iter()
+                .rev()
+                .map(|start| chunk.get_span(start).line)
+                .collect();
+            *stacks.entry((loops, line)).or_default() += count;
+        }
+
+        let mut lines: Vec<_> = lines
+            .into_iter()
+            .map(|(line, instructions)| LineStats {
+                line,
+                instructions,
+                time: self.time.get(&line).copied().unwrap_or_default(),
+            })
+            .collect();
+        lines.sort_by_key(|stats| (std::cmp::Reverse(stats.instructions), stats.line));
+
+        let mut stacks: Vec<_> = stacks
+            .into_iter()
+            .map(|((loops, line), instructions)| FoldedStack { loops, line, instructions })
+            .collect();
+        stacks.sort_by(|a, b| (&a.loops, a.line).cmp(&(&b.loops, b.line)));
+
+        LineProfile { lines, stacks }
+    }
+}
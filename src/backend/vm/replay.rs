@@ -0,0 +1,207 @@
+use crate::backend::vm::snapshot::invalid;
+use crate::backend::vm::VmConfig;
+use crate::shared::bytecode_cache::bounded;
+use crate::shared::{Result, Value};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::time::Duration;
+
+/// Every run log starts with these bytes.
+pub const RUN_LOG_MAGIC: &[u8; 6] = b"LUMAR\0";
+
+/// Bumped whenever the layout of `RunLog` changes.
+pub const RUN_LOG_VERSION: u32 = 1;
+
+/// Something outside the program that changed how a run went.
+///
+/// Either something stopped the run at a safe point, or the host supplied
+/// a value through `VM::host_input`. Luma has no builtins that read stdin,
+/// the clock or a random source yet, so host values only come from
+/// embedders for now. Each event records how many instructions had run,
+/// which is where a replay has to see it too.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    /// An `InterruptHandle` request (Ctrl-C in the REPL or a `--record`
+    /// run) was seen.
+    Interrupt { instruction: u64 },
+    /// The wall-clock timeout had passed.
+    Timeout { instruction: u64 },
+    /// The host answered a request for `source`, e.g. `"stdin"`, `"clock"`
+    /// or `"random"`, with `value`.
+    Host { instruction: u64, source: String, value: Value },
+}
+
+/// Records the inputs of later runs, or feeds recorded ones back in their
+/// place. Install one with `VM::set_input_log`.
+///
+/// While replaying, the VM ignores the clock: a timeout happens exactly
+/// where the recording says and nowhere else. Interrupt requests still
+/// stop a replay, so a runaway one can be cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct InputLog {
+    events: Vec<InputEvent>,
+    replaying: bool,
+    next: usize,
+}
+
+impl InputLog {
+    pub fn record() -> Self {
+        Self::default()
+    }
+
+    pub fn replay(events: Vec<InputEvent>) -> Self {
+        Self { events, replaying: true, next: 0 }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// The inputs recorded so far, or while replaying the ones replayed so
+    /// far.
+    pub fn events(&self) -> &[InputEvent] {
+        if self.replaying {
+            &self.events[..self.next]
+        } else {
+            &self.events
+        }
+    }
+
+    pub(crate) fn record_input(&mut self, event: InputEvent) {
+        if !self.replaying {
+            self.events.push(event);
+        }
+    }
+
+    // Whether the recorded run received `event` at this point
+    pub(crate) fn replay_input(&mut self, event: InputEvent) -> bool {
+        let due = self.replaying && self.events.get(self.next) == Some(&event);
+        if due {
+            self.next += 1;
+        }
+        due
+    }
+
+    // The value the recorded run got from `source` at this point, if it
+    // asked for one
+    #[allow(dead_code)]
+    pub(crate) fn replay_host_input(&mut self, instruction: u64, source: &str) -> Option<Value> {
+        match self.events.get(self.next) {
+            Some(InputEvent::Host { instruction: at, source: from, value })
+                if self.replaying && *at == instruction && from == source =>
+            {
+                let value = value.clone();
+                self.next += 1;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Everything needed to run a script again exactly as a user saw it: the
+/// source, the settings that affect the result, the inputs it received,
+/// and what it produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunLog {
+    /// Path the script was run from, for messages.
+    pub script: String,
+    pub source: String,
+    pub max_instructions: Option<u64>,
+    pub timeout: Option<Duration>,
+    pub max_memory: Option<usize>,
+    pub inputs: Vec<InputEvent>,
+    /// Everything the program showed, one `\n`-terminated line per `show`.
+    pub output: String,
+    /// The error the run stopped with, as displayed.
+    pub error: Option<String>,
+}
+
+impl RunLog {
+    /// A log for a run of `source` with `config`, with nothing recorded yet.
+    pub fn new(script: &str, source: &str, config: &VmConfig) -> Self {
+        Self {
+            script: script.to_string(),
+            source: source.to_string(),
+            max_instructions: config.max_instructions,
+            timeout: config.timeout,
+            max_memory: config.max_memory,
+            inputs: Vec::new(),
+            output: String::new(),
+            error: None,
+        }
+    }
+
+    /// Settings for replaying the run.
+    pub fn config(&self) -> VmConfig {
+        VmConfig::default()
+            .with_max_instructions(self.max_instructions)
+            .with_timeout(self.timeout)
+            .with_max_memory(self.max_memory)
+    }
+
+    /// Describe the first way `replayed` differs from this recording, or
+    /// return `None` when it produced the same bytes and error.
+    pub fn divergence(&self, replayed: &RunLog) -> Option<String> {
+        if replayed.output != self.output {
+            // The outputs differ, so some pair of lines must
+            let mut expected = self.output.split_inclusive('\n');
+            let mut actual = replayed.output.split_inclusive('\n');
+            let mut line = 1;
+            loop {
+                let (expected, actual) = (expected.next(), actual.next());
+                if expected != actual {
+                    let show = |text: Option<&str>| text.map_or("nothing".to_string(), |text| format!("{:?}", text));
+                    return Some(format!(
+                        "output line {} differs: recorded {}, replayed {}",
+                        line,
+                        show(expected),
+                        show(actual)
+                    ));
+                }
+                line += 1;
+            }
+        }
+        if replayed.error != self.error {
+            let show = |error: &Option<String>| error.clone().unwrap_or_else(|| "success".to_string());
+            return Some(format!(
+                "recorded run ended with {}, replay ended with {}",
+                show(&self.error),
+                show(&replayed.error)
+            ));
+        }
+        if replayed.inputs != self.inputs {
+            return Some(format!(
+                "replay used {} of the {} recorded inputs",
+                replayed.inputs.len(),
+                self.inputs.len()
+            ));
+        }
+        None
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = RUN_LOG_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &RUN_LOG_VERSION)?;
+        bincode::serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(bytes);
+        let mut magic = [0u8; RUN_LOG_MAGIC.len()];
+        if reader.read_exact(&mut magic).is_err() || &magic != RUN_LOG_MAGIC {
+            return Err(invalid("not a Luma run log".to_string()));
+        }
+
+        let version: u32 = bounded(bytes).deserialize_from(&mut reader)?;
+        if version != RUN_LOG_VERSION {
+            return Err(invalid(format!(
+                "unsupported run log format version {} (expected {})",
+                version, RUN_LOG_VERSION
+            )));
+        }
+        Ok(bounded(bytes).deserialize_from(&mut reader)?)
+    }
+}
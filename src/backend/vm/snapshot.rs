@@ -0,0 +1,64 @@
+use crate::shared::bytecode_cache::bounded;
+use crate::shared::{Chunk, LumaError, Result, Value};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+
+/// Every snapshot starts with these bytes.
+pub const SNAPSHOT_MAGIC: &[u8; 6] = b"LUMAS\0";
+
+/// Bumped whenever the layout of `VmSnapshot` changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// VM state saved by `VM::snapshot` and loaded by `VM::restore`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VmSnapshot {
+    /// Every global, sorted by name.
+    pub globals: Vec<(String, Value)>,
+    /// The run in progress, when the snapshot was taken while paused in a
+    /// debug hook.
+    pub paused: Option<PausedRun>,
+}
+
+/// A run stopped between two instructions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PausedRun {
+    pub chunk: Chunk,
+    /// Offset of the next instruction to execute.
+    pub ip: usize,
+    /// The value stack, bottom first.
+    pub stack: Vec<Value>,
+}
+
+impl VmSnapshot {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &SNAPSHOT_VERSION)?;
+        bincode::serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    /// Decode a snapshot, rejecting other format versions since their
+    /// layout cannot be trusted.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Cursor::new(bytes);
+        let mut magic = [0u8; SNAPSHOT_MAGIC.len()];
+        if reader.read_exact(&mut magic).is_err() || &magic != SNAPSHOT_MAGIC {
+            return Err(invalid("not a Luma VM snapshot".to_string()));
+        }
+
+        let version: u32 = bounded(bytes).deserialize_from(&mut reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid(format!(
+                "unsupported snapshot format version {} (expected {})",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+        Ok(bounded(bytes).deserialize_from(&mut reader)?)
+    }
+}
+
+// A file that is not in the expected format
+pub(super) fn invalid(message: String) -> LumaError {
+    LumaError::SerializationError(Box::new(bincode::ErrorKind::Custom(message)))
+}
@@ -1,27 +1,38 @@
+use crate::backend::vm::{DEFAULT_INITIAL_STACK_SIZE, DEFAULT_MAX_STACK_SIZE};
 use crate::shared::Value;
+use std::fmt;
 
-const STACK_MAX: usize = 256;
-
+/// Value stack that starts small and grows on demand up to `max_size`.
 #[derive(Debug)]
 pub struct Stack {
     values: Vec<Value>,
+    max_size: usize,
 }
 
 impl Stack {
     pub fn new() -> Self {
+        Self::with_limits(DEFAULT_INITIAL_STACK_SIZE, DEFAULT_MAX_STACK_SIZE)
+    }
+
+    pub fn with_limits(initial_size: usize, max_size: usize) -> Self {
         Self {
-            values: Vec::with_capacity(STACK_MAX),
+            values: Vec::with_capacity(initial_size.min(max_size)),
+            max_size,
         }
     }
 
     pub fn push(&mut self, value: Value) -> Result<(), String> {
-        if self.values.len() >= STACK_MAX {
-            return Err("Stack overflow".to_string());
+        if self.values.len() >= self.max_size {
+            return Err(format!("Stack overflow: more than {} values", self.max_size));
         }
         self.values.push(value);
         Ok(())
     }
 
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
     pub fn pop(&mut self) -> Result<Value, String> {
         self.values.pop().ok_or_else(|| "Stack underflow".to_string())
     }
@@ -65,14 +76,25 @@ impl Stack {
         }
     }
 
+    /// The values on the stack, bottom first.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
     // For debugging
     #[allow(dead_code)]
     pub fn print_stack(&self) {
-        print!("          ");
+        println!("          {}", self);
+    }
+}
+
+// One `[ value ]` cell per slot, bottom first
+impl fmt::Display for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for value in &self.values {
-            print!("[ {} ]", value);
+            write!(f, "[ {} ]", value)?;
         }
-        println!();
+        Ok(())
     }
 }
 
@@ -0,0 +1,42 @@
+use crate::backend::vm::{Output, Stack};
+use crate::shared::{Chunk, Result};
+use std::ops::RangeInclusive;
+
+/// Writes a line for every instruction the VM executes, without changing
+/// the bytecode being run.
+///
+/// Each line has the offset, the source span, the instruction as the
+/// disassembler shows it, and the value stack just before it runs:
+///
+/// ```text
+/// 0007  2:8+1     OpConstant           2  0          [ 1 ]
+/// ```
+///
+/// Install one with `VM::set_tracer`.
+pub struct InstructionTracer {
+    output: Box<dyn Output>,
+    lines: Option<RangeInclusive<usize>>,
+}
+
+impl InstructionTracer {
+    pub fn new(output: impl Output + 'static) -> Self {
+        Self { output: Box::new(output), lines: None }
+    }
+
+    /// Only trace instructions compiled from source lines in `lines`.
+    pub fn with_lines(mut self, lines: RangeInclusive<usize>) -> Self {
+        self.lines = Some(lines);
+        self
+    }
+
+    pub(crate) fn trace(&mut self, chunk: &Chunk, offset: usize, stack: &Stack) -> Result<()> {
+        let span = chunk.get_span(offset);
+        if self.lines.as_ref().is_some_and(|lines| !lines.contains(&span.line)) {
+            return Ok(());
+        }
+        let (instruction, _) = chunk.describe_instruction(offset);
+        let line = format!("{:04}  {:<10}{:<36} {}", offset, format!("{}+{}", span, span.length), instruction, stack);
+        self.output.write_line(line.trim_end())?;
+        Ok(())
+    }
+}
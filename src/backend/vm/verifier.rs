@@ -0,0 +1,229 @@
+use crate::backend::vm::OpCode;
+use crate::shared::{Chunk, LumaError, Result, Value};
+
+/// Facts established while verifying a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedChunk {
+    /// Deepest the value stack can get while running the chunk.
+    pub max_stack_depth: usize,
+}
+
+/// Check a chunk before it is executed.
+///
+/// The verifier walks the bytecode once and rejects anything the VM would
+/// otherwise only trip over mid-run: unknown opcodes, truncated operands,
+/// constant indexes outside the pool, global names that are not strings,
+/// jumps that leave the chunk or land inside another instruction, and
+/// control flow that reaches an instruction with different stack depths.
+pub fn verify(chunk: &Chunk) -> Result<VerifiedChunk> {
+    if chunk.lines.len() != chunk.code.len() {
+        return Err(LumaError::VerifyError {
+            offset: 0,
+            message: format!(
+                "line table has {} entries for {} bytes of code",
+                chunk.lines.len(),
+                chunk.code.len()
+            ),
+        });
+    }
+
+    let boundaries = decode_boundaries(chunk)?;
+    Verifier { chunk, boundaries }.check_stack_depths()
+}
+
+// Decode the chunk linearly, validating opcodes and operands, and record
+// which offsets start an instruction
+fn decode_boundaries(chunk: &Chunk) -> Result<Vec<bool>> {
+    let code = &chunk.code;
+    let mut boundaries = vec![false; code.len() + 1];
+    let mut offset = 0;
+
+    while offset < code.len() {
+        boundaries[offset] = true;
+        let opcode = OpCode::from_byte(code[offset])
+            .ok_or_else(|| error(offset, format!("unknown opcode {}", code[offset])))?;
+
+        let next = offset + 1 + opcode.operand_count();
+        if next > code.len() {
+            return Err(error(offset, format!("{:?} is missing its operand", opcode)));
+        }
+
+        match opcode {
+            OpCode::OpConstant => {
+                let index = code[offset + 1] as usize;
+                if index >= chunk.constants.len() {
+                    return Err(error(offset, format!(
+                        "constant index {} out of range (pool has {} constants)",
+                        index,
+                        chunk.constants.len()
+                    )));
+                }
+            }
+            OpCode::OpDefineGlobal | OpCode::OpGetGlobal | OpCode::OpSetGlobal => {
+                let index = code[offset + 1] as usize;
+                match chunk.constants.get(index) {
+                    Some(Value::String(_)) => {}
+                    Some(other) => {
+                        return Err(error(offset, format!(
+                            "{:?} names a global with constant {}, which is a {} not a string",
+                            opcode,
+                            index,
+                            other.type_name()
+                        )));
+                    }
+                    None => {
+                        return Err(error(offset, format!(
+                            "constant index {} out of range (pool has {} constants)",
+                            index,
+                            chunk.constants.len()
+                        )));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        offset = next;
+    }
+
+    // Falling off the end is a valid way to finish, so jumps may target it
+    boundaries[code.len()] = true;
+    Ok(boundaries)
+}
+
+struct Verifier<'a> {
+    chunk: &'a Chunk,
+    boundaries: Vec<bool>,
+}
+
+impl Verifier<'_> {
+    // Abstract interpretation over stack depth: every path into an
+    // instruction must agree on how many values are on the stack
+    fn check_stack_depths(&self) -> Result<VerifiedChunk> {
+        let code = &self.chunk.code;
+        let mut depths: Vec<Option<usize>> = vec![None; code.len() + 1];
+        let mut worklist = vec![(0, 0)];
+        let mut max_stack_depth = 0;
+
+        while let Some((offset, depth)) = worklist.pop() {
+            match depths[offset] {
+                Some(seen) if seen == depth => continue,
+                Some(seen) => {
+                    return Err(error(offset, format!(
+                        "inconsistent stack depth: reached with {} and {} values",
+                        seen, depth
+                    )));
+                }
+                None => depths[offset] = Some(depth),
+            }
+
+            if offset == code.len() {
+                continue;
+            }
+
+            let opcode = OpCode::from_byte(code[offset]).expect("opcodes validated while decoding");
+            let operand = code.get(offset + 1).copied().unwrap_or(0) as usize;
+            let next = offset + 1 + opcode.operand_count();
+
+            let (pops, pushes) = stack_effect(opcode, operand);
+            if depth < pops {
+                return Err(error(offset, format!(
+                    "{:?} needs {} value(s) but the stack holds {}",
+                    opcode, pops, depth
+                )));
+            }
+            let after = depth - pops + pushes;
+            max_stack_depth = max_stack_depth.max(after).max(depth);
+
+            match opcode {
+                OpCode::OpGetLocal | OpCode::OpSetLocal if operand >= depth => {
+                    return Err(error(offset, format!(
+                        "local slot {} out of range (stack holds {})",
+                        operand, depth
+                    )));
+                }
+                OpCode::OpJump => {
+                    worklist.push((self.jump_target(offset, next, operand, true)?, after));
+                }
+                OpCode::OpJumpIfFalse => {
+                    worklist.push((self.jump_target(offset, next, operand, true)?, after));
+                    worklist.push((next, after));
+                }
+                OpCode::OpLoop => {
+                    worklist.push((self.jump_target(offset, next, operand, false)?, after));
+                }
+                // Return ends this path whatever is left on the stack
+                OpCode::OpReturn => {}
+                _ => worklist.push((next, after)),
+            }
+        }
+
+        Ok(VerifiedChunk { max_stack_depth })
+    }
+
+    fn jump_target(&self, offset: usize, next: usize, distance: usize, forward: bool) -> Result<usize> {
+        let target = if forward {
+            Some(next + distance)
+        } else {
+            next.checked_sub(distance)
+        };
+
+        match target {
+            Some(target) if target < self.boundaries.len() && self.boundaries[target] => Ok(target),
+            Some(target) if target < self.boundaries.len() => {
+                Err(error(offset, format!("jump target {} is inside another instruction", target)))
+            }
+            _ => Err(error(offset, format!(
+                "jump target {}{} is outside the chunk",
+                if forward { "+" } else { "-" },
+                distance
+            ))),
+        }
+    }
+}
+
+// Values an instruction pops and pushes
+fn stack_effect(opcode: OpCode, operand: usize) -> (usize, usize) {
+    match opcode {
+        OpCode::OpConstant | OpCode::OpNil | OpCode::OpTrue | OpCode::OpFalse => (0, 1),
+        OpCode::OpGetGlobal | OpCode::OpGetLocal => (0, 1),
+
+        OpCode::OpAdd
+        | OpCode::OpSubtract
+        | OpCode::OpMultiply
+        | OpCode::OpDivide
+        | OpCode::OpModulo
+        | OpCode::OpEqual
+        | OpCode::OpGreater
+        | OpCode::OpLess
+        | OpCode::OpGreaterEqual
+        | OpCode::OpLessEqual
+        | OpCode::OpNotEqual
+        | OpCode::OpAnd
+        | OpCode::OpOr
+        | OpCode::OpConcat => (2, 1),
+
+        // Operate on (or peek at) the top value in place
+        OpCode::OpNegate
+        | OpCode::OpNot
+        | OpCode::OpSetGlobal
+        | OpCode::OpSetLocal
+        | OpCode::OpJumpIfFalse
+        | OpCode::OpPrint => (1, 1),
+
+        OpCode::OpDefineGlobal | OpCode::OpPop => (1, 0),
+        OpCode::OpDup => (1, 2),
+        OpCode::OpSwap => (2, 2),
+        OpCode::OpCall => (operand, 1),
+
+        OpCode::OpJump
+        | OpCode::OpLoop
+        | OpCode::OpReturn
+        | OpCode::OpLoopStart
+        | OpCode::OpLoopEnd => (0, 0),
+    }
+}
+
+fn error(offset: usize, message: String) -> LumaError {
+    LumaError::VerifyError { offset, message }
+}
@@ -1,154 +1,250 @@
-use crate::backend::vm::{OpCode, Stack};
-use crate::shared::{Chunk, Value, LumaError, Result};
+use crate::backend::vm::memory::Heap;
+use crate::backend::vm::profile::LineProfiler;
+use crate::backend::vm::coverage::CoverageRecorder;
+use crate::backend::vm::{ops, verify, Coverage, DebugHook, InputEvent, InputLog, InstructionTracer, InterruptHandle, LineProfile, MemoryUsage, OpCode, Output, PausedRun, Stack, StdoutOutput, VmConfig, VmSnapshot};
+use crate::shared::{Chunk, Limit, Value, LumaError, Result, RuntimeTrace, SharedStr, TraceFrame};
 use hashbrown::HashMap;
 use std::time::Instant;
 
 pub struct VM {
     chunk: Option<Chunk>,
     ip: usize, // Instruction pointer
+    instruction_start: usize, // Offset of the instruction currently executing
     stack: Stack,
-    globals: HashMap<String, Value>,
+    operands: Vec<Value>, // Operands of the instruction that just failed, for its trace
+    call_depth: usize, // Frames below the current one; 0 while running top-level code
+    running: bool, // Inside `run`, e.g. while a debug hook has the program paused
+    resumable: bool, // `restore` loaded a paused run that `resume` can continue
+    globals: HashMap<SharedStr, Value>,
+    heap: Heap,
+    output: Box<dyn Output>,
+    config: VmConfig,
     
-    // Performance monitoring
-    execution_count: HashMap<usize, u64>, // instruction offset -> count
-    hot_threshold: u64,
+    // Execution limits for the current run
+    instructions_executed: u64,
+    deadline: Option<Instant>,
+    interrupt: InterruptHandle,
+    input_log: Option<InputLog>, // Records or replays interrupts, timeouts and host inputs
+    
+    // Debugging
+    debug_hook: Option<Box<dyn DebugHook>>,
+    debug_line: usize, // Last line reported to the hook; 0 before the first
+    debug_line_offset: Option<usize>, // Offset of the last report since the latest back-edge
+    tracer: Option<InstructionTracer>,
+    
+    // Performance monitoring, only updated when profiling is enabled
+    execution_count: HashMap<usize, u64>, // loop header or entry offset -> count
     start_time: Option<Instant>,
+    line_profiler: Option<LineProfiler>, // Only present when line profiling is enabled
+    coverage: Option<CoverageRecorder>, // Only present when coverage is enabled
 }
 
 impl VM {
     pub fn new() -> Self {
+        Self::with_config(VmConfig::default())
+    }
+
+    pub fn with_config(config: VmConfig) -> Self {
         Self {
             chunk: None,
             ip: 0,
-            stack: Stack::new(),
+            instruction_start: 0,
+            stack: Stack::with_limits(config.initial_stack_size, config.max_stack_size),
+            operands: Vec::new(),
+            call_depth: 0,
+            running: false,
+            resumable: false,
             globals: HashMap::new(),
+            heap: Heap::new(config.max_memory),
+            output: Box::new(StdoutOutput),
+            config,
+            instructions_executed: 0,
+            deadline: None,
+            interrupt: InterruptHandle::new(),
+            input_log: None,
+            debug_hook: None,
+            debug_line: 0,
+            debug_line_offset: None,
+            tracer: None,
             execution_count: HashMap::new(),
-            hot_threshold: 1000, // Mark as hot after 1000 executions
             start_time: None,
+            line_profiler: None,
+            coverage: None,
         }
     }
 
+    #[allow(dead_code)]
+    pub fn config(&self) -> &VmConfig {
+        &self.config
+    }
+
     pub fn interpret(&mut self, chunk: Chunk) -> Result<Value> {
+        verify(&chunk)?;
+        let chunk_len = chunk.code.len();
         self.chunk = Some(chunk);
         self.ip = 0;
+        self.resumable = false;
         self.start_time = Some(Instant::now());
+        self.begin_run();
+        if self.config.line_profiling {
+            self.line_profiler = Some(LineProfiler::new(chunk_len));
+        }
+        if self.config.coverage {
+            self.coverage = Some(CoverageRecorder::new(chunk_len));
+        }
+        if self.config.profiling {
+            self.record_execution(0); // Chunk entry
+        }
+        self.run()
+    }
+
+    /// Continue a paused run loaded by `restore`.
+    #[allow(dead_code)]
+    pub fn resume(&mut self) -> Result<Value> {
+        if !self.resumable {
+            return Err(LumaError::runtime_error("there is no paused run to resume"));
+        }
+        self.resumable = false;
+        self.begin_run();
         self.run()
     }
 
+    // Reset the per-run limits and debugger state
+    fn begin_run(&mut self) {
+        self.instructions_executed = 0;
+        self.deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+        self.debug_line = 0;
+        self.debug_line_offset = None;
+    }
+
     fn run(&mut self) -> Result<Value> {
+        self.running = true;
+        let result = self.execute();
+        self.running = false;
+        if let Some(profiler) = &mut self.line_profiler {
+            profiler.finish();
+        }
+        self.heap.collect();
+        
+        // Point any runtime error at the source of the failing instruction
+        // and record what the VM was doing
+        result.map_err(|e| {
+            let trace = self.trace();
+            e.with_span(trace.span).with_trace(trace)
+        })
+    }
+
+    fn execute(&mut self) -> Result<Value> {
         loop {
-            // Performance monitoring
-            *self.execution_count.entry(self.ip).or_insert(0) += 1;
+            self.instruction_start = self.ip;
+            self.instructions_executed += 1;
+            if let (Some(profiler), Some(chunk)) = (&mut self.line_profiler, &self.chunk) {
+                profiler.record(self.instruction_start, chunk.get_span(self.instruction_start).line);
+            }
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record(self.instruction_start);
+            }
+            if self.debug_hook.is_some() {
+                self.report_line()?;
+            }
+            if let (Some(tracer), Some(chunk)) = (&mut self.tracer, &self.chunk) {
+                tracer.trace(chunk, self.instruction_start, &self.stack)?;
+            }
             
             let instruction = self.read_byte()?;
             let opcode = OpCode::from_byte(instruction)
-                .ok_or_else(|| LumaError::RuntimeError(format!("Unknown opcode: {}", instruction)))?;
+                .ok_or_else(|| LumaError::UnsupportedOpcode { opcode: instruction.to_string(), span: None })?;
 
             match opcode {
                 OpCode::OpConstant => {
                     let constant_index = self.read_byte()? as usize;
                     let value = self.get_constant(constant_index)?;
-                    self.stack.push(value).map_err(LumaError::StackError)?;
+                    self.push(value)?;
                 }
                 
                 OpCode::OpNil => {
-                    self.stack.push(Value::Nil).map_err(LumaError::StackError)?;
+                    self.push(Value::Nil)?;
                 }
                 
                 OpCode::OpTrue => {
-                    self.stack.push(Value::Boolean(true)).map_err(LumaError::StackError)?;
+                    self.push(Value::Boolean(true))?;
                 }
                 
                 OpCode::OpFalse => {
-                    self.stack.push(Value::Boolean(false)).map_err(LumaError::StackError)?;
+                    self.push(Value::Boolean(false))?;
                 }
                 
-                OpCode::OpAdd => {
-                    let b = self.stack.pop().map_err(LumaError::StackError)?;
-                    let a = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = self.add_values(a, b)?;
-                    self.stack.push(result).map_err(LumaError::StackError)?;
-                }
-                OpCode::OpSubtract => {
-                    let b = self.stack.pop().map_err(LumaError::StackError)?;
-                    let a = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = self.subtract_values(a, b)?;
-                    self.stack.push(result).map_err(LumaError::StackError)?;
-                }
-                OpCode::OpMultiply => {
-                    let b = self.stack.pop().map_err(LumaError::StackError)?;
-                    let a = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = self.multiply_values(a, b)?;
-                    self.stack.push(result).map_err(LumaError::StackError)?;
-                }
-                OpCode::OpDivide => {
-                    let b = self.stack.pop().map_err(LumaError::StackError)?;
-                    let a = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = self.divide_values(a, b)?;
-                    self.stack.push(result).map_err(LumaError::StackError)?;
-                }
-                OpCode::OpModulo => {
-                    let b = self.stack.pop().map_err(LumaError::StackError)?;
-                    let a = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = self.modulo_values(a, b)?;
-                    self.stack.push(result).map_err(LumaError::StackError)?;
-                }
+                OpCode::OpAdd => self.binary_op(ops::add)?,
+                OpCode::OpSubtract => self.binary_op(ops::subtract)?,
+                OpCode::OpMultiply => self.binary_op(ops::multiply)?,
+                OpCode::OpDivide => self.binary_op(ops::divide)?,
+                OpCode::OpModulo => self.binary_op(ops::modulo)?,
                 
                 OpCode::OpNegate => {
-                    let value = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = self.negate_value(value)?;
-                    self.stack.push(result).map_err(LumaError::StackError)?;
+                    let value = self.stack.pop().map_err(LumaError::stack_error)?;
+                    self.heap.release(&value);
+                    match ops::negate(&value) {
+                        Ok(result) => self.push(result)?,
+                        Err(e) => {
+                            self.operands = vec![value];
+                            return Err(e);
+                        }
+                    }
                 }
                 
                 OpCode::OpEqual => self.binary_op(|a, b| Ok(Value::Boolean(a == b)))?,
-                OpCode::OpGreater => self.comparison_op(|a, b| a > b)?,
-                OpCode::OpLess => self.comparison_op(|a, b| a < b)?,
-                OpCode::OpGreaterEqual => self.comparison_op(|a, b| a >= b)?,
-                OpCode::OpLessEqual => self.comparison_op(|a, b| a <= b)?,
+                OpCode::OpGreater => self.binary_op(|a, b| ops::compare(a, b, ">", |a, b| a > b))?,
+                OpCode::OpLess => self.binary_op(|a, b| ops::compare(a, b, "<", |a, b| a < b))?,
+                OpCode::OpGreaterEqual => self.binary_op(|a, b| ops::compare(a, b, ">=", |a, b| a >= b))?,
+                OpCode::OpLessEqual => self.binary_op(|a, b| ops::compare(a, b, "<=", |a, b| a <= b))?,
                 OpCode::OpNotEqual => self.binary_op(|a, b| Ok(Value::Boolean(a != b)))?,
                 
                 OpCode::OpNot => {
-                    let value = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = Value::Boolean(!value.is_truthy());
-                    self.stack.push(result).map_err(LumaError::StackError)?;
+                    let value = self.stack.pop().map_err(LumaError::stack_error)?;
+                    self.heap.release(&value);
+                    self.push(ops::not(&value))?;
                 }
+                OpCode::OpAnd => self.binary_op(|a, b| Ok(ops::and(a, b)))?,
+                OpCode::OpOr => self.binary_op(|a, b| Ok(ops::or(a, b)))?,
                 
                 OpCode::OpPrint => {
-                    let value = self.stack.peek(0).map_err(LumaError::StackError)?.clone();
-                    println!("{}", value);
+                    let value = self.stack.peek(0).map_err(LumaError::stack_error)?.to_string();
+                    self.output.write_line(&value)?;
                     // Keep the value on stack for potential return
                 }
                 
                 OpCode::OpPop => {
-                    self.stack.pop().map_err(LumaError::StackError)?;
+                    let value = self.stack.pop().map_err(LumaError::stack_error)?;
+                    self.heap.release(&value);
                 }
                 
                 OpCode::OpDefineGlobal => {
                     let name_index = self.read_byte()? as usize;
                     let name = self.get_constant_string(name_index)?;
-                    let value = self.stack.pop().map_err(LumaError::StackError)?;
-                    self.globals.insert(name, value);
+                    let value = self.stack.pop().map_err(LumaError::stack_error)?;
+                    if let Some(old) = self.globals.insert(name, value) {
+                        self.heap.release(&old);
+                    }
                 }
                 
                 OpCode::OpGetGlobal => {
                     let name_index = self.read_byte()? as usize;
                     let name = self.get_constant_string(name_index)?;
-                    let value = self.globals.get(&name)
+                    let value = self.globals.get(&*name)
                         .cloned()
-                        .ok_or_else(|| {
-                            let line = self.get_current_line();
-                            LumaError::RuntimeError(format!("Undefined variable '{}' at line {}", name, line))
-                        })?;
-                    self.stack.push(value).map_err(LumaError::StackError)?;
+                        .ok_or_else(|| LumaError::UndefinedVariable { name: name.to_string(), span: None })?;
+                    self.push(value)?;
                 }
                 
                 OpCode::OpSetGlobal => {
                     let name_index = self.read_byte()? as usize;
                     let name = self.get_constant_string(name_index)?;
-                    let value = self.stack.peek(0).map_err(LumaError::StackError)?.clone();
+                    let value = self.stack.peek(0).map_err(LumaError::stack_error)?.clone();
                     
                     // Allow setting existing or new global variables
-                    self.globals.insert(name, value);
+                    if let Some(old) = self.globals.insert(name, value) {
+                        self.heap.release(&old);
+                    }
                 }
                 
                 OpCode::OpJump => {
@@ -158,8 +254,11 @@ impl VM {
                 
                 OpCode::OpJumpIfFalse => {
                     let offset = self.read_byte()? as usize;
-                    let value = self.stack.peek(0).map_err(LumaError::StackError)?;
-                    if !value.is_truthy() {
+                    let condition = self.stack.peek(0).map_err(LumaError::stack_error)?.is_truthy();
+                    if let Some(coverage) = &mut self.coverage {
+                        coverage.record_branch(self.instruction_start, condition);
+                    }
+                    if !condition {
                         self.ip += offset;
                     }
                 }
@@ -167,24 +266,35 @@ impl VM {
                 OpCode::OpLoop => {
                     let offset = self.read_byte()? as usize;
                     self.ip -= offset;
+                    
+                    // Back-edges are the only place a loop can get hot, so
+                    // counting here is enough to find hot spots
+                    if self.config.profiling {
+                        self.record_execution(self.ip);
+                    }
+                    
+                    // Report the header again next iteration unless the body
+                    // already reported a line of its own, as a `repeat`
+                    // counter update does
+                    if self.debug_line_offset.is_none_or(|offset| offset <= self.ip) {
+                        self.debug_line = 0;
+                    }
+                    self.debug_line_offset = None;
+                    self.check_safe_point()?;
                 }
                 
                 OpCode::OpReturn => {
                     // Return the top value from stack or Nil if empty
                     if !self.stack.is_empty() {
-                        let result = self.stack.pop().map_err(LumaError::StackError)?;
+                        let result = self.stack.pop().map_err(LumaError::stack_error)?;
+                        self.heap.release(&result); // Now owned by the caller
                         return Ok(result);
                     } else {
                         return Ok(Value::Nil);
                     }
                 }
                 
-                OpCode::OpConcat => {
-                    let b = self.stack.pop().map_err(LumaError::StackError)?;
-                    let a = self.stack.pop().map_err(LumaError::StackError)?;
-                    let result = Value::String(format!("{}{}", a.to_string(), b.to_string()));
-                    self.stack.push(result).map_err(LumaError::StackError)?;
-                }
+                OpCode::OpConcat => self.binary_op(|a, b| Ok(ops::concat(a, b)))?,
                 
                 OpCode::OpLoopStart => {
                     // Mark start of potentially hot loop for JIT
@@ -193,17 +303,10 @@ impl VM {
                 
                 OpCode::OpLoopEnd => {
                     // Mark end of potentially hot loop for JIT
-                    // Check if this loop should be JIT compiled
-                    if let Some(count) = self.execution_count.get(&self.ip) {
-                        if *count > self.hot_threshold {
-                            // TODO: Trigger JIT compilation
-                            println!("Hot loop detected at instruction {}", self.ip);
-                        }
-                    }
                 }
                 
                 _ => {
-                    return Err(LumaError::RuntimeError(format!("Unimplemented opcode: {:?}", opcode)));
+                    return Err(LumaError::UnsupportedOpcode { opcode: format!("{:?}", opcode), span: None });
                 }
             }
             
@@ -215,7 +318,9 @@ impl VM {
         
         // If we reach here without return, return the top value or Nil
         if !self.stack.is_empty() {
-            Ok(self.stack.pop().map_err(LumaError::StackError)?)
+            let result = self.stack.pop().map_err(LumaError::stack_error)?;
+            self.heap.release(&result);
+            Ok(result)
         } else {
             Ok(Value::Nil)
         }
@@ -223,7 +328,7 @@ impl VM {
 
     fn read_byte(&mut self) -> Result<u8> {
         if self.ip >= self.get_code_len() {
-            return Err(LumaError::RuntimeError("Instruction pointer out of bounds".into()));
+            return Err(LumaError::runtime_error("Instruction pointer out of bounds"));
         }
         
         let byte = self.get_chunk().code[self.ip];
@@ -234,13 +339,14 @@ impl VM {
     fn get_constant(&self, index: usize) -> Result<Value> {
         self.get_chunk().constants.get(index)
             .cloned()
-            .ok_or_else(|| LumaError::RuntimeError(format!("Constant index {} out of bounds", index)))
+            .ok_or_else(|| LumaError::runtime_error(format!("Constant index {} out of bounds", index)))
     }
 
-    fn get_constant_string(&self, index: usize) -> Result<String> {
-        match self.get_constant(index)? {
-            Value::String(s) => Ok(s),
-            _ => Err(LumaError::RuntimeError("Expected string constant".into())),
+    fn get_constant_string(&self, index: usize) -> Result<SharedStr> {
+        match self.get_chunk().constants.get(index) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            None => Err(LumaError::runtime_error(format!("Constant index {} out of bounds", index))),
+            _ => Err(LumaError::runtime_error("Expected string constant")),
         }
     }
 
@@ -252,116 +358,292 @@ impl VM {
         self.get_chunk().code.len()
     }
 
+    fn push(&mut self, value: Value) -> Result<()> {
+        self.stack.push(value).map_err(|_| LumaError::StackOverflow {
+            limit: self.stack.max_size(),
+            call_depth: self.call_depth,
+            span: None,
+        })
+    }
+
     fn binary_op<F>(&mut self, op: F) -> Result<()>
     where
-        F: FnOnce(Value, Value) -> Result<Value>,
+        F: FnOnce(&Value, &Value) -> Result<Value>,
     {
-        let b = self.stack.pop().map_err(LumaError::StackError)?;
-        let a = self.stack.pop().map_err(LumaError::StackError)?;
-        let result = op(a, b)?;
-        self.stack.push(result).map_err(LumaError::StackError)?;
+        let b = self.stack.pop().map_err(LumaError::stack_error)?;
+        let a = self.stack.pop().map_err(LumaError::stack_error)?;
+        let result = match op(&a, &b) {
+            Ok(result) => result,
+            Err(e) => {
+                // The trace keeps the operands, but the VM no longer holds them
+                self.heap.release(&a);
+                self.heap.release(&b);
+                self.operands = vec![a, b];
+                return Err(e);
+            }
+        };
+        
+        // Drop `a` before releasing `b` so `s + s` frees `s`
+        self.heap.release(&a);
+        drop(a);
+        self.heap.release(&b);
+        
+        self.heap.allocate(&result)?; // Any string result is newly built
+        self.push(result)?;
         Ok(())
     }
 
-    fn comparison_op<F>(&mut self, op: F) -> Result<()>
-    where
-        F: FnOnce(f64, f64) -> bool,
-    {
-        self.binary_op(|a, b| {
-            let a_num = a.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-            let b_num = b.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-            Ok(Value::Boolean(op(a_num, b_num)))
-        })
+    /// Send `show` output somewhere other than stdout.
+    pub fn set_output(&mut self, output: impl Output + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// A handle other threads can use to stop this VM mid-run.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Runtime string memory currently held and the most held at once.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.heap.usage()
+    }
+
+    /// Names of all currently defined globals.
+    pub fn global_names(&self) -> impl Iterator<Item = &str> {
+        self.globals.keys().map(|name| &**name)
+    }
+
+    /// The current value of a global, if it is defined.
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Define or overwrite a global.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        if let Some(old) = self.globals.insert(name.into(), value) {
+            self.heap.release(&old);
+        }
+    }
+
+    /// The value stack as it stands, e.g. while paused in a debug hook.
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    /// Frames below the current one; 0 while running top-level code.
+    pub fn call_depth(&self) -> usize {
+        self.call_depth
+    }
+
+    /// Call `hook` at every new source line of later runs, or stop calling
+    /// one with `None`.
+    pub fn set_debug_hook(&mut self, hook: Option<Box<dyn DebugHook>>) {
+        self.debug_hook = hook;
     }
 
-    fn add_values(&self, a: Value, b: Value) -> Result<Value> {
-        match (&a, &b) {
-            (Value::String(_), _) | (_, Value::String(_)) => {
-                Ok(Value::String(format!("{}{}", a.to_string(), b.to_string())))
+    /// Serialize the globals, plus the chunk, next instruction and stack when
+    /// called from a debug hook while a run is paused.
+    pub fn snapshot(&self) -> Result<Vec<u8>> {
+        let mut globals: Vec<_> = self.globals.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        let paused = match (&self.chunk, self.running) {
+            (Some(chunk), true) => Some(PausedRun {
+                chunk: chunk.clone(),
+                ip: self.instruction_start,
+                stack: self.stack.values().to_vec(),
+            }),
+            _ => None,
+        };
+        VmSnapshot { globals, paused }.encode()
+    }
+
+    /// Replace the globals with those of a snapshot. If it holds a paused
+    /// run, that run is loaded too and `resume` continues it. Call it
+    /// between runs, not from a debug hook.
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.running {
+            return Err(LumaError::runtime_error("cannot restore a snapshot while the VM is running"));
+        }
+        let snapshot = VmSnapshot::decode(bytes)?;
+        if let Some(paused) = &snapshot.paused {
+            verify(&paused.chunk)?;
+            if paused.ip >= paused.chunk.code.len() {
+                return Err(LumaError::runtime_error("snapshot resumes past the end of its chunk"));
             }
-            _ => {
-                let a_num = a.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-                let b_num = b.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-                Ok(Value::Number(a_num + b_num))
+        }
+        
+        self.globals.clear();
+        self.heap.clear();
+        self.stack.clear();
+        for (name, value) in snapshot.globals {
+            self.globals.insert(name.into(), value);
+        }
+        if let Some(paused) = snapshot.paused {
+            for value in paused.stack {
+                self.push(value)?;
             }
+            self.chunk = Some(paused.chunk);
+            self.ip = paused.ip;
+            self.resumable = true;
         }
+        Ok(())
     }
 
-    fn subtract_values(&self, a: Value, b: Value) -> Result<Value> {
-        let a_num = a.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        let b_num = b.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        Ok(Value::Number(a_num - b_num))
+    /// Write every instruction of later runs to `tracer`, or stop with `None`.
+    pub fn set_tracer(&mut self, tracer: Option<InstructionTracer>) {
+        self.tracer = tracer;
     }
 
-    fn multiply_values(&self, a: Value, b: Value) -> Result<Value> {
-        let a_num = a.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        let b_num = b.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        Ok(Value::Number(a_num * b_num))
+    /// Record the interrupts, timeouts and host inputs of later runs, or
+    /// replay recorded ones in their place; `None` goes back to live inputs.
+    pub fn set_input_log(&mut self, log: Option<InputLog>) {
+        self.input_log = log;
     }
 
-    fn divide_values(&self, a: Value, b: Value) -> Result<Value> {
-        let a_num = a.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        let b_num = b.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        
-        if b_num == 0.0 {
-            return Err(LumaError::RuntimeError("Division by zero".into()));
+    pub fn input_log(&self) -> Option<&InputLog> {
+        self.input_log.as_ref()
+    }
+
+    /// Get a value the program cannot compute itself, such as a line of
+    /// stdin, the time or a random number, from `read`. `source` names the
+    /// kind of input. While recording, the log keeps the value; while
+    /// replaying, the recorded value comes back without calling `read`, and
+    /// asking for an input the recording does not have here is an error.
+    #[allow(dead_code)]
+    pub fn host_input(&mut self, source: &str, read: impl FnOnce() -> Value) -> Result<Value> {
+        let instruction = self.instructions_executed;
+        let value = match &mut self.input_log {
+            Some(log) if log.is_replaying() => log.replay_host_input(instruction, source).ok_or_else(|| {
+                LumaError::runtime_error(format!("the recorded run had no {} input at this point", source))
+            })?,
+            _ => read(),
+        };
+        self.record_input(InputEvent::Host { instruction, source: source.to_string(), value: value.clone() });
+        Ok(value)
+    }
+
+    // Tell the debug hook when the instruction about to run starts a new line
+    fn report_line(&mut self) -> Result<()> {
+        let offset = self.instruction_start;
+        let chunk = self.get_chunk();
+        let Some(&byte) = chunk.code.get(offset) else {
+            return Ok(());
+        };
+        if matches!(OpCode::from_byte(byte), Some(OpCode::OpJump | OpCode::OpLoop)) {
+            return Ok(());
+        }
+        let line = chunk.get_span(offset).line;
+        if line == 0 || line == self.debug_line {
+            return Ok(());
         }
+        self.debug_line = line;
+        self.debug_line_offset = Some(offset);
         
-        Ok(Value::Number(a_num / b_num))
+        // Take the hook out so it can borrow the VM
+        let Some(mut hook) = self.debug_hook.take() else {
+            return Ok(());
+        };
+        let result = hook.on_line(self, line);
+        if self.debug_hook.is_none() {
+            self.debug_hook = Some(hook);
+        }
+        result
     }
 
-    fn modulo_values(&self, a: Value, b: Value) -> Result<Value> {
-        let a_num = a.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        let b_num = b.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        
-        if b_num == 0.0 {
-            return Err(LumaError::RuntimeError("Modulo by zero".into()));
+    // Called at safe points: loop back-edges (and calls, once OpCall is
+    // implemented). Straight-line code always reaches one or the end of
+    // the chunk in a bounded number of steps, so nothing else needs a check.
+    fn check_safe_point(&mut self) -> Result<()> {
+        let instruction = self.instructions_executed;
+        if self.interrupt.take() || self.replay_input(InputEvent::Interrupt { instruction }) {
+            self.record_input(InputEvent::Interrupt { instruction });
+            return Err(LumaError::Interrupted { span: None });
         }
-        
-        Ok(Value::Number(a_num % b_num))
+        if let Some(limit) = self.config.max_instructions {
+            if self.instructions_executed > limit {
+                return Err(LumaError::LimitExceeded { limit: Limit::Instructions(limit), span: None });
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.config.timeout) {
+            // A replay takes the clock's answer from the recording
+            let expired = match &self.input_log {
+                Some(log) if log.is_replaying() => self.replay_input(InputEvent::Timeout { instruction }),
+                _ => Instant::now() >= deadline,
+            };
+            if expired {
+                self.record_input(InputEvent::Timeout { instruction });
+                return Err(LumaError::LimitExceeded { limit: Limit::Timeout(timeout), span: None });
+            }
+        }
+        Ok(())
     }
 
-    fn negate_value(&self, value: Value) -> Result<Value> {
-        let num = value.to_number().map_err(|e| LumaError::RuntimeError(e))?;
-        Ok(Value::Number(-num))
+    fn record_input(&mut self, event: InputEvent) {
+        if let Some(log) = &mut self.input_log {
+            log.record_input(event);
+        }
     }
 
+    fn replay_input(&mut self, event: InputEvent) -> bool {
+        self.input_log.as_mut().is_some_and(|log| log.replay_input(event))
+    }
+
+    fn record_execution(&mut self, offset: usize) {
+        *self.execution_count.entry(offset).or_insert(0) += 1;
+    }
+
+    /// Execution counts per loop header (and chunk entry at offset 0), most
+    /// executed first. Empty unless the VM was created with profiling on.
     pub fn get_execution_stats(&self) -> Vec<(usize, u64)> {
         let mut stats: Vec<_> = self.execution_count.iter()
             .map(|(&offset, &count)| (offset, count))
             .collect();
-        stats.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by execution count, descending
+        stats.sort_by_key(|&(_, count)| std::cmp::Reverse(count)); // Sort by execution count, descending
         stats
     }
 
-    fn get_current_line(&self) -> usize {
-        if let Some(chunk) = &self.chunk {
-            // Find the closest line number for current instruction pointer
-            if self.ip < chunk.lines.len() && chunk.lines[self.ip] > 0 {
-                chunk.lines[self.ip]
-            } else {
-                // Look backwards for a valid line number
-                for i in (0..self.ip.min(chunk.lines.len())).rev() {
-                    if chunk.lines[i] > 0 {
-                        return chunk.lines[i];
-                    }
-                }
-                // If no valid line found, estimate based on instruction position
-                (self.ip / 3) + 1  // Rough estimate: ~3 instructions per line
-            }
-        } else {
-            1 // Default to line 1 if no chunk
-        }
+    /// Instructions and wall time per source line for the latest run. `None`
+    /// unless the VM was created with line profiling on.
+    pub fn line_profile(&self) -> Option<LineProfile> {
+        Some(self.line_profiler.as_ref()?.profile(self.chunk.as_ref()?))
+    }
+
+    /// Lines and condition outcomes that ran in the latest run. `None`
+    /// unless the VM was created with coverage on.
+    pub fn coverage(&self) -> Option<Coverage> {
+        Some(self.coverage.as_ref()?.coverage(self.chunk.as_ref()?))
+    }
+
+    // Trace for the instruction currently executing, which just failed
+    fn trace(&mut self) -> RuntimeTrace {
+        let offset = self.instruction_start;
+        let chunk = self.get_chunk();
+        let mut trace = RuntimeTrace::new(chunk.get_span(offset), offset);
+        
+        trace.frames = chunk
+            .enclosing_loops(offset)
+            .into_iter()
+            .map(|start| TraceFrame::Loop { span: chunk.get_span(start) })
+            .collect();
+        
+        trace.values = std::mem::take(&mut self.operands);
+        trace
     }
 
     #[allow(dead_code)]
     pub fn reset(&mut self) {
         self.chunk = None;
         self.ip = 0;
+        self.instruction_start = 0;
         self.stack.clear();
+        self.call_depth = 0;
+        self.resumable = false;
         self.globals.clear();
+        self.heap.clear();
         self.execution_count.clear();
         self.start_time = None;
+        self.line_profiler = None;
+        self.coverage = None;
     }
 }
 
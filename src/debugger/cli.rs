@@ -0,0 +1,159 @@
+use crate::backend::vm::{DebugHook, VM};
+use crate::debugger::{format_value, is_hidden_global, parse_value, Resume, Stepper};
+use crate::shared::{LumaError, Result};
+use std::io::{BufRead, Write};
+
+const PROMPT: &str = "(debug) ";
+
+/// Line-oriented debugger behind `luma debug`.
+///
+/// Reads commands from `input` whenever the program pauses and writes
+/// replies to `output`. It pauses at the first line; end of input lets the
+/// program run to completion.
+pub struct CliDebugger {
+    stepper: Stepper,
+    source: Vec<String>,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+}
+
+impl CliDebugger {
+    pub fn new(source: &str, input: impl BufRead + 'static, output: impl Write + 'static) -> Self {
+        Self {
+            stepper: Stepper::new(Resume::StepInto),
+            source: source.lines().map(str::to_string).collect(),
+            input: Box::new(input),
+            output: Box::new(output),
+        }
+    }
+
+    fn show_line(&mut self, line: usize) -> Result<()> {
+        let text = self.source.get(line - 1).map_or("", |text| text.trim_end());
+        writeln!(self.output, "> {} | {}", line, text)?;
+        Ok(())
+    }
+
+    // Carry out one command; `Some` means leave the pause
+    fn command(&mut self, vm: &mut VM, line: usize, command: &str) -> Result<Option<Resume>> {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+
+        match name {
+            "" => {}
+            "s" | "step" => return Ok(Some(Resume::StepInto)),
+            "n" | "next" => return Ok(Some(Resume::StepOver)),
+            "c" | "continue" => return Ok(Some(Resume::Continue)),
+            "q" | "quit" => return Err(LumaError::Interrupted { span: None }),
+            "b" | "break" => match self.line_argument(argument) {
+                Some(target) if self.stepper.add_breakpoint(target) => {
+                    writeln!(self.output, "Breakpoint set at line {}", target)?;
+                }
+                Some(target) => writeln!(self.output, "Breakpoint already set at line {}", target)?,
+                None => writeln!(self.output, "Expected a line between 1 and {}", self.source.len())?,
+            },
+            "d" | "delete" => match argument.parse() {
+                Ok(target) if self.stepper.remove_breakpoint(target) => {
+                    writeln!(self.output, "Breakpoint at line {} removed", target)?;
+                }
+                Ok(target) => writeln!(self.output, "No breakpoint at line {}", target)?,
+                Err(_) => writeln!(self.output, "Expected a line number")?,
+            },
+            "breakpoints" => {
+                let lines: Vec<_> = self.stepper.breakpoints().map(|line| line.to_string()).collect();
+                if lines.is_empty() {
+                    writeln!(self.output, "No breakpoints")?;
+                } else {
+                    writeln!(self.output, "Breakpoints at lines {}", lines.join(", "))?;
+                }
+            }
+            "l" | "line" => self.show_line(line)?,
+            "g" | "globals" => {
+                let mut names: Vec<_> = vm.global_names().filter(|name| !is_hidden_global(name)).collect();
+                names.sort_unstable();
+                if names.is_empty() {
+                    writeln!(self.output, "No globals")?;
+                }
+                for name in names {
+                    let value = vm.get_global(name).map(format_value).unwrap_or_default();
+                    writeln!(self.output, "{} = {}", name, value)?;
+                }
+            }
+            "p" | "print" => match vm.get_global(argument) {
+                Some(value) => writeln!(self.output, "{} = {}", argument, format_value(value))?,
+                None => writeln!(self.output, "No global named '{}'", argument)?,
+            },
+            "set" => {
+                // `set name = value`, the `=` being optional
+                let (target, value) = argument
+                    .split_once(['=', ' '])
+                    .map_or((argument, ""), |(target, value)| (target.trim(), value.trim().trim_start_matches('=')));
+                match parse_value(value) {
+                    Some(value) if !target.is_empty() => {
+                        writeln!(self.output, "{} = {}", target, format_value(&value))?;
+                        vm.set_global(target, value);
+                    }
+                    _ => writeln!(self.output, "Expected 'set <name> = <number|string|true|false|nil>'")?,
+                }
+            }
+            "stack" => {
+                if vm.stack().is_empty() {
+                    writeln!(self.output, "(empty)")?;
+                } else {
+                    writeln!(self.output, "{}", vm.stack())?;
+                }
+            }
+            "h" | "help" => self.help()?,
+            _ => writeln!(self.output, "Unknown command '{}'; type 'help' for a list", name)?,
+        }
+        Ok(None)
+    }
+
+    fn line_argument(&self, argument: &str) -> Option<usize> {
+        argument.parse().ok().filter(|line| (1..=self.source.len()).contains(line))
+    }
+
+    fn help(&mut self) -> Result<()> {
+        writeln!(self.output, "  step, s           Run to the next line, entering calls")?;
+        writeln!(self.output, "  next, n           Run to the next line in this frame")?;
+        writeln!(self.output, "  continue, c       Run to the next breakpoint")?;
+        writeln!(self.output, "  break, b <line>   Pause whenever <line> is reached")?;
+        writeln!(self.output, "  delete, d <line>  Remove the breakpoint at <line>")?;
+        writeln!(self.output, "  breakpoints       List breakpoints")?;
+        writeln!(self.output, "  line, l           Show the line about to run")?;
+        writeln!(self.output, "  print, p <name>   Show a global")?;
+        writeln!(self.output, "  set <name> = <v>  Change a global")?;
+        writeln!(self.output, "  globals, g        Show every global")?;
+        writeln!(self.output, "  stack             Show the value stack, bottom first")?;
+        writeln!(self.output, "  quit, q           Stop the program")?;
+        Ok(())
+    }
+}
+
+impl DebugHook for CliDebugger {
+    fn on_line(&mut self, vm: &mut VM, line: usize) -> Result<()> {
+        if !self.stepper.should_pause(line, vm.call_depth()) {
+            return Ok(());
+        }
+        self.show_line(line)?;
+
+        loop {
+            write!(self.output, "{}", PROMPT)?;
+            self.output.flush()?;
+
+            let mut command = String::new();
+            if self.input.read_line(&mut command)? == 0 {
+                // Input is closed, so nobody can answer another pause
+                writeln!(self.output)?;
+                self.stepper.clear_breakpoints();
+                self.stepper.resume(Resume::Continue, vm.call_depth());
+                return Ok(());
+            }
+            if let Some(resume) = self.command(vm, line, command.trim())? {
+                self.stepper.resume(resume, vm.call_depth());
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+// Debug Adapter Protocol server, so editors can debug Luma scripts.
+//
+// Messages are JSON bodies behind a `Content-Length` header, read from
+// `input` and written to `output` (stdin/stdout for `luma dap`). The VM runs
+// on the same thread: while the program is paused the debug hook answers
+// requests itself, and returning from the hook resumes execution.
+use crate::backend::vm::{DebugHook, Output, VM};
+use crate::debugger::{format_value, is_hidden_global, parse_value, Resume, Stepper};
+use crate::frontend::{Compiler, Lexer, Parser};
+use crate::shared::{Chunk, LumaError, Result};
+use hashbrown::HashSet;
+use serde_json::{json, Value as Json};
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+// The VM runs one program on one thread
+const THREAD_ID: u64 = 1;
+const FRAME_ID: u64 = 1;
+
+/// Largest message body the server accepts. Requests are small, so a
+/// bigger `Content-Length` means a broken client, not a message to buffer.
+pub const MAX_MESSAGE_LENGTH: usize = 4 * 1024 * 1024;
+
+// `variablesReference`s of the two scopes a paused frame offers
+const GLOBALS_REFERENCE: u64 = 1;
+const STACK_REFERENCE: u64 = 2;
+
+/// Run a debug session over `input` and `output` until the client
+/// disconnects or closes its end.
+///
+/// The client sends `initialize`, `launch` with the script's path in
+/// `program` (and optionally `stopOnEntry`), any `setBreakpoints`, then
+/// `configurationDone`, at which point the script starts.
+pub fn serve(input: impl BufRead + 'static, output: impl Write + 'static) -> Result<()> {
+    let session = Rc::new(RefCell::new(Session::new(input, output)));
+
+    // Configure until the client is ready for the program to start
+    let chunk = loop {
+        let Some(request) = session.borrow_mut().read_message()? else {
+            return Ok(());
+        };
+        let next = session.borrow_mut().handle(&request, None)?;
+        match next {
+            Next::Start => match session.borrow_mut().chunk.take() {
+                Some(chunk) => break chunk,
+                None => return Ok(()), // Configured without a launch
+            },
+            Next::Disconnect => return Ok(()),
+            Next::Wait | Next::Resume(_) => {}
+        }
+    };
+
+    let mut vm = VM::new();
+    vm.set_output(SessionOutput(session.clone()));
+    vm.set_debug_hook(Some(Box::new(SessionHook(session.clone()))));
+    let result = vm.interpret(chunk);
+    vm.set_debug_hook(None);
+
+    let mut session = session.borrow_mut();
+    if session.disconnected {
+        return Ok(());
+    }
+    let exit_code = match result {
+        Ok(_) => 0,
+        Err(e) => {
+            session.event("output", json!({ "category": "stderr", "output": format!("{}\n", e) }))?;
+            1
+        }
+    };
+    session.event("exited", json!({ "exitCode": exit_code }))?;
+    session.event("terminated", json!({}))?;
+
+    // Answer whatever the client still asks until it lets go
+    while let Some(request) = session.read_message()? {
+        if let Next::Disconnect = session.handle(&request, None)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// What the server does after handling a request
+enum Next {
+    Wait,
+    Start,
+    Resume(Resume),
+    Disconnect,
+}
+
+struct Session {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    seq: u64,
+    stepper: Stepper,
+    path: String,
+    chunk: Option<Chunk>, // Compiled by `launch`, taken when the program starts
+    code_lines: Option<HashSet<usize>>, // Lines with instructions, once launched
+    stop_on_entry: bool,
+    launched: bool,
+    configured: bool,
+    paused_at: Option<usize>,
+    disconnected: bool,
+}
+
+impl Session {
+    fn new(input: impl BufRead + 'static, output: impl Write + 'static) -> Self {
+        Self {
+            input: Box::new(input),
+            output: Box::new(output),
+            seq: 0,
+            stepper: Stepper::new(Resume::Continue),
+            path: String::new(),
+            chunk: None,
+            code_lines: None,
+            stop_on_entry: false,
+            launched: false,
+            configured: false,
+            paused_at: None,
+            disconnected: false,
+        }
+    }
+
+    fn handle(&mut self, request: &Json, vm: Option<&mut VM>) -> Result<Next> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let arguments = &request["arguments"];
+
+        let reply = match command {
+            "initialize" => {
+                self.respond(request, Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsSetVariable": true,
+                })))?;
+                self.event("initialized", json!({}))?;
+                return Ok(Next::Wait);
+            }
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, Ok(json!({})))?;
+                return Ok(if self.launched { Next::Start } else { Next::Wait });
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "disconnect" => {
+                self.disconnected = true;
+                self.respond(request, Ok(json!({})))?;
+                return Ok(Next::Disconnect);
+            }
+            "continue" | "next" | "stepIn" if self.paused_at.is_some() => {
+                let resume = match command {
+                    "continue" => Resume::Continue,
+                    "next" => Resume::StepOver,
+                    _ => Resume::StepInto,
+                };
+                self.respond(request, Ok(json!({ "allThreadsContinued": true })))?;
+                return Ok(Next::Resume(resume));
+            }
+            "stackTrace" | "scopes" | "variables" | "setVariable" => match (self.paused_at, vm) {
+                (Some(line), Some(vm)) => self.inspect(command, arguments, line, vm),
+                _ => Err("the program is not paused".to_string()),
+            },
+            "continue" | "next" | "stepIn" => Err("the program is not paused".to_string()),
+            _ => Err(format!("unsupported request '{}'", command)),
+        };
+
+        self.respond(request, reply)?;
+        if command == "launch" && self.launched && self.configured {
+            return Ok(Next::Start);
+        }
+        Ok(Next::Wait)
+    }
+
+    fn launch(&mut self, arguments: &Json) -> std::result::Result<Json, String> {
+        let path = arguments["program"].as_str().ok_or("launch needs a 'program' path")?;
+        let source = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        let chunk = compile(&source).map_err(|e| e.to_string())?;
+
+        self.code_lines = Some(chunk.lines.iter().map(|span| span.line).filter(|&line| line != 0).collect());
+        self.chunk = Some(chunk);
+        self.path = path.to_string();
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+        let resume = if self.stop_on_entry { Resume::StepInto } else { Resume::Continue };
+        self.stepper.resume(resume, 0);
+        self.launched = true;
+        Ok(json!({}))
+    }
+
+    // Replaces every breakpoint; the script is the only source there is
+    fn set_breakpoints(&mut self, arguments: &Json) -> Json {
+        self.stepper.clear_breakpoints();
+        let requested = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+        let breakpoints: Vec<_> = requested
+            .iter()
+            .filter_map(|breakpoint| breakpoint["line"].as_u64())
+            .map(|line| {
+                let line = line as usize;
+                // Before launch there are no instructions to check against
+                let verified = self.code_lines.as_ref().is_none_or(|lines| lines.contains(&line));
+                if verified {
+                    self.stepper.add_breakpoint(line);
+                }
+                json!({ "verified": verified, "line": line })
+            })
+            .collect();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    // Requests that look at the paused program
+    fn inspect(&mut self, command: &str, arguments: &Json, line: usize, vm: &mut VM) -> std::result::Result<Json, String> {
+        match command {
+            "stackTrace" => Ok(json!({
+                "stackFrames": [{
+                    "id": FRAME_ID,
+                    "name": "main",
+                    "line": line,
+                    "column": 1,
+                    "source": { "path": self.path },
+                }],
+                "totalFrames": 1,
+            })),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Globals", "variablesReference": GLOBALS_REFERENCE, "expensive": false },
+                    { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+                ],
+            })),
+            "variables" => match arguments["variablesReference"].as_u64() {
+                Some(GLOBALS_REFERENCE) => {
+                    let mut names: Vec<_> = vm.global_names().filter(|name| !is_hidden_global(name)).collect();
+                    names.sort_unstable();
+                    let variables: Vec<_> = names
+                        .into_iter()
+                        .filter_map(|name| vm.get_global(name).map(|value| variable(name, value)))
+                        .collect();
+                    Ok(json!({ "variables": variables }))
+                }
+                Some(STACK_REFERENCE) => {
+                    let variables: Vec<_> = vm
+                        .stack()
+                        .values()
+                        .iter()
+                        .enumerate()
+                        .map(|(slot, value)| variable(&format!("[{}]", slot), value))
+                        .collect();
+                    Ok(json!({ "variables": variables }))
+                }
+                _ => Err("unknown variablesReference".to_string()),
+            },
+            "setVariable" => {
+                if arguments["variablesReference"].as_u64() != Some(GLOBALS_REFERENCE) {
+                    return Err("only globals can be changed".to_string());
+                }
+                let name = arguments["name"].as_str().ok_or("setVariable needs a 'name'")?;
+                let text = arguments["value"].as_str().unwrap_or_default();
+                let value = parse_value(text)
+                    .ok_or_else(|| format!("'{}' is not a number, string, true, false or nil", text))?;
+                let body = json!({ "value": format_value(&value), "type": value.type_name() });
+                vm.set_global(name, value);
+                Ok(body)
+            }
+            _ => unreachable!("not an inspection request: {}", command),
+        }
+    }
+
+    // Pause at `line`, answering requests until one resumes the program
+    fn pause(&mut self, vm: &mut VM, line: usize) -> Result<()> {
+        let reason = if self.stop_on_entry {
+            self.stop_on_entry = false;
+            "entry"
+        } else if self.stepper.breakpoints().any(|breakpoint| breakpoint == line) {
+            "breakpoint"
+        } else {
+            "step"
+        };
+        self.event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }))?;
+        self.paused_at = Some(line);
+
+        let next = loop {
+            // A client that goes away ends the program like `disconnect`
+            let Some(request) = self.read_message()? else {
+                self.disconnected = true;
+                break Next::Disconnect;
+            };
+            match self.handle(&request, Some(&mut *vm))? {
+                Next::Wait | Next::Start => {}
+                next => break next,
+            }
+        };
+
+        self.paused_at = None;
+        match next {
+            Next::Resume(resume) => {
+                self.stepper.resume(resume, vm.call_depth());
+                Ok(())
+            }
+            _ => Err(LumaError::Interrupted { span: None }),
+        }
+    }
+
+    fn read_message(&mut self) -> Result<Option<Json>> {
+        read_message(&mut self.input).or_else(|e| {
+            // Tell the client why the session ends before giving up on it
+            self.event("output", json!({ "category": "stderr", "output": format!("{}\n", e) }))?;
+            Err(e)
+        })
+    }
+
+    fn respond(&mut self, request: &Json, reply: std::result::Result<Json, String>) -> Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": reply.is_ok(),
+        });
+        match reply {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = Json::String(message),
+        }
+        self.send(response)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send(&mut self, mut message: Json) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)?;
+        Ok(())
+    }
+}
+
+fn variable(name: &str, value: &crate::shared::Value) -> Json {
+    json!({
+        "name": name,
+        "value": format_value(value),
+        "type": value.type_name(),
+        "variablesReference": 0,
+    })
+}
+
+fn compile(source: &str) -> Result<Chunk> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let statements = Parser::new(tokens).parse()?;
+    Compiler::new().compile(&statements)
+}
+
+/// Read one framed message; `None` once the input is closed. Bodies over
+/// `MAX_MESSAGE_LENGTH` are an error.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue; // Stray blank line between messages
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.unwrap_or_default();
+    if length > MAX_MESSAGE_LENGTH {
+        let message = format!("message of {} bytes is over the {} byte limit", length, MAX_MESSAGE_LENGTH);
+        return Err(LumaError::IoError(io::Error::new(io::ErrorKind::InvalidData, message)));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| LumaError::IoError(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// Write one message with its `Content-Length` header.
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+// Both halves share the session: the VM owns them, `serve` keeps a handle
+
+struct SessionHook(Rc<RefCell<Session>>);
+
+impl DebugHook for SessionHook {
+    fn on_line(&mut self, vm: &mut VM, line: usize) -> Result<()> {
+        let mut session = self.0.borrow_mut();
+        if !session.stepper.should_pause(line, vm.call_depth()) {
+            return Ok(());
+        }
+        session.pause(vm, line)
+    }
+}
+
+// Program output goes to the client as `output` events
+struct SessionOutput(Rc<RefCell<Session>>);
+
+impl Output for SessionOutput {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let body = json!({ "category": "stdout", "output": format!("{}\n", line) });
+        self.0.borrow_mut().event("output", body).map_err(|e| match e {
+            LumaError::IoError(e) => e,
+            e => io::Error::other(e.to_string()),
+        })
+    }
+}
@@ -0,0 +1,105 @@
+// Source-level debugging built on the VM's line hook (`DebugHook`).
+// `Stepper` holds the breakpoint and stepping rules; the frontends (`cli`
+// for a terminal, `dap` for editors) decide how to talk to the user while
+// the program is paused.
+pub mod cli;
+pub mod dap;
+
+pub use cli::*;
+
+use crate::shared::Value;
+use std::collections::BTreeSet;
+
+/// How a paused program carries on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Run until the next breakpoint.
+    Continue,
+    /// Stop at the next line, following calls into their bodies.
+    StepInto,
+    /// Stop at the next line of the current frame or one that encloses it.
+    StepOver,
+}
+
+/// Breakpoints and stepping state, shared by the debugger frontends.
+#[derive(Debug, Clone)]
+pub struct Stepper {
+    breakpoints: BTreeSet<usize>,
+    resume: Resume,
+    depth: usize, // Call depth where the program last paused
+}
+
+impl Stepper {
+    /// A stepper that stops at the first line when `resume` is `StepInto`,
+    /// or runs to the first breakpoint when it is `Continue`.
+    pub fn new(resume: Resume) -> Self {
+        Self { breakpoints: BTreeSet::new(), resume, depth: 0 }
+    }
+
+    /// Set a breakpoint; false if one was already set at `line`.
+    pub fn add_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.insert(line)
+    }
+
+    /// Remove a breakpoint; false if none was set at `line`.
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Lines with a breakpoint, in order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Whether to pause as execution reaches `line` at call depth `depth`.
+    pub fn should_pause(&self, line: usize, depth: usize) -> bool {
+        if self.breakpoints.contains(&line) {
+            return true;
+        }
+        match self.resume {
+            Resume::Continue => false,
+            Resume::StepInto => true,
+            Resume::StepOver => depth <= self.depth,
+        }
+    }
+
+    /// Carry on from a pause at call depth `depth`.
+    pub fn resume(&mut self, resume: Resume, depth: usize) {
+        self.resume = resume;
+        self.depth = depth;
+    }
+}
+
+/// A value as it would be written in source: strings are quoted.
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        value => value.to_string(),
+    }
+}
+
+/// Read a literal typed while debugging: a number, `true`, `false`, `nil`
+/// or a double-quoted string.
+pub fn parse_value(text: &str) -> Option<Value> {
+    let text = text.trim();
+    match text {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        "nil" => return Some(Value::Nil),
+        _ => {}
+    }
+    if let Some(inner) = text.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        return Some(Value::String(inner.into()));
+    }
+    text.parse::<f64>().ok().filter(|n| n.is_finite()).map(Value::Number)
+}
+
+/// Whether a global was made by the compiler (such as a `repeat` counter)
+/// rather than by the program.
+pub fn is_hidden_global(name: &str) -> bool {
+    name.starts_with("__")
+}
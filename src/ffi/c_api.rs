@@ -1,6 +1,9 @@
+// Raw pointers come from C callers, which are responsible for passing valid handles
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_double};
-use crate::backend::vm::VM;
+use std::os::raw::{c_char, c_double, c_void};
+use crate::backend::vm::{CallbackOutput, StdoutOutput, VM};
 use crate::frontend::{Lexer, Parser, Compiler};
 use crate::shared::LumaError;
 
@@ -12,8 +15,8 @@ pub struct LumaVM {
 
 #[repr(C)]
 pub struct LumaResult {
-    success: bool,
-    error_message: *mut c_char,
+    pub success: bool,
+    pub error_message: *mut c_char, // Free with `luma_free_error_message`
 }
 
 #[no_mangle]
@@ -33,6 +36,62 @@ pub extern "C" fn luma_vm_free(vm: *mut LumaVM) {
     }
 }
 
+/// Receives each line a program shows, without the trailing newline.
+pub type LumaOutputCallback = extern "C" fn(line: *const c_char, user_data: *mut c_void);
+
+/// Route `show` output to `callback` instead of stdout; NULL restores stdout.
+#[no_mangle]
+pub extern "C" fn luma_vm_set_output(
+    vm: *mut LumaVM,
+    callback: Option<LumaOutputCallback>,
+    user_data: *mut c_void,
+) -> LumaResult {
+    if vm.is_null() {
+        return LumaResult {
+            success: false,
+            error_message: create_error_string("Invalid VM pointer"),
+        };
+    }
+
+    let vm_ref = unsafe { &mut *vm };
+    match callback {
+        Some(callback) => vm_ref.vm.set_output(CallbackOutput::new(move |line: &str| {
+            // C strings end at the first NUL, so drop any embedded ones
+            let line = CString::new(line.replace('\0', "")).unwrap_or_default();
+            callback(line.as_ptr(), user_data);
+        })),
+        None => vm_ref.vm.set_output(StdoutOutput),
+    }
+
+    LumaResult {
+        success: true,
+        error_message: std::ptr::null_mut(),
+    }
+}
+
+/// Store the bytes of runtime string memory the VM holds now and the most
+/// it has held at once.
+#[no_mangle]
+pub extern "C" fn luma_vm_memory_usage(vm: *mut LumaVM, current: *mut usize, peak: *mut usize) -> LumaResult {
+    if vm.is_null() || current.is_null() || peak.is_null() {
+        return LumaResult {
+            success: false,
+            error_message: create_error_string("Invalid pointer"),
+        };
+    }
+
+    let usage = unsafe { &*vm }.vm.memory_usage();
+    unsafe {
+        *current = usage.current;
+        *peak = usage.peak;
+    }
+
+    LumaResult {
+        success: true,
+        error_message: std::ptr::null_mut(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn luma_execute_source(vm: *mut LumaVM, source: *const c_char) -> LumaResult {
     if vm.is_null() || source.is_null() {
@@ -139,10 +198,10 @@ fn create_error_string(message: &str) -> *mut c_char {
 
 fn execute_luma_source(vm: &mut VM, source: &str) -> Result<(), LumaError> {
     let mut lexer = Lexer::new(source);
-    let tokens = lexer.tokenize().map_err(|e| LumaError::lex_error(e.to_string(), 1))?;
+    let tokens = lexer.tokenize()?;
     
     let mut parser = Parser::new(tokens);
-    let statements = parser.parse().map_err(|e| LumaError::parse_error(e.to_string(), 1))?;
+    let statements = parser.parse()?;
     
     let mut compiler = Compiler::new();
     let chunk = compiler.compile(&statements)?;
@@ -158,6 +217,8 @@ fn execute_luma_source(vm: &mut VM, source: &str) -> Result<(), LumaError> {
 #ifndef LUMA_H
 #define LUMA_H
 
+#include <stddef.h>
+
 #ifdef __cplusplus
 extern "C" {
 #endif
@@ -176,6 +237,13 @@ void luma_vm_free(LumaVM* vm);
 // Code execution
 LumaResult luma_execute_source(LumaVM* vm, const char* source);
 
+// Output: receives each shown line; pass NULL to write to stdout again
+typedef void (*LumaOutputCallback)(const char* line, void* user_data);
+LumaResult luma_vm_set_output(LumaVM* vm, LumaOutputCallback callback, void* user_data);
+
+// Runtime string memory in bytes: held now and the most held at once
+LumaResult luma_vm_memory_usage(LumaVM* vm, size_t* current, size_t* peak);
+
 // Variable access
 LumaResult luma_set_global_number(LumaVM* vm, const char* name, double value);
 LumaResult luma_get_global_number(LumaVM* vm, const char* name, double* value);
@@ -1,7 +1,14 @@
+use crate::shared::Span;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Statement {
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StatementKind {
     Assignment {
         name: String,
         value: Expression,
@@ -24,7 +31,13 @@ pub enum Statement {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExpressionKind {
     Literal(f64),
     StringLiteral(String),
     BooleanLiteral(bool),
@@ -74,27 +87,41 @@ pub enum UnaryOperator {
     Minus, // Negative numbers
 }
 
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
 impl Expression {
-    /// Create a new binary operation expression
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Create a new binary operation expression spanning both operands
     pub fn binary_op(left: Expression, operator: BinaryOperator, right: Expression) -> Self {
-        Expression::BinaryOp {
-            left: Box::new(left),
-            operator,
-            right: Box::new(right),
-        }
+        let span = left.span.to(right.span);
+        Expression::new(
+            ExpressionKind::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        )
     }
 }
 
 impl std::fmt::Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Statement::Assignment { name, value } => {
+        match &self.kind {
+            StatementKind::Assignment { name, value } => {
                 write!(f, "let {} be {}", name, value)
             }
-            Statement::Show(expr) => {
+            StatementKind::Show(expr) => {
                 write!(f, "show {}", expr)
             }
-            Statement::If { condition, then_branch, else_ifs, else_branch } => {
+            StatementKind::If { condition, then_branch, else_ifs, else_branch } => {
                 write!(f, "if {} then", condition)?;
                 for stmt in then_branch {
                     write!(f, "\n  {}", stmt)?;
@@ -113,14 +140,14 @@ impl std::fmt::Display for Statement {
                 }
                 Ok(())
             }
-            Statement::While { condition, body } => {
+            StatementKind::While { condition, body } => {
                 write!(f, "while {}:", condition)?;
                 for stmt in body {
                     write!(f, "\n  {}", stmt)?;
                 }
                 Ok(())
             }
-            Statement::Repeat { count, body } => {
+            StatementKind::Repeat { count, body } => {
                 write!(f, "repeat {} times:", count)?;
                 for stmt in body {
                     write!(f, "\n  {}", stmt)?;
@@ -133,18 +160,18 @@ impl std::fmt::Display for Statement {
 
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Expression::Literal(n) => write!(f, "{}", n),
-            Expression::StringLiteral(s) => write!(f, "\"{}\"", s),
-            Expression::BooleanLiteral(b) => write!(f, "{}", b),
-            Expression::Identifier(name) => write!(f, "{}", name),
-            Expression::BinaryOp { left, operator, right } => {
+        match &self.kind {
+            ExpressionKind::Literal(n) => write!(f, "{}", n),
+            ExpressionKind::StringLiteral(s) => write!(f, "\"{}\"", s),
+            ExpressionKind::BooleanLiteral(b) => write!(f, "{}", b),
+            ExpressionKind::Identifier(name) => write!(f, "{}", name),
+            ExpressionKind::BinaryOp { left, operator, right } => {
                 write!(f, "({} {} {})", left, operator, right)
             },
-            Expression::UnaryOp { operator, operand } => {
+            ExpressionKind::UnaryOp { operator, operand } => {
                 write!(f, "({}{})", operator, operand)
             },
-            Expression::FunctionCall { name, arguments } => {
+            ExpressionKind::FunctionCall { name, arguments } => {
                 write!(f, "{}(", name)?;
                 for (i, arg) in arguments.iter().enumerate() {
                     if i > 0 { write!(f, ", ")?; }
@@ -1,12 +1,12 @@
-use crate::frontend::{Statement, Expression, BinaryOperator, UnaryOperator};
+use crate::frontend::{Statement, StatementKind, Expression, ExpressionKind, BinaryOperator, UnaryOperator};
 use crate::backend::vm::OpCode;
-use crate::shared::{Chunk, Value, LumaError, Result};
+use crate::shared::{Chunk, Value, LumaError, Result, Span};
 
 pub struct Compiler {
     chunk: Chunk,
     locals: Vec<Local>,
     scope_depth: usize,
-    current_line: usize,
+    current_span: Span, // Span of the statement being compiled
 }
 
 #[derive(Debug, Clone)]
@@ -22,195 +22,176 @@ impl Compiler {
             chunk: Chunk::new(),
             locals: Vec::new(),
             scope_depth: 0,
-            current_line: 1,
+            current_span: Span::default(),
         }
     }
 
     pub fn compile(&mut self, statements: &[Statement]) -> Result<Chunk> {
         for (i, statement) in statements.iter().enumerate() {
-            // More accurate line tracking: account for comments and empty lines
-            self.current_line = self.estimate_statement_line(i + 1);
-            self.compile_statement(statement)?;
-        }
-        
-        // Ensure the chunk ends with a return
-        self.emit_opcode(OpCode::OpReturn, self.current_line);
-        
-        Ok(std::mem::take(&mut self.chunk))
-    }
-    
-    pub fn compile_with_source(&mut self, statements: &[Statement], source: &str) -> Result<Chunk> {
-        let source_lines: Vec<&str> = source.lines().collect();
-        let mut statement_index = 0;
-        
-        for (_i, statement) in statements.iter().enumerate() {
-            // Find the actual line number of this statement in the source
-            self.current_line = self.find_statement_line(&source_lines, statement_index);
-            statement_index += 1;
-            self.compile_statement(statement)?;
+            self.current_span = statement.span;
+            match &statement.kind {
+                // A trailing `show` leaves its value on the stack as the
+                // program's result
+                StatementKind::Show(expression) if i + 1 == statements.len() => {
+                    self.compile_expression(expression)?;
+                    self.emit_opcode(OpCode::OpPrint, statement.span);
+                }
+                _ => self.compile_statement(statement)?,
+            }
         }
         
         // Ensure the chunk ends with a return
-        self.emit_opcode(OpCode::OpReturn, self.current_line);
+        self.emit_opcode(OpCode::OpReturn, self.current_span);
         
         Ok(std::mem::take(&mut self.chunk))
     }
     
-    fn find_statement_line(&self, source_lines: &[&str], statement_index: usize) -> usize {
-        let mut statement_count = 0;
-        
-        for (line_num, line) in source_lines.iter().enumerate() {
-            let trimmed = line.trim();
-            
-            // Skip empty lines and comments (both # and ##)
-            if trimmed.is_empty() || trimmed.starts_with("##") || trimmed.starts_with('#') {
-                continue;
-            }
-            
-            // This is an actual statement line
-            if statement_count == statement_index {
-                return line_num + 1; // Convert to 1-based line number
-            }
-            statement_count += 1;
-        }
-        
-        // Fallback estimation
-        self.estimate_statement_line(statement_index + 1)
-    }
-    
-    fn estimate_statement_line(&self, statement_index: usize) -> usize {
-        // Better estimation that accounts for comments and empty lines
-        // Each statement is roughly 2 lines apart (allowing for comments)
-        statement_index * 2
+    /// Kept for existing callers: spans now come from the tokens, so the
+    /// source text is no longer needed for line tracking.
+    #[allow(dead_code)]
+    pub fn compile_with_source(&mut self, statements: &[Statement], _source: &str) -> Result<Chunk> {
+        self.compile(statements)
     }
 
     fn compile_statement(&mut self, statement: &Statement) -> Result<()> {
-        match statement {
-            Statement::Assignment { name, value } => {
+        let span = statement.span;
+        match &statement.kind {
+            StatementKind::Assignment { name, value } => {
                 self.compile_expression(value)?;
                 
                 if self.scope_depth > 0 {
                     // Local variable
                     if let Some(local_index) = self.resolve_local(name) {
                         // Variable exists, set it
-                        self.emit_opcode(OpCode::OpSetLocal, 0);
-                        self.emit_byte(local_index as u8, 0);
+                        self.emit_opcode(OpCode::OpSetLocal, span);
+                        self.emit_byte(local_index as u8, span);
                     } else {
                         // New variable, add it
-                        self.add_local(name.clone())?;
+                        self.add_local(name.clone(), span)?;
                     }
                 } else {
                     // Global variable - check if it exists
-                    let name_constant = self.chunk.add_constant(Value::String(name.clone()));
+                    let name_constant = self.chunk.intern_string(name);
                     
                     // For now, always define new globals or update existing ones
-                    self.emit_opcode(OpCode::OpSetGlobal, 0);
-                    self.emit_byte(name_constant as u8, 0);
-                    self.emit_opcode(OpCode::OpPop, 0); // Pop the value after assignment
+                    self.emit_opcode(OpCode::OpSetGlobal, span);
+                    self.emit_constant_operand(name_constant, span)?;
+                    self.emit_opcode(OpCode::OpPop, span); // Pop the value after assignment
                 }
             }
             
-            Statement::Show(expression) => {
+            StatementKind::Show(expression) => {
                 self.compile_expression(expression)?;
-                self.emit_opcode(OpCode::OpPrint, 0);
+                self.emit_opcode(OpCode::OpPrint, span);
+                self.emit_opcode(OpCode::OpPop, span);
             }
             
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                self.compile_expression(condition)?;
+            StatementKind::If { condition, then_branch, else_ifs, else_branch } => {
+                let mut end_jumps = Vec::new();
                 
-                let else_jump = self.emit_jump(OpCode::OpJumpIfFalse, 0);
-                self.emit_opcode(OpCode::OpPop, 0); // Pop condition
-                
-                for stmt in then_branch {
-                    self.compile_statement(stmt)?;
+                // `if` and each `else if` test their condition in turn; the
+                // first true one runs its body and jumps past the rest
+                let branches = std::iter::once((condition, then_branch))
+                    .chain(else_ifs.iter().map(|(condition, body)| (condition, body)));
+                for (condition, body) in branches {
+                    self.compile_expression(condition)?;
+                    
+                    let next_jump = self.emit_jump(OpCode::OpJumpIfFalse, condition.span);
+                    self.emit_opcode(OpCode::OpPop, condition.span); // Pop condition
+                    
+                    for stmt in body {
+                        self.compile_statement(stmt)?;
+                    }
+                    
+                    end_jumps.push(self.emit_jump(OpCode::OpJump, span));
+                    
+                    self.patch_jump(next_jump);
+                    self.emit_opcode(OpCode::OpPop, condition.span); // Pop condition
                 }
                 
-                let end_jump = self.emit_jump(OpCode::OpJump, 0);
-                
-                self.patch_jump(else_jump);
-                self.emit_opcode(OpCode::OpPop, 0); // Pop condition
-                
                 if let Some(else_stmts) = else_branch {
                     for stmt in else_stmts {
                         self.compile_statement(stmt)?;
                     }
                 }
                 
-                self.patch_jump(end_jump);
+                for jump in end_jumps {
+                    self.patch_jump(jump);
+                }
             }
             
-            Statement::While { condition, body } => {
+            StatementKind::While { condition, body } => {
                 let loop_start = self.chunk.code.len();
-                self.emit_opcode(OpCode::OpLoopStart, 0);
+                self.emit_opcode(OpCode::OpLoopStart, span);
                 
                 self.compile_expression(condition)?;
                 
-                let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse, 0);
-                self.emit_opcode(OpCode::OpPop, 0); // Pop condition
+                let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse, condition.span);
+                self.emit_opcode(OpCode::OpPop, condition.span); // Pop condition
                 
                 for stmt in body {
                     self.compile_statement(stmt)?;
                 }
                 
-                self.emit_loop(loop_start, 0);
+                self.emit_loop(loop_start, span);
                 
                 self.patch_jump(exit_jump);
-                self.emit_opcode(OpCode::OpPop, 0); // Pop condition
-                self.emit_opcode(OpCode::OpLoopEnd, 0);
+                self.emit_opcode(OpCode::OpPop, span); // Pop condition
+                self.emit_opcode(OpCode::OpLoopEnd, span);
             }
             
-            Statement::Repeat { count, body } => {
-                // Compile count expression
+            StatementKind::Repeat { count, body } => {
+                // Hidden globals hold the loop bound and the counter; the
+                // offset keeps nested and sequential loops apart
+                let id = self.chunk.code.len();
+                let count_constant = self.chunk.intern_string(&format!("__repeat_count_{}", id));
+                let counter_constant = self.chunk.intern_string(&format!("__repeat_counter_{}", id));
+
+                // Evaluate the count once and store it
                 self.compile_expression(count)?;
-                
-                // Initialize counter variable (use a special name to avoid conflicts)
-                let counter_name = format!("__repeat_counter_{}", self.chunk.code.len());
-                let counter_constant = self.chunk.add_constant(Value::String(counter_name.clone()));
-                self.emit_opcode(OpCode::OpDefineGlobal, 0);
-                self.emit_byte(counter_constant as u8, 0);
-                
+                self.emit_opcode(OpCode::OpDefineGlobal, span);
+                self.emit_constant_operand(count_constant, span)?;
+
                 // Initialize counter to 0
-                self.emit_opcode(OpCode::OpConstant, 0);
                 let zero_constant = self.chunk.add_constant(Value::Number(0.0));
-                self.emit_byte(zero_constant as u8, 0);
-                
+                self.emit_opcode(OpCode::OpConstant, span);
+                self.emit_constant_operand(zero_constant, span)?;
+                self.emit_opcode(OpCode::OpDefineGlobal, span);
+                self.emit_constant_operand(counter_constant, span)?;
+
                 let loop_start = self.chunk.code.len();
-                self.emit_opcode(OpCode::OpLoopStart, 0);
-                
+                self.emit_opcode(OpCode::OpLoopStart, span);
+
                 // Check if counter < count
-                self.emit_opcode(OpCode::OpGetGlobal, 0);
-                self.emit_byte(counter_constant as u8, 0);
-                
-                // Get count from stack (we need to duplicate it)
-                self.emit_opcode(OpCode::OpGetGlobal, 0);
-                let count_var = format!("__repeat_count_{}", self.chunk.code.len());
-                let _count_constant = self.chunk.add_constant(Value::String(count_var));
-                
-                self.emit_opcode(OpCode::OpLess, 0);
-                let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse, 0);
-                self.emit_opcode(OpCode::OpPop, 0);
-                
+                self.emit_opcode(OpCode::OpGetGlobal, span);
+                self.emit_constant_operand(counter_constant, span)?;
+                self.emit_opcode(OpCode::OpGetGlobal, span);
+                self.emit_constant_operand(count_constant, span)?;
+                self.emit_opcode(OpCode::OpLess, span);
+                let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse, span);
+                self.emit_opcode(OpCode::OpPop, span);
+
                 // Execute body
                 for stmt in body {
                     self.compile_statement(stmt)?;
                 }
-                
+
                 // Increment counter
-                self.emit_opcode(OpCode::OpGetGlobal, 0);
-                self.emit_byte(counter_constant as u8, 0);
-                self.emit_opcode(OpCode::OpConstant, 0);
+                self.emit_opcode(OpCode::OpGetGlobal, span);
+                self.emit_constant_operand(counter_constant, span)?;
                 let one_constant = self.chunk.add_constant(Value::Number(1.0));
-                self.emit_byte(one_constant as u8, 0);
-                self.emit_opcode(OpCode::OpAdd, 0);
-                self.emit_opcode(OpCode::OpSetGlobal, 0);
-                self.emit_byte(counter_constant as u8, 0);
-                self.emit_opcode(OpCode::OpPop, 0);
-                
-                self.emit_loop(loop_start, 0);
-                
+                self.emit_opcode(OpCode::OpConstant, span);
+                self.emit_constant_operand(one_constant, span)?;
+                self.emit_opcode(OpCode::OpAdd, span);
+                self.emit_opcode(OpCode::OpSetGlobal, span);
+                self.emit_constant_operand(counter_constant, span)?;
+                self.emit_opcode(OpCode::OpPop, span);
+
+                self.emit_loop(loop_start, span);
+
                 self.patch_jump(exit_jump);
-                self.emit_opcode(OpCode::OpPop, 0);
-                self.emit_opcode(OpCode::OpLoopEnd, 0);
+                self.emit_opcode(OpCode::OpPop, span);
+                self.emit_opcode(OpCode::OpLoopEnd, span);
             }
         }
         
@@ -218,99 +199,110 @@ impl Compiler {
     }
 
     fn compile_expression(&mut self, expression: &Expression) -> Result<()> {
-        match expression {
-            Expression::Literal(value) => {
+        let span = expression.span;
+        match &expression.kind {
+            ExpressionKind::Literal(value) => {
                 let constant = self.chunk.add_constant(Value::Number(*value));
-                self.emit_opcode(OpCode::OpConstant, 0);
-                self.emit_byte(constant as u8, 0);
+                self.emit_opcode(OpCode::OpConstant, span);
+                self.emit_constant_operand(constant, span)?;
             }
             
-            Expression::StringLiteral(value) => {
-                let constant = self.chunk.add_constant(Value::String(value.clone()));
-                self.emit_opcode(OpCode::OpConstant, 0);
-                self.emit_byte(constant as u8, 0);
+            ExpressionKind::StringLiteral(value) => {
+                let constant = self.chunk.intern_string(value);
+                self.emit_opcode(OpCode::OpConstant, span);
+                self.emit_constant_operand(constant, span)?;
             }
             
-            Expression::BooleanLiteral(value) => {
+            ExpressionKind::BooleanLiteral(value) => {
                 if *value {
-                    self.emit_opcode(OpCode::OpTrue, 0);
+                    self.emit_opcode(OpCode::OpTrue, span);
                 } else {
-                    self.emit_opcode(OpCode::OpFalse, 0);
+                    self.emit_opcode(OpCode::OpFalse, span);
                 }
             }
             
-            Expression::Identifier(name) => {
+            ExpressionKind::Identifier(name) => {
                 if let Some(local_index) = self.resolve_local(name) {
-                    self.emit_opcode(OpCode::OpGetLocal, 0);
-                    self.emit_byte(local_index as u8, 0);
+                    self.emit_opcode(OpCode::OpGetLocal, span);
+                    self.emit_byte(local_index as u8, span);
                 } else {
-                    let constant = self.chunk.add_constant(Value::String(name.clone()));
-                    self.emit_opcode(OpCode::OpGetGlobal, 0);
-                    self.emit_byte(constant as u8, 0);
+                    let constant = self.chunk.intern_string(name);
+                    self.emit_opcode(OpCode::OpGetGlobal, span);
+                    self.emit_constant_operand(constant, span)?;
                 }
             }
             
-            Expression::BinaryOp { left, operator, right } => {
+            ExpressionKind::BinaryOp { left, operator, right } => {
                 self.compile_expression(left)?;
                 self.compile_expression(right)?;
                 
                 match operator {
-                    BinaryOperator::Add => self.emit_opcode(OpCode::OpAdd, 0),
-                    BinaryOperator::Subtract => self.emit_opcode(OpCode::OpSubtract, 0),
-                    BinaryOperator::Multiply => self.emit_opcode(OpCode::OpMultiply, 0),
-                    BinaryOperator::Divide => self.emit_opcode(OpCode::OpDivide, 0),
-                    BinaryOperator::Modulo => self.emit_opcode(OpCode::OpModulo, 0),
-                    BinaryOperator::Equal => self.emit_opcode(OpCode::OpEqual, 0),
-                    BinaryOperator::NotEqual => self.emit_opcode(OpCode::OpNotEqual, 0),
-                    BinaryOperator::Greater | BinaryOperator::GreaterThan => self.emit_opcode(OpCode::OpGreater, 0),
-                    BinaryOperator::GreaterEqual => self.emit_opcode(OpCode::OpGreaterEqual, 0),
-                    BinaryOperator::Less | BinaryOperator::LessThan => self.emit_opcode(OpCode::OpLess, 0),
-                    BinaryOperator::LessEqual => self.emit_opcode(OpCode::OpLessEqual, 0),
-                    BinaryOperator::And => self.emit_opcode(OpCode::OpAnd, 0),
-                    BinaryOperator::Or => self.emit_opcode(OpCode::OpOr, 0),
+                    BinaryOperator::Add => self.emit_opcode(OpCode::OpAdd, span),
+                    BinaryOperator::Subtract => self.emit_opcode(OpCode::OpSubtract, span),
+                    BinaryOperator::Multiply => self.emit_opcode(OpCode::OpMultiply, span),
+                    BinaryOperator::Divide => self.emit_opcode(OpCode::OpDivide, span),
+                    BinaryOperator::Modulo => self.emit_opcode(OpCode::OpModulo, span),
+                    BinaryOperator::Equal => self.emit_opcode(OpCode::OpEqual, span),
+                    BinaryOperator::NotEqual => self.emit_opcode(OpCode::OpNotEqual, span),
+                    BinaryOperator::Greater | BinaryOperator::GreaterThan => self.emit_opcode(OpCode::OpGreater, span),
+                    BinaryOperator::GreaterEqual => self.emit_opcode(OpCode::OpGreaterEqual, span),
+                    BinaryOperator::Less | BinaryOperator::LessThan => self.emit_opcode(OpCode::OpLess, span),
+                    BinaryOperator::LessEqual => self.emit_opcode(OpCode::OpLessEqual, span),
+                    BinaryOperator::And => self.emit_opcode(OpCode::OpAnd, span),
+                    BinaryOperator::Or => self.emit_opcode(OpCode::OpOr, span),
                 }
             }
             
-            Expression::UnaryOp { operator, operand } => {
+            ExpressionKind::UnaryOp { operator, operand } => {
                 self.compile_expression(operand)?;
                 
                 match operator {
-                    UnaryOperator::Minus => self.emit_opcode(OpCode::OpNegate, 0),
-                    UnaryOperator::Not => self.emit_opcode(OpCode::OpNot, 0),
+                    UnaryOperator::Minus => self.emit_opcode(OpCode::OpNegate, span),
+                    UnaryOperator::Not => self.emit_opcode(OpCode::OpNot, span),
                 }
             }
             
-            Expression::FunctionCall { name: _, arguments: _ } => {
-                return Err(LumaError::compile_error("Function calls not implemented in JIT-VM".to_string(), 0));
+            ExpressionKind::FunctionCall { name: _, arguments: _ } => {
+                return Err(LumaError::compile_error("Function calls not implemented in JIT-VM".to_string(), span));
             }
         }
         
         Ok(())
     }
 
-    fn emit_opcode(&mut self, opcode: OpCode, _line: usize) {
-        self.chunk.write_opcode(opcode, self.current_line);
+    fn emit_opcode(&mut self, opcode: OpCode, span: Span) {
+        self.chunk.write_opcode(opcode, span);
+    }
+
+    fn emit_byte(&mut self, byte: u8, span: Span) {
+        self.chunk.write_byte(byte, span);
     }
 
-    fn emit_byte(&mut self, byte: u8, _line: usize) {
-        self.chunk.write_byte(byte, self.current_line);
+    // Constant indices are a single byte operand, so a chunk can only
+    // refer to the first 256 constants
+    fn emit_constant_operand(&mut self, index: usize, span: Span) -> Result<()> {
+        if index > u8::MAX as usize {
+            return Err(LumaError::compile_error("Too many constants in one chunk".to_string(), span));
+        }
+        self.emit_byte(index as u8, span);
+        Ok(())
     }
 
-    fn emit_jump(&mut self, opcode: OpCode, line: usize) -> usize {
-        self.chunk.emit_jump(opcode, line)
+    fn emit_jump(&mut self, opcode: OpCode, span: Span) -> usize {
+        self.chunk.emit_jump(opcode, span)
     }
 
     fn patch_jump(&mut self, offset: usize) {
         self.chunk.patch_jump(offset);
     }
 
-    fn emit_loop(&mut self, loop_start: usize, line: usize) {
-        self.chunk.emit_loop(loop_start, line);
+    fn emit_loop(&mut self, loop_start: usize, span: Span) {
+        self.chunk.emit_loop(loop_start, span);
     }
 
-    fn add_local(&mut self, name: String) -> Result<()> {
+    fn add_local(&mut self, name: String, span: Span) -> Result<()> {
         if self.locals.len() >= u8::MAX as usize {
-            return Err(LumaError::compile_error("Too many local variables in scope".to_string(), 0));
+            return Err(LumaError::compile_error("Too many local variables in scope".to_string(), span));
         }
         
         self.locals.push(Local {
@@ -346,7 +338,7 @@ impl Compiler {
                 }
             }
             self.locals.pop();
-            self.emit_opcode(OpCode::OpPop, 0);
+            self.emit_opcode(OpCode::OpPop, self.current_span);
         }
     }
 }
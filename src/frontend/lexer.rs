@@ -1,5 +1,5 @@
-use crate::frontend::Token;
-use crate::shared::LumaError;
+use crate::frontend::{SpannedToken, Token};
+use crate::shared::{LumaError, Span};
 
 pub struct Lexer {
     input: Vec<char>,
@@ -18,7 +18,7 @@ impl Lexer {
         }
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, LumaError> {
+    pub fn tokenize(&mut self) -> Result<Vec<SpannedToken>, LumaError> {
         let mut tokens = Vec::new();
         
         while !self.is_at_end() {
@@ -27,7 +27,7 @@ impl Lexer {
             }
         }
         
-        tokens.push(Token::Eof);
+        tokens.push(SpannedToken::new(Token::Eof, Span::new(self.line, self.column, 0)));
         Ok(tokens)
     }
     
@@ -36,13 +36,24 @@ impl Lexer {
         self.line
     }
 
-    fn next_token(&mut self) -> Result<Option<Token>, LumaError> {
-        self.skip_whitespace();
-        
-        if self.is_at_end() {
-            return Ok(None);
+    fn next_token(&mut self) -> Result<Option<SpannedToken>, LumaError> {
+        loop {
+            self.skip_whitespace();
+
+            if self.is_at_end() {
+                return Ok(None);
+            }
+
+            let (start_pos, start_line, start_column) = (self.position, self.line, self.column);
+            if let Some(token) = self.scan_token()? {
+                let span = Span::new(start_line, start_column, self.position - start_pos);
+                return Ok(Some(SpannedToken::new(token, span)));
+            }
+            // Comments and ignored characters produce no token; keep scanning
         }
+    }
 
+    fn scan_token(&mut self) -> Result<Option<Token>, LumaError> {
         let ch = self.current_char();
         self.advance();
 
@@ -63,7 +74,7 @@ impl Lexer {
                 // Colon is no longer used in Luma syntax
                 Err(LumaError::lex_error(
                     "Unexpected character ':'. Use 'then' instead for control structures.".to_string(),
-                    self.line
+                    self.previous_char_span()
                 ))
             }
             ',' => Ok(Some(Token::Comma)),
@@ -82,7 +93,7 @@ impl Lexer {
                 } else {
                     Err(LumaError::lex_error(
                         "Unexpected character '!'. Did you mean '!='?".to_string(),
-                        self.line
+                        self.previous_char_span()
                     ))
                 }
             }
@@ -119,8 +130,7 @@ impl Lexer {
                 }
                 // Skip to end of line for both # and ##
                 self.skip_comment();
-                // Continue to next token
-                self.next_token()
+                Ok(None)
             }
             _ if ch.is_ascii_digit() => {
                 self.position -= 1;
@@ -140,12 +150,12 @@ impl Lexer {
                     Ok(Some(self.read_number()?))
                 } else {
                     // Skip standalone dots (often used in comments)
-                    self.next_token()
+                    Ok(None)
                 }
             }
             _ => {
                 // Skip all non-recognized characters (including Thai text, punctuation, etc.)
-                self.next_token()
+                Ok(None)
             },
         }
     }
@@ -177,6 +187,7 @@ impl Lexer {
 
     fn read_number(&mut self) -> Result<Token, LumaError> {
         let start_pos = self.position;
+        let start_column = self.column;
         
         while !self.is_at_end() && (self.current_char().is_ascii_digit() || self.current_char() == '.') {
             self.advance();
@@ -186,7 +197,7 @@ impl Lexer {
         let number = number_str.parse::<f64>()
             .map_err(|_| LumaError::lex_error(
                 format!("Invalid number '{}'", number_str), 
-                self.line
+                Span::new(self.line, start_column, self.position - start_pos)
             ))?;
         
         Ok(Token::Number(number))
@@ -290,6 +301,11 @@ impl Lexer {
         }
     }
 
+    // Span of the single character just consumed by `advance`
+    fn previous_char_span(&self) -> Span {
+        Span::new(self.line, self.column - 1, 1)
+    }
+
     fn current_char(&self) -> char {
         self.input[self.position]
     }
@@ -306,6 +322,7 @@ impl Lexer {
     }
 
     fn read_string(&mut self, quote_char: char) -> Result<Token, LumaError> {
+        let quote_span = Span::new(self.line, self.column, 1);
         self.advance(); // Skip opening quote
         let start_pos = self.position;
         
@@ -320,7 +337,7 @@ impl Lexer {
         if self.is_at_end() {
             return Err(LumaError::lex_error(
                 "Unterminated string".to_string(),
-                self.line
+                quote_span
             ));
         }
         
@@ -1,17 +1,23 @@
-use crate::frontend::{Token, Statement, Expression, BinaryOperator, UnaryOperator};
-use crate::shared::LumaError;
+use crate::frontend::{
+    BinaryOperator, Expression, ExpressionKind, SpannedToken, Statement, StatementKind, Token,
+    UnaryOperator,
+};
+use crate::shared::{LumaError, Span};
 
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     current: usize,
-    current_line: usize,
+    errors: Vec<LumaError>, // Errors recovered from so far
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0, current_line: 1 }
+    pub fn new(tokens: Vec<SpannedToken>) -> Self {
+        Self { tokens, current: 0, errors: Vec::new() }
     }
 
+    /// Parse the whole program, recovering from syntax errors so that every
+    /// problem is reported at once. A single error is returned as-is; several
+    /// are bundled into `LumaError::ParseErrors`.
     pub fn parse(&mut self) -> Result<Vec<Statement>, LumaError> {
         let mut statements = Vec::new();
         
@@ -19,7 +25,6 @@ impl Parser {
             // Skip newlines at the beginning
             if self.check(&Token::Newline) {
                 self.advance();
-                self.current_line += 1;
                 continue;
             }
             
@@ -27,16 +32,56 @@ impl Parser {
                 break;
             }
             
-            statements.push(self.parse_statement()?);
+            if let Some(statement) = self.parse_statement_recovering() {
+                statements.push(statement);
+            }
             
             // Consume optional newline after statement
             if self.check(&Token::Newline) {
                 self.advance();
-                self.current_line += 1;
             }
         }
         
-        Ok(statements)
+        match self.errors.len() {
+            0 => Ok(statements),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(LumaError::ParseErrors(std::mem::take(&mut self.errors))),
+        }
+    }
+
+    // Parse one statement; on failure record the error and skip ahead to the
+    // next place a statement can start
+    fn parse_statement_recovering(&mut self) -> Option<Statement> {
+        let start = self.current;
+        match self.parse_statement() {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.errors.push(error);
+                // Always make progress, even if the error was at the first token
+                if self.current == start {
+                    self.advance();
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // Panic-mode recovery: discard tokens up to the end of the line or the
+    // next keyword that begins a statement
+    fn synchronize(&mut self) {
+        while !self.is_at_end() {
+            match self.peek() {
+                Token::Newline => {
+                    self.advance();
+                    return;
+                }
+                Token::If | Token::While | Token::Repeat | Token::Let | Token::Show => return,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
     }
 
     fn parse_statement(&mut self) -> Result<Statement, LumaError> {
@@ -56,18 +101,18 @@ impl Parser {
         } else {
             Err(LumaError::parse_error(
                 format!("Expected statement, found '{}'", self.peek()),
-                self.current_line()
+                self.peek_span()
             ))
         }
     }
 
     fn parse_assignment(&mut self) -> Result<Statement, LumaError> {
-        self.consume(&Token::Let, "Expected 'let'")?;
+        let start = self.consume(&Token::Let, "Expected 'let'")?;
         
         let name = if let Token::Identifier(name) = self.advance() {
             name.clone()
         } else {
-            return Err(LumaError::parse_error("Expected identifier after 'let'".to_string(), self.current_line()));
+            return Err(LumaError::parse_error("Expected identifier after 'let'".to_string(), self.previous_span()));
         };
         
         // Support both "be" and "is" after let
@@ -76,19 +121,21 @@ impl Parser {
         } else if self.check(&Token::Is) {
             self.consume(&Token::Is, "Expected 'is' after identifier")?;
         } else {
-            return Err(LumaError::parse_error("Expected 'be' or 'is' after identifier".to_string(), self.current_line()));
+            return Err(LumaError::parse_error("Expected 'be' or 'is' after identifier".to_string(), self.peek_span()));
         }
         
         let value = self.parse_expression()?;
+        let span = start.to(value.span);
         
-        Ok(Statement::Assignment { name, value })
+        Ok(Statement::new(StatementKind::Assignment { name, value }, span))
     }
 
     fn parse_variable_reassignment(&mut self) -> Result<Statement, LumaError> {
+        let start = self.peek_span();
         let name = if let Token::Identifier(name) = self.advance() {
             name.clone()
         } else {
-            return Err(LumaError::parse_error("Expected identifier".to_string(), self.current_line()));
+            return Err(LumaError::parse_error("Expected identifier".to_string(), self.previous_span()));
         };
         
         // Support both "is" and "=" for reassignment
@@ -97,24 +144,26 @@ impl Parser {
         } else if self.check(&Token::Assign) {
             self.consume(&Token::Assign, "Expected '=' after identifier")?;
         } else {
-            return Err(LumaError::parse_error("Expected 'is' or '=' after identifier".to_string(), self.current_line()));
+            return Err(LumaError::parse_error("Expected 'is' or '=' after identifier".to_string(), self.peek_span()));
         }
         
         let value = self.parse_expression()?;
+        let span = start.to(value.span);
         
-        Ok(Statement::Assignment { name, value })
+        Ok(Statement::new(StatementKind::Assignment { name, value }, span))
     }
 
     fn parse_show(&mut self) -> Result<Statement, LumaError> {
-        self.consume(&Token::Show, "Expected 'show'")?;
+        let start = self.consume(&Token::Show, "Expected 'show'")?;
         let expression = self.parse_expression()?;
-        Ok(Statement::Show(expression))
+        let span = start.to(expression.span);
+        Ok(Statement::new(StatementKind::Show(expression), span))
     }
 
     fn parse_if_statement(&mut self) -> Result<Statement, LumaError> {
-        self.consume(&Token::If, "Expected 'if'")?;
+        let start = self.consume(&Token::If, "Expected 'if'")?;
         let condition = self.parse_expression()?;
-        self.consume(&Token::Then, "Expected 'then' after if condition")?;
+        let header = start.to(self.consume(&Token::Then, "Expected 'then' after if condition")?);
         
         // Consume optional newline after "then"
         self.skip_newlines();
@@ -143,12 +192,15 @@ impl Parser {
             None
         };
         
-        Ok(Statement::If {
-            condition,
-            then_branch,
-            else_ifs,
-            else_branch,
-        })
+        Ok(Statement::new(
+            StatementKind::If {
+                condition,
+                then_branch,
+                else_ifs,
+                else_branch,
+            },
+            header,
+        ))
     }
 
     fn parse_if_block(&mut self) -> Result<Vec<Statement>, LumaError> {
@@ -170,10 +222,10 @@ impl Parser {
             // Parse statements that belong to this if block
             if self.check(&Token::Let) || self.check(&Token::Show) || 
                matches!(self.peek(), Token::Identifier(_)) {
-                statements.push(self.parse_statement()?);
+                statements.extend(self.parse_statement_recovering());
             } else if self.check(&Token::If) || self.check(&Token::While) || self.check(&Token::Repeat) {
                 // These create their own nested blocks
-                statements.push(self.parse_statement()?);
+                statements.extend(self.parse_statement_recovering());
             } else {
                 // End of this if block
                 break;
@@ -202,7 +254,7 @@ impl Parser {
                self.check(&Token::Repeat) || 
                matches!(self.peek(), Token::Identifier(_)) {
                 
-                statements.push(self.parse_statement()?);
+                statements.extend(self.parse_statement_recovering());
             } else {
                 // If we encounter an unrecognized token, break
                 break;
@@ -321,47 +373,59 @@ impl Parser {
 
     fn parse_unary(&mut self) -> Result<Expression, LumaError> {
         if self.check(&Token::Not) {
+            let start = self.peek_span();
             self.advance();
             let operand = self.parse_unary()?;
-            return Ok(Expression::UnaryOp {
-                operator: UnaryOperator::Not,
-                operand: Box::new(operand),
-            });
+            let span = start.to(operand.span);
+            return Ok(Expression::new(
+                ExpressionKind::UnaryOp {
+                    operator: UnaryOperator::Not,
+                    operand: Box::new(operand),
+                },
+                span,
+            ));
         }
         
         if self.check(&Token::Minus) {
+            let start = self.peek_span();
             self.advance();
             let operand = self.parse_unary()?;
-            return Ok(Expression::UnaryOp {
-                operator: UnaryOperator::Minus,
-                operand: Box::new(operand),
-            });
+            let span = start.to(operand.span);
+            return Ok(Expression::new(
+                ExpressionKind::UnaryOp {
+                    operator: UnaryOperator::Minus,
+                    operand: Box::new(operand),
+                },
+                span,
+            ));
         }
         
         self.parse_primary()
     }
 
     fn parse_primary(&mut self) -> Result<Expression, LumaError> {
+        let start = self.peek_span();
+
         if let Token::Number(n) = self.peek() {
             let number = *n;
             self.advance();
-            return Ok(Expression::Literal(number));
+            return Ok(Expression::new(ExpressionKind::Literal(number), start));
         }
         
         if let Token::String(s) = self.peek() {
             let string = s.clone();
             self.advance();
-            return Ok(Expression::StringLiteral(string));
+            return Ok(Expression::new(ExpressionKind::StringLiteral(string), start));
         }
         
         if self.check(&Token::True) {
             self.advance();
-            return Ok(Expression::BooleanLiteral(true));
+            return Ok(Expression::new(ExpressionKind::BooleanLiteral(true), start));
         }
         
         if self.check(&Token::False) {
             self.advance();
-            return Ok(Expression::BooleanLiteral(false));
+            return Ok(Expression::new(ExpressionKind::BooleanLiteral(false), start));
         }
         
         if let Token::Identifier(name) = self.peek() {
@@ -383,23 +447,25 @@ impl Parser {
                     }
                 }
                 
-                self.consume(&Token::RightParen, "Expected ')' after function arguments")?;
-                return Ok(Expression::FunctionCall { name, arguments });
+                let end = self.consume(&Token::RightParen, "Expected ')' after function arguments")?;
+                return Ok(Expression::new(ExpressionKind::FunctionCall { name, arguments }, start.to(end)));
             } else {
-                return Ok(Expression::Identifier(name));
+                return Ok(Expression::new(ExpressionKind::Identifier(name), start));
             }
         }
         
         if self.check(&Token::LeftParen) {
             self.advance(); // consume '('
-            let expr = self.parse_expression()?;
-            self.consume(&Token::RightParen, "Expected ')' after expression")?;
+            let mut expr = self.parse_expression()?;
+            let end = self.consume(&Token::RightParen, "Expected ')' after expression")?;
+            // Widen the span to include the parentheses
+            expr.span = start.to(end);
             return Ok(expr);
         }
         
         Err(LumaError::parse_error(
             format!("Expected expression, found '{}'", self.peek()),
-            self.current_line()
+            self.peek_span()
         ))
     }
 
@@ -415,7 +481,7 @@ impl Parser {
         if !self.is_at_end() {
             self.current += 1;
         }
-        self.previous()
+        &self.tokens[self.current - 1].token
     }
 
     fn is_at_end(&self) -> bool {
@@ -426,33 +492,38 @@ impl Parser {
         if self.current >= self.tokens.len() {
             &Token::Eof
         } else {
-            &self.tokens[self.current]
+            &self.tokens[self.current].token
         }
     }
 
-    fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+    fn peek_span(&self) -> Span {
+        match self.tokens.get(self.current).or(self.tokens.last()) {
+            Some(token) => token.span,
+            None => Span::default(),
+        }
     }
 
-    fn current_line(&self) -> usize {
-        self.current_line
+    fn previous_span(&self) -> Span {
+        self.tokens[self.current.saturating_sub(1)].span
     }
 
-    fn consume(&mut self, token_type: &Token, message: &str) -> Result<&Token, LumaError> {
+    /// Consume the expected token and return its span.
+    fn consume(&mut self, token_type: &Token, message: &str) -> Result<Span, LumaError> {
         if self.check(token_type) {
-            Ok(self.advance())
+            self.advance();
+            Ok(self.previous_span())
         } else {
             Err(LumaError::parse_error(
                 format!("{}: expected '{}', found '{}'", message, token_type, self.peek()),
-                self.current_line()
+                self.peek_span()
             ))
         }
     }
 
     fn parse_while_statement(&mut self) -> Result<Statement, LumaError> {
-        self.consume(&Token::While, "while statement")?;
+        let start = self.consume(&Token::While, "while statement")?;
         let condition = self.parse_expression()?;
-        self.consume(&Token::Then, "while statement (expected 'then' after condition)")?;
+        let header = start.to(self.consume(&Token::Then, "while statement (expected 'then' after condition)")?);
         
         // Consume newline after then
         if self.check(&Token::Newline) {
@@ -461,17 +532,14 @@ impl Parser {
         
         let body = self.parse_block()?;
         
-        Ok(Statement::While {
-            condition,
-            body,
-        })
+        Ok(Statement::new(StatementKind::While { condition, body }, header))
     }
 
     fn parse_repeat_statement(&mut self) -> Result<Statement, LumaError> {
-        self.consume(&Token::Repeat, "repeat statement")?;
+        let start = self.consume(&Token::Repeat, "repeat statement")?;
         let count = self.parse_expression()?;
         self.consume(&Token::Times, "repeat statement (expected 'times' after count)")?;
-        self.consume(&Token::Then, "repeat statement (expected 'then' after 'times')")?;
+        let header = start.to(self.consume(&Token::Then, "repeat statement (expected 'then' after 'times')")?);
         
         // Consume newline after then
         if self.check(&Token::Newline) {
@@ -480,10 +548,7 @@ impl Parser {
         
         let body = self.parse_block()?;
         
-        Ok(Statement::Repeat {
-            count,
-            body,
-        })
+        Ok(Statement::new(StatementKind::Repeat { count, body }, header))
     }
 }
 
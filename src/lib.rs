@@ -2,4 +2,5 @@
 pub mod frontend;
 pub mod backend;
 pub mod shared;
-pub mod ffi;
\ No newline at end of file
+pub mod ffi;
+pub mod debugger;
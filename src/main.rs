@@ -1,47 +1,244 @@
 use std::env;
 use std::fs;
-use std::time::Instant;
+use std::io::IsTerminal;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 mod frontend;
 mod backend;
 mod shared;
 mod ffi;
+mod debugger;
 
-use frontend::{Lexer, Parser, Compiler};
-use backend::vm::VM;
-use shared::{LumaError, Result};
+use frontend::{Lexer, Parser, Compiler, Statement};
+use backend::vm::{BufferOutput, CallbackOutput, InputLog, InstructionTracer, InterruptHandle, Output, RunLog, StderrOutput, StdoutOutput, VmConfig, VM};
+use backend::register::{RegisterCompiler, RegisterVM};
+use backend::Backend;
+use debugger::CliDebugger;
+use shared::bytecode_cache;
+use shared::{Chunk, Diagnostic, LumaError, Result, Value};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     
-    if args.len() == 1 {
-        run_repl();
-    } else if args.len() == 2 {
-        let filename = &args[1];
-        if let Err(e) = execute_file(filename) {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
+    let result = match args.get(1).map(String::as_str) {
+        None => {
+            run_repl();
+            Ok(())
         }
-    } else {
-        eprintln!("Usage: {} [script]", args[0]);
+        Some("compile") => match &args[2..] {
+            [file] => compile_file(file, None),
+            [file, flag, output] if flag == "-o" => compile_file(file, Some(output)),
+            _ => usage(&args[0]),
+        },
+        Some("--disassemble") => match &args[2..] {
+            [file] => disassemble_file(file),
+            _ => usage(&args[0]),
+        },
+        Some("debug") => match &args[2..] {
+            [file] => debug_file(file),
+            _ => usage(&args[0]),
+        },
+        Some("replay") => match &args[2..] {
+            [log] => replay_file(log),
+            _ => usage(&args[0]),
+        },
+        Some("dap") => match &args[2..] {
+            [] => debugger::dap::serve(std::io::stdin().lock(), std::io::stdout()),
+            _ => usage(&args[0]),
+        },
+        Some("run") => run_command(&args[0], &args[2..]),
+        Some(_) => run_command(&args[0], &args[1..]),
+    };
+    
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
         std::process::exit(1);
     }
 }
 
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {} [run] [options] <script>", program);
+    eprintln!("       {} compile <script> [-o <output.lumac>]", program);
+    eprintln!("       {} --disassemble <script>", program);
+    eprintln!("       {} debug <script>", program);
+    eprintln!("       {} replay <run.log>   (re-run a log written by --record)", program);
+    eprintln!("       {} dap   (Debug Adapter Protocol server on stdin/stdout)", program);
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --backend <stack|register>  Virtual machine to run on (default: stack)");
+    eprintln!("  --max-instructions <n>      Stop after executing n instructions");
+    eprintln!("  --timeout-ms <ms>           Stop after running for ms milliseconds");
+    eprintln!("  --max-memory <bytes>        Stop once strings take up more than bytes");
+    eprintln!("  --profile                   Print instructions and time spent per line");
+    eprintln!("  --profile-folded <file>     Write a per-line profile as folded stacks for flamegraphs");
+    eprintln!("  --coverage <file>           Write line and branch coverage as an lcov tracefile");
+    eprintln!("  --trace[=<from>-<to>]       Print each instruction and the stack to stderr,");
+    eprintln!("                              optionally only for source lines from..=to");
+    eprintln!("  --record <file>             Log the source, limits, timeouts, Ctrl-C and output for replay");
+    std::process::exit(1);
+}
+
+/// Settings for running a script, collected from the command line.
+struct RunOptions {
+    script: String,
+    backend: Backend,
+    max_instructions: Option<u64>,
+    timeout_ms: Option<u64>,
+    max_memory: Option<u64>,
+    profile: bool,
+    profile_folded: Option<String>,
+    coverage: Option<String>,
+    trace: Option<RangeInclusive<usize>>, // Lines to trace; `1..=usize::MAX` for all
+    record: Option<String>,
+}
+
+impl RunOptions {
+    fn parse(args: &[String]) -> std::result::Result<Self, String> {
+        let mut script = None;
+        let mut backend = Backend::default();
+        let mut max_instructions = None;
+        let mut timeout_ms = None;
+        let mut max_memory = None;
+        let mut profile = false;
+        let mut profile_folded = None;
+        let mut coverage = None;
+        let mut trace = None;
+        let mut record = None;
+        
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next().cloned())
+                    .ok_or_else(|| format!("{} expects a value", flag))
+            };
+            let mut number = || {
+                let value = value()?;
+                value.parse::<u64>().map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+            };
+            
+            match flag {
+                "--backend" => backend = value()?.parse()?,
+                "--max-instructions" => max_instructions = Some(number()?),
+                "--timeout-ms" => timeout_ms = Some(number()?),
+                "--max-memory" => max_memory = Some(number()?),
+                "--profile" => profile = true,
+                "--profile-folded" => profile_folded = Some(value()?),
+                "--coverage" => coverage = Some(value()?),
+                "--record" => record = Some(value()?),
+                "--trace" => {
+                    // The range is optional, so it can only be given inline
+                    trace = Some(match &inline_value {
+                        Some(range) => parse_line_range(range).ok_or_else(|| {
+                            format!("--trace expects a line range like 10-20, got '{}'", range)
+                        })?,
+                        None => 1..=usize::MAX,
+                    });
+                }
+                _ if flag.starts_with("--") => return Err(format!("unknown option '{}'", flag)),
+                _ if script.is_none() => script = Some(arg.clone()),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+        }
+        
+        let script = script.ok_or("no script given")?;
+        let limited = max_instructions.is_some() || timeout_ms.is_some() || max_memory.is_some();
+        if backend == Backend::Register && limited {
+            return Err("execution limits need the stack backend".to_string());
+        }
+        if backend == Backend::Register && (profile || profile_folded.is_some()) {
+            return Err("profiling needs the stack backend".to_string());
+        }
+        if backend == Backend::Register && coverage.is_some() {
+            return Err("coverage needs the stack backend".to_string());
+        }
+        if backend == Backend::Register && trace.is_some() {
+            return Err("tracing needs the stack backend".to_string());
+        }
+        if backend == Backend::Register && record.is_some() {
+            return Err("recording needs the stack backend".to_string());
+        }
+        if record.is_some() && script.ends_with(&format!(".{}", bytecode_cache::CACHE_EXTENSION)) {
+            return Err("recording needs a source script, not bytecode".to_string());
+        }
+        Ok(Self { script, backend, max_instructions, timeout_ms, max_memory, profile, profile_folded, coverage, trace, record })
+    }
+    
+    fn vm_config(&self) -> VmConfig {
+        VmConfig::default()
+            .with_max_instructions(self.max_instructions)
+            .with_timeout(self.timeout_ms.map(Duration::from_millis))
+            .with_max_memory(self.max_memory.map(|bytes| bytes as usize))
+            .with_line_profiling(self.profiling())
+            .with_coverage(self.coverage.is_some())
+    }
+    
+    fn new_vm(&self) -> VM {
+        let mut vm = VM::with_config(self.vm_config());
+        if let Some(lines) = &self.trace {
+            vm.set_tracer(Some(InstructionTracer::new(StderrOutput).with_lines(lines.clone())));
+        }
+        vm
+    }
+    
+    fn profiling(&self) -> bool {
+        self.profile || self.profile_folded.is_some()
+    }
+}
+
+// `N` for a single line or `N-M` for lines N through M
+fn parse_line_range(range: &str) -> Option<RangeInclusive<usize>> {
+    let (from, to) = range.split_once('-').unwrap_or((range, range));
+    let (from, to) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+    (from <= to).then_some(from..=to)
+}
+
+fn run_command(program: &str, args: &[String]) -> Result<()> {
+    let options = match RunOptions::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            usage(program);
+        }
+    };
+    
+    match options.backend {
+        Backend::Stack => execute_file(&options),
+        Backend::Register => execute_file_register(&options.script),
+    }
+}
+
 fn run_repl() {
     println!("Luma JIT-VM Language v0.2.0");
     println!("Type 'exit' to quit, 'help' for commands");
     
-    let mut vm = VM::new();
+    // Profile in the REPL so `stats` has something to report
+    let mut vm = VM::with_config(VmConfig::default().with_profiling(true));
+    let interrupt = vm.interrupt_handle();
+    interrupt_on_ctrl_c(interrupt.clone());
+    let mut last_input = String::new();
     
     loop {
         print!("luma> ");
-        if let Err(_) = std::io::Write::flush(&mut std::io::stdout()) {
+        if std::io::Write::flush(&mut std::io::stdout()).is_err() {
             break;
         }
         
         let mut input = String::new();
         match std::io::stdin().read_line(&mut input) {
+            Ok(0) => {
+                // End of input (Ctrl-D)
+                println!();
+                break;
+            }
             Ok(_) => {
                 let input = input.trim();
                 
@@ -60,13 +257,51 @@ fn run_repl() {
                     continue;
                 }
                 
+                if let Some(path) = input.strip_prefix(":save") {
+                    match path.trim() {
+                        "" => println!("Usage: :save <file>"),
+                        path => match vm.snapshot().and_then(|bytes| Ok(fs::write(path, bytes)?)) {
+                            Ok(()) => println!("Saved {} globals to {}", vm.global_names().count(), path),
+                            Err(e) => eprintln!("Error: {}", e),
+                        },
+                    }
+                    continue;
+                }
+                
+                if let Some(path) = input.strip_prefix(":load") {
+                    match path.trim() {
+                        "" => println!("Usage: :load <file>"),
+                        path => match fs::read(path).map_err(LumaError::from).and_then(|bytes| vm.restore(&bytes)) {
+                            Ok(()) => println!("Loaded {} globals from {}", vm.global_names().count(), path),
+                            Err(e) => eprintln!("Error: {}", e),
+                        },
+                    }
+                    continue;
+                }
+                
+                if let Some(code) = input.strip_prefix(":disasm") {
+                    // Without an argument, show the bytecode of the previous input
+                    let code = match code.trim() {
+                        "" => last_input.as_str(),
+                        code => code,
+                    };
+                    match compile_source(code) {
+                        Ok(chunk) => print!("{}", chunk.disassemble("<repl>")),
+                        Err(e) => report_error(&e, code, "<repl>", &global_names(&vm)),
+                    }
+                    continue;
+                }
+                
                 if input.is_empty() {
                     continue;
                 }
                 
+                // Forget a Ctrl-C pressed at the prompt
+                interrupt.clear();
                 if let Err(e) = execute_source_vm(input, &mut vm) {
-                    eprintln!("Error: {}", e);
+                    report_error(&e, input, "<repl>", &global_names(&vm));
                 }
+                last_input = input.to_string();
             }
             Err(e) => {
                 eprintln!("Error reading input: {}", e);
@@ -76,9 +311,43 @@ fn run_repl() {
     }
 }
 
-fn execute_file(filename: &str) -> Result<()> {
+// Make Ctrl-C stop the running program instead of the whole process
+#[cfg(unix)]
+fn interrupt_on_ctrl_c(handle: InterruptHandle) {
+    use std::os::raw::c_int;
+    use std::sync::OnceLock;
+    
+    const SIGINT: c_int = 2;
+    static HANDLE: OnceLock<InterruptHandle> = OnceLock::new();
+    
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+    
+    extern "C" fn on_sigint(_: c_int) {
+        if let Some(handle) = HANDLE.get() {
+            handle.interrupt();
+        }
+    }
+    
+    if HANDLE.set(handle).is_ok() {
+        unsafe {
+            signal(SIGINT, on_sigint);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn interrupt_on_ctrl_c(_handle: InterruptHandle) {}
+
+fn execute_file(options: &RunOptions) -> Result<()> {
+    let filename = options.script.as_str();
+    if Path::new(filename).extension().is_some_and(|ext| ext == bytecode_cache::CACHE_EXTENSION) {
+        return execute_bytecode_file(options);
+    }
+    
     let source = fs::read_to_string(filename)
-        .map_err(|e| LumaError::IoError(e))?;
+        .map_err(LumaError::IoError)?;
     
     if source.trim().is_empty() {
         println!("Code executed successfully!");
@@ -86,27 +355,267 @@ fn execute_file(filename: &str) -> Result<()> {
     }
     
     let start_time = Instant::now();
-    let mut vm = VM::new();
-    let result = execute_source_vm(&source, &mut vm);
+    let mut vm = options.new_vm();
+    let recorded_output = options.record.is_some().then(|| {
+        // Ctrl-C stops the script instead of the process, so the interrupt
+        // is recorded and the log still gets written
+        interrupt_on_ctrl_c(vm.interrupt_handle());
+        vm.set_input_log(Some(InputLog::record()));
+        tee_output(&mut vm)
+    });
+    let result = load_chunk(filename, &source).and_then(|chunk| vm.interpret(chunk));
     let execution_time = start_time.elapsed();
     
     // Print performance info
     println!("\n⚡ Execution time: {:.7}ms", execution_time.as_secs_f64() * 1000.0);
+    write_reports(&vm, &source, options)?;
+    if let (Some(path), Some(output)) = (&options.record, recorded_output) {
+        let log = run_log(&vm, filename, &source, &output, &result);
+        fs::write(path, log.encode()?)?;
+        eprintln!("Recorded run to {}", path);
+    }
+    
+    if let Err(e) = result {
+        report_error(&e, &source, filename, &global_names(&vm));
+        std::process::exit(1);
+    }
     
-    result
+    Ok(())
 }
 
-fn execute_source_vm(source: &str, vm: &mut VM) -> Result<()> {
-    // Frontend: Compile to bytecode
+// Compile and run a script on the register machine. It has its own
+// instruction set, so `.lumac` files (stack bytecode) cannot run here.
+fn execute_file_register(filename: &str) -> Result<()> {
+    if Path::new(filename).extension().is_some_and(|ext| ext == bytecode_cache::CACHE_EXTENSION) {
+        return Err(LumaError::BytecodeError(
+            "the register backend runs .luma sources, not .lumac bytecode".to_string(),
+        ));
+    }
+    
+    let source = fs::read_to_string(filename)?;
+    
+    if source.trim().is_empty() {
+        println!("Code executed successfully!");
+        return Ok(());
+    }
+    
+    let start_time = Instant::now();
+    let mut vm = RegisterVM::new();
+    let result = compile_statements(&source)
+        .and_then(|statements| RegisterCompiler::new().compile(&statements))
+        .and_then(|chunk| vm.interpret(&chunk));
+    let execution_time = start_time.elapsed();
+    
+    println!("\n⚡ Execution time: {:.7}ms", execution_time.as_secs_f64() * 1000.0);
+    
+    if let Err(e) = result {
+        report_error(&e, &source, filename, &vm.global_names().collect::<Vec<_>>());
+        std::process::exit(1);
+    }
+    
+    Ok(())
+}
+
+// Run a precompiled `.lumac` file; without the source, errors have no snippet
+fn execute_bytecode_file(options: &RunOptions) -> Result<()> {
+    let filename = options.script.as_str();
+    let (_, chunk) = bytecode_cache::read_cache(filename)?;
+    let mut vm = options.new_vm();
+    let result = vm.interpret(chunk);
+    write_reports(&vm, "", options)?;
+    if let Err(e) = result {
+        report_error(&e, "", filename, &global_names(&vm));
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Run a script under the interactive debugger on stdin/stdout. Always
+// compiles from source so paused lines can be shown.
+fn debug_file(filename: &str) -> Result<()> {
+    let source = fs::read_to_string(filename)?;
+    let chunk = match compile_source(&source) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            report_error(&e, &source, filename, &[]);
+            std::process::exit(1);
+        }
+    };
+    
+    let mut vm = VM::new();
+    let debugger = CliDebugger::new(&source, std::io::stdin().lock(), std::io::stdout());
+    vm.set_debug_hook(Some(Box::new(debugger)));
+    match vm.interpret(chunk) {
+        Ok(_) => println!("Program finished"),
+        // `quit` at the debugger prompt
+        Err(e) if matches!(e.cause(), LumaError::Interrupted { .. }) => println!("Program stopped"),
+        Err(e) => {
+            report_error(&e, &source, filename, &global_names(&vm));
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+// Run a recorded script again with the recorded limits and inputs, and
+// check it shows exactly what it showed then
+fn replay_file(filename: &str) -> Result<()> {
+    let recording = RunLog::decode(&fs::read(filename)?)?;
+    let mut vm = VM::with_config(recording.config());
+    vm.set_input_log(Some(InputLog::replay(recording.inputs.clone())));
+    let output = tee_output(&mut vm);
+    let result = compile_source(&recording.source).and_then(|chunk| vm.interpret(chunk));
+    if let Err(e) = &result {
+        report_error(e, &recording.source, &recording.script, &global_names(&vm));
+    }
+    
+    let replayed = run_log(&vm, &recording.script, &recording.source, &output, &result);
+    if let Some(difference) = recording.divergence(&replayed) {
+        eprintln!("Replay of {} diverged from the recording: {}", recording.script, difference);
+        std::process::exit(2);
+    }
+    eprintln!("Replay of {} matched the recording", recording.script);
+    if result.is_err() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Print program output as usual while keeping a copy for a run log
+fn tee_output(vm: &mut VM) -> BufferOutput {
+    let copy = BufferOutput::new();
+    let mut writer = copy.clone();
+    vm.set_output(CallbackOutput::new(move |line| {
+        let _ = StdoutOutput.write_line(line);
+        let _ = writer.write_line(line);
+    }));
+    copy
+}
+
+fn run_log(vm: &VM, script: &str, source: &str, output: &BufferOutput, result: &Result<Value>) -> RunLog {
+    let mut log = RunLog::new(script, source, vm.config());
+    log.inputs = vm.input_log().map_or_else(Vec::new, |inputs| inputs.events().to_vec());
+    log.output = output.contents();
+    log.error = result.as_ref().err().map(ToString::to_string);
+    log
+}
+
+// Print the line profile to stderr (keeping stdout for the program) and
+// write the folded stacks and coverage, as the options ask
+fn write_reports(vm: &VM, source: &str, options: &RunOptions) -> Result<()> {
+    if let Some(profile) = vm.line_profile() {
+        if options.profile {
+            eprint!("\n{}", profile.report(source));
+        }
+        if let Some(path) = &options.profile_folded {
+            let root = Path::new(&options.script).file_name().map_or("script".into(), |name| name.to_string_lossy());
+            fs::write(path, profile.folded(&root))?;
+            eprintln!("Wrote folded stacks to {}", path);
+        }
+    }
+    if let (Some(coverage), Some(path)) = (vm.coverage(), &options.coverage) {
+        fs::write(path, coverage.lcov(&options.script))?;
+        eprintln!("Wrote coverage to {}", path);
+    }
+    Ok(())
+}
+
+// Use the script's `.lumac` cache when it was built from this exact source by
+// this compiler; otherwise compile, refreshing a stale cache if there was one
+fn load_chunk(filename: &str, source: &str) -> Result<Chunk> {
+    let cache = bytecode_cache::cache_path(filename);
+    if let Some(chunk) = bytecode_cache::load_fresh(&cache, source) {
+        return Ok(chunk);
+    }
+    
+    let chunk = compile_source(source)?;
+    if cache.exists() {
+        if let Err(e) = bytecode_cache::write_cache(&cache, &chunk, source) {
+            eprintln!("Warning: could not update {}: {}", cache.display(), e);
+        }
+    }
+    Ok(chunk)
+}
+
+fn compile_file(filename: &str, output: Option<&str>) -> Result<()> {
+    let source = fs::read_to_string(filename)?;
+    let chunk = match compile_source(&source) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            report_error(&e, &source, filename, &[]);
+            std::process::exit(1);
+        }
+    };
+    
+    let output = match output {
+        Some(path) => path.into(),
+        None => bytecode_cache::cache_path(filename),
+    };
+    bytecode_cache::write_cache(&output, &chunk, &source)?;
+    println!("Compiled {} -> {}", filename, output.display());
+    Ok(())
+}
+
+// Print the bytecode for a script, or for a precompiled `.lumac` file
+fn disassemble_file(filename: &str) -> Result<()> {
+    let chunk = if Path::new(filename).extension().is_some_and(|ext| ext == bytecode_cache::CACHE_EXTENSION) {
+        bytecode_cache::read_cache(filename)?.1
+    } else {
+        let source = fs::read_to_string(filename)?;
+        match compile_source(&source) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                report_error(&e, &source, filename, &[]);
+                std::process::exit(1);
+            }
+        }
+    };
+    
+    print!("{}", chunk.disassemble(filename));
+    Ok(())
+}
+
+// Variable names offered as "did you mean" suggestions
+fn global_names(vm: &VM) -> Vec<&str> {
+    vm.global_names().collect()
+}
+
+fn report_error(error: &LumaError, source: &str, file_name: &str, known_names: &[&str]) {
+    // Plain text when piped (e.g. the web playground) or when NO_COLOR is set
+    let color = std::io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    
+    let errors = error.errors();
+    for (i, error) in errors.iter().enumerate() {
+        if i > 0 {
+            eprintln!();
+        }
+        let diagnostic = Diagnostic::from_error(error, known_names.iter().copied());
+        eprint!("{}", diagnostic.render(source, file_name, color));
+    }
+    
+    if errors.len() > 1 {
+        eprintln!("\n{} errors found", errors.len());
+    }
+}
+
+fn compile_statements(source: &str) -> Result<Vec<Statement>> {
     let mut lexer = Lexer::new(source);
     let tokens = lexer.tokenize()?;
     
     let mut parser = Parser::new(tokens);
-    let statements = parser.parse()?;
+    parser.parse()
+}
+
+fn compile_source(source: &str) -> Result<Chunk> {
+    let statements = compile_statements(source)?;
     
     let mut compiler = Compiler::new();
-    // Pass source code to compiler for accurate line tracking
-    let chunk = compiler.compile_with_source(&statements, source)?;
+    compiler.compile(&statements)
+}
+
+fn execute_source_vm(source: &str, vm: &mut VM) -> Result<()> {
+    // Frontend: Compile to bytecode
+    let chunk = compile_source(source)?;
     
     // Backend: Execute on VM
     vm.interpret(chunk)?;
@@ -118,7 +627,11 @@ fn print_help() {
     println!("Luma JIT-VM Language REPL Commands:");
     println!("  help, :help    - Show this help message");
     println!("  stats, :stats  - Show execution statistics");
-    println!("  exit, quit, :q - Exit the REPL");
+    println!("  :disasm [code] - Show bytecode for code (default: previous input)");
+    println!("  :save <file>   - Save all variables to a snapshot file");
+    println!("  :load <file>   - Replace all variables with those in a snapshot file");
+    println!("  exit, quit, :q - Exit the REPL (or Ctrl-D)");
+    println!("  Ctrl-C         - Stop the running program, keeping variables");
     println!();
     println!("Language Syntax:");
     println!("  let <name> be <value>  - Assign value to variable");
@@ -143,6 +656,9 @@ fn print_help() {
 }
 
 fn print_stats(vm: &VM) {
+    let memory = vm.memory_usage();
+    println!("String memory: {} bytes (peak {} bytes)", memory.current, memory.peak);
+    
     let stats = vm.get_execution_stats();
     
     if stats.is_empty() {
@@ -151,10 +667,10 @@ fn print_stats(vm: &VM) {
     }
     
     println!("Execution Statistics (Top 10 Hot Spots):");
-    println!("{:<10} {:<15} {}", "Offset", "Executions", "Status");
+    println!("{:<10} {:<15} Status", "Offset", "Executions");
     println!("{:-<40}", "");
     
-    for (_i, (offset, count)) in stats.iter().take(10).enumerate() {
+    for (offset, count) in stats.iter().take(10) {
         let status = if *count > 1000 {
             "HOT - JIT Candidate"
         } else if *count > 100 {
@@ -0,0 +1,421 @@
+use crate::backend::vm::OpCode;
+use crate::shared::{Chunk, LumaError, Result, Span, Value};
+use std::collections::HashMap;
+
+/// Build a chunk from bytecode assembly.
+///
+/// The input format is the output of `Chunk::disassemble`, so disassembled
+/// chunks assemble back to an identical chunk. For hand-written code it
+/// also accepts:
+///
+/// ```text
+/// .const limit 3          ; named constant, usable as an operand
+/// .const i "i"
+/// .line 2                 ; span for instructions without a position
+///     OpConstant 0        ; constant pool index...
+///     OpDefineGlobal i    ; ...or a named constant
+/// top:                    ; label, usable as a jump operand
+///     OpGetGlobal i
+///     OpConstant limit
+///     OpLess
+///     OpJumpIfFalse done
+///     OpPop
+///     OpLoop top
+/// done:
+///     OpReturn
+/// ```
+///
+/// Offsets at the start of instruction lines are informational and
+/// ignored, as is anything after an instruction's operand.
+#[allow(dead_code)]
+pub fn assemble(text: &str) -> Result<Chunk> {
+    let mut assembler = Assembler::default();
+    for (index, line) in text.lines().enumerate() {
+        assembler.line = index + 1;
+        assembler.line_length = line.len();
+        assembler.assemble_line(line)?;
+    }
+    assembler.finish()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Constants,
+    Globals,
+    Code,
+}
+
+// A jump whose operand names a label that may not be defined yet
+struct PendingJump {
+    opcode_offset: usize,
+    label: String,
+    line: usize,
+}
+
+struct Assembler {
+    chunk: Chunk,
+    section: Section,
+    named_constants: HashMap<String, usize>,
+    labels: HashMap<String, usize>,
+    pending_jumps: Vec<PendingJump>,
+    default_span: Span, // Set by `.line`, used by instructions without a position
+    previous_span: Span, // Span of the last instruction, used by `|`
+    line: usize,
+    line_length: usize,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self {
+            chunk: Chunk::new(),
+            section: Section::Code,
+            named_constants: HashMap::new(),
+            labels: HashMap::new(),
+            pending_jumps: Vec::new(),
+            default_span: Span::default(),
+            previous_span: Span::default(),
+            line: 0,
+            line_length: 0,
+        }
+    }
+}
+
+impl Assembler {
+    fn assemble_line(&mut self, line: &str) -> Result<()> {
+        let trimmed = line.trim();
+        if trimmed.starts_with("==") {
+            return Ok(());
+        }
+        if let Some(header) = trimmed.strip_prefix("--").and_then(|h| h.strip_suffix("--")) {
+            self.section = match header.trim() {
+                "constants" => Section::Constants,
+                "globals" => Section::Globals,
+                "code" => Section::Code,
+                other => return Err(self.error(format!("unknown section '{}'", other))),
+            };
+            return Ok(());
+        }
+
+        let tokens = self.tokenize(line)?;
+        let Some(first) = tokens.first() else {
+            return Ok(());
+        };
+
+        if first.starts_with('.') {
+            return self.directive(&tokens);
+        }
+
+        match self.section {
+            Section::Constants => self.constant_entry(&tokens),
+            Section::Globals => self.global_entry(&tokens),
+            Section::Code => self.code_line(&tokens),
+        }
+    }
+
+    // `   3  string   "name"` as printed by the disassembler
+    fn constant_entry(&mut self, tokens: &[String]) -> Result<()> {
+        let index = self.number_token(&tokens[0], "constant index")?;
+        if index != self.chunk.constants.len() {
+            return Err(self.error(format!(
+                "constant {} is out of order (expected {})",
+                index,
+                self.chunk.constants.len()
+            )));
+        }
+
+        let kind = tokens.get(1).ok_or_else(|| self.error("missing constant type".to_string()))?;
+        let literal = tokens.get(2).map(String::as_str).unwrap_or("");
+        let value = match kind.as_str() {
+            "number" => Value::Number(self.parse_number(literal)?),
+            "string" => Value::string(self.parse_string(literal)?),
+            "boolean" => Value::Boolean(self.parse_bool(literal)?),
+            "nil" => Value::Nil,
+            other => return Err(self.error(format!("unknown constant type '{}'", other))),
+        };
+
+        // Pushed directly so duplicates in the pool survive the round trip
+        self.chunk.constants.push(value);
+        Ok(())
+    }
+
+    fn global_entry(&mut self, tokens: &[String]) -> Result<()> {
+        let index = self.number_token(&tokens[0], "global index")?;
+        let name = tokens.get(1).ok_or_else(|| self.error("missing global name".to_string()))?;
+        self.chunk.globals.insert(name.clone(), index);
+        Ok(())
+    }
+
+    fn directive(&mut self, tokens: &[String]) -> Result<()> {
+        match tokens[0].as_str() {
+            ".const" => {
+                let (Some(name), Some(literal)) = (tokens.get(1), tokens.get(2)) else {
+                    return Err(self.error("expected `.const <name> <value>`".to_string()));
+                };
+                let value = self.parse_literal(literal)?;
+                let index = self.chunk.add_constant(value);
+                self.named_constants.insert(name.clone(), index);
+            }
+            ".line" => {
+                let position = tokens
+                    .get(1)
+                    .ok_or_else(|| self.error("expected `.line <line>[:<column>+<length>]`".to_string()))?;
+                self.default_span = match parse_position(position) {
+                    Some(span) => span,
+                    None => Span::new(self.number_token(position, "line")?, 1, 0),
+                };
+            }
+            other => return Err(self.error(format!("unknown directive '{}'", other))),
+        }
+        Ok(())
+    }
+
+    fn code_line(&mut self, tokens: &[String]) -> Result<()> {
+        let mut tokens = tokens.iter().map(String::as_str).peekable();
+
+        // A lone `name:` defines a label at the current offset
+        if let Some(label) = tokens.peek().and_then(|t| t.strip_suffix(':')) {
+            if is_identifier(label) {
+                if self.labels.insert(label.to_string(), self.chunk.code.len()).is_some() {
+                    return Err(self.error(format!("label '{}' is defined twice", label)));
+                }
+                return Ok(());
+            }
+        }
+
+        // Optional offset and position columns from disassembler output
+        if tokens.peek().is_some_and(|t| t.bytes().all(|b| b.is_ascii_digit())) {
+            tokens.next();
+        }
+        let span = match tokens.peek() {
+            Some(&"|") => {
+                tokens.next();
+                self.previous_span
+            }
+            Some(token) => match parse_position(token) {
+                Some(span) => {
+                    tokens.next();
+                    span
+                }
+                None => self.default_span,
+            },
+            None => return Err(self.error("expected an instruction".to_string())),
+        };
+
+        let mnemonic = tokens.next().ok_or_else(|| self.error("expected an instruction".to_string()))?;
+        self.previous_span = span;
+
+        // Undecodable bytes are disassembled as `Unknown opcode N`
+        if mnemonic == "Unknown" && tokens.next() == Some("opcode") {
+            let byte = tokens.next().unwrap_or("");
+            let byte = self.byte_operand(byte)?;
+            self.chunk.write_byte(byte, span);
+            return Ok(());
+        }
+
+        let opcode = opcode_named(mnemonic)
+            .ok_or_else(|| self.error(format!("unknown instruction '{}'", mnemonic)))?;
+        let opcode_offset = self.chunk.code.len();
+        self.chunk.write_opcode(opcode, span);
+
+        if opcode.operand_count() == 0 {
+            return Ok(());
+        }
+
+        let operand = match tokens.next() {
+            // A truncated instruction at the end of a malformed chunk
+            Some("<missing") => return Ok(()),
+            Some(operand) => operand,
+            None => return Err(self.error(format!("{:?} expects an operand", opcode))),
+        };
+
+        let byte = if operand.bytes().all(|b| b.is_ascii_digit()) {
+            self.byte_operand(operand)?
+        } else {
+            match opcode {
+                OpCode::OpJump | OpCode::OpJumpIfFalse | OpCode::OpLoop => {
+                    self.pending_jumps.push(PendingJump {
+                        opcode_offset,
+                        label: operand.to_string(),
+                        line: self.line,
+                    });
+                    0
+                }
+                _ => {
+                    let index = *self
+                        .named_constants
+                        .get(operand)
+                        .ok_or_else(|| self.error(format!("unknown constant '{}'", operand)))?;
+                    self.byte_operand(&index.to_string())?
+                }
+            }
+        };
+        self.chunk.write_byte(byte, span);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Chunk> {
+        for jump in std::mem::take(&mut self.pending_jumps) {
+            self.line = jump.line;
+            let target = *self
+                .labels
+                .get(&jump.label)
+                .ok_or_else(|| self.error(format!("unknown label '{}'", jump.label)))?;
+
+            // Jump distances are measured from the end of the instruction
+            let next = jump.opcode_offset + 2;
+            let distance = if self.chunk.code[jump.opcode_offset] == OpCode::OpLoop.to_byte() {
+                next.checked_sub(target)
+            } else {
+                target.checked_sub(next)
+            };
+            match distance {
+                Some(distance) if distance <= u8::MAX as usize => {
+                    self.chunk.code[jump.opcode_offset + 1] = distance as u8;
+                }
+                _ => {
+                    return Err(self.error(format!("label '{}' is out of range for this jump", jump.label)));
+                }
+            }
+        }
+        Ok(self.chunk)
+    }
+
+    // Split a line into whitespace-separated tokens, keeping quoted strings
+    // (with their escapes) whole and dropping `;` comments
+    fn tokenize(&self, line: &str) -> Result<Vec<String>> {
+        let mut tokens = Vec::new();
+        let mut chars = line.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == ';' {
+                break;
+            } else if c == '"' {
+                let mut token = String::new();
+                token.push(chars.next().unwrap());
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    token.push(c);
+                    if c == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            token.push(escaped);
+                        }
+                    } else if c == '"' {
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return Err(self.error("unterminated string".to_string()));
+                }
+                tokens.push(token);
+            } else {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    // Value of a `.const` literal, with its type inferred from the text
+    fn parse_literal(&self, literal: &str) -> Result<Value> {
+        match literal {
+            "true" | "false" => Ok(Value::Boolean(literal == "true")),
+            "nil" => Ok(Value::Nil),
+            _ if literal.starts_with('"') => Ok(Value::string(self.parse_string(literal)?)),
+            _ => Ok(Value::Number(self.parse_number(literal)?)),
+        }
+    }
+
+    fn parse_number(&self, literal: &str) -> Result<f64> {
+        literal
+            .parse()
+            .map_err(|_| self.error(format!("invalid number '{}'", literal)))
+    }
+
+    fn parse_bool(&self, literal: &str) -> Result<bool> {
+        literal
+            .parse()
+            .map_err(|_| self.error(format!("invalid boolean '{}'", literal)))
+    }
+
+    // Undo the escaping of Rust's `{:?}` formatting for strings
+    fn parse_string(&self, literal: &str) -> Result<String> {
+        let inner = literal
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .ok_or_else(|| self.error(format!("expected a quoted string, found '{}'", literal)))?;
+
+        let mut result = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            let escaped = match chars.next() {
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some(c @ ('\\' | '"' | '\'')) => c,
+                Some('u') => {
+                    let hex: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                    u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| self.error(format!("invalid unicode escape '\\u{{{}}}'", hex)))?
+                }
+                other => {
+                    return Err(self.error(format!("invalid escape '\\{}'", other.unwrap_or(' '))));
+                }
+            };
+            result.push(escaped);
+        }
+        Ok(result)
+    }
+
+    fn number_token(&self, token: &str, what: &str) -> Result<usize> {
+        token
+            .parse()
+            .map_err(|_| self.error(format!("invalid {} '{}'", what, token)))
+    }
+
+    fn byte_operand(&self, token: &str) -> Result<u8> {
+        token
+            .parse()
+            .map_err(|_| self.error(format!("operand '{}' does not fit in a byte", token)))
+    }
+
+    fn error(&self, message: String) -> LumaError {
+        LumaError::parse_error(message, Span::new(self.line, 1, self.line_length))
+    }
+}
+
+// `line:column+length` or `line:column`, the span syntax of the disassembler
+fn parse_position(token: &str) -> Option<Span> {
+    let (line, rest) = token.split_once(':')?;
+    let (column, length) = rest.split_once('+').unwrap_or((rest, "0"));
+    Some(Span::new(line.parse().ok()?, column.parse().ok()?, length.parse().ok()?))
+}
+
+fn opcode_named(name: &str) -> Option<OpCode> {
+    (0..=u8::MAX)
+        .filter_map(OpCode::from_byte)
+        .find(|opcode| format!("{:?}", opcode) == name)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -0,0 +1,160 @@
+use crate::backend::vm::verify;
+use crate::shared::{Chunk, LumaError, Result};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+/// Every `.lumac` file starts with these bytes.
+pub const MAGIC: &[u8; 6] = b"LUMAC\0";
+
+/// Bumped whenever the layout of the header or of `Chunk` changes.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Version of the compiler that produced a cache file.
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Bumped whenever the bytecode a given source compiles to changes, so
+/// caches written by an older compiler of the same release are rebuilt.
+///
+/// 1. Initial cache format
+/// 2. `show` pops its value; `repeat` stores its loop bound
+/// 3. `else if` branches are compiled instead of dropped
+pub const CODEGEN_VERSION: u32 = 3;
+
+pub const CACHE_EXTENSION: &str = "lumac";
+
+/// Metadata stored after the magic bytes, ahead of the serialized chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheHeader {
+    pub format_version: u32,
+    pub compiler_version: String,
+    pub codegen_version: u32,
+    pub source_hash: u64,
+}
+
+impl CacheHeader {
+    pub fn for_source(source: &str) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            compiler_version: COMPILER_VERSION.to_string(),
+            codegen_version: CODEGEN_VERSION,
+            source_hash: hash_source(source),
+        }
+    }
+
+    /// Whether a chunk with this header can stand in for compiling `source`.
+    pub fn is_fresh_for(&self, source: &str) -> bool {
+        *self == CacheHeader::for_source(source)
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of the source text. Unlike `DefaultHasher`, the
+/// result does not depend on the Rust version that built the compiler.
+pub fn hash_source(source: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    source.bytes().fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// Default cache location for a script: `script.luma` -> `script.lumac`.
+pub fn cache_path(source_path: impl AsRef<Path>) -> PathBuf {
+    source_path.as_ref().with_extension(CACHE_EXTENSION)
+}
+
+/// Serialize a compiled chunk together with the header describing its source.
+pub fn encode(chunk: &Chunk, source: &str) -> Result<Vec<u8>> {
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &CacheHeader::for_source(source))?;
+    bincode::serialize_into(&mut bytes, chunk)?;
+    Ok(bytes)
+}
+
+/// Read the header of a cache file without decoding the chunk.
+pub fn decode_header(bytes: &[u8]) -> Result<CacheHeader> {
+    let mut reader = Cursor::new(bytes);
+    read_header(&mut reader)
+}
+
+/// Decode a cache file, rejecting files from other format versions since
+/// their chunk layout cannot be trusted.
+pub fn decode(bytes: &[u8]) -> Result<(CacheHeader, Chunk)> {
+    // The version leads the header, so check it before decoding the rest,
+    // whose layout may differ in other versions
+    let mut reader = Cursor::new(bytes);
+    read_magic(&mut reader)?;
+    let format_version: u32 = bounded(bytes).deserialize_from(&mut reader)?;
+    if format_version != FORMAT_VERSION {
+        return Err(LumaError::BytecodeError(format!(
+            "unsupported bytecode format version {} (expected {})",
+            format_version, FORMAT_VERSION
+        )));
+    }
+
+    let mut reader = Cursor::new(bytes);
+    let header = read_header(&mut reader)?;
+    let chunk = bounded(bytes).deserialize_from(&mut reader).map_err(corrupt)?;
+    Ok((header, chunk))
+}
+
+/// bincode's default settings, except that no length prefix may claim more
+/// bytes than `bytes` holds. A corrupt or hostile file then fails to decode
+/// instead of asking for a huge allocation, which would abort the process.
+pub(crate) fn bounded(bytes: &[u8]) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bytes.len() as u64)
+}
+
+fn read_header(reader: &mut Cursor<&[u8]>) -> Result<CacheHeader> {
+    let bytes = *reader.get_ref();
+    read_magic(reader)?;
+    bounded(bytes).deserialize_from(reader).map_err(corrupt)
+}
+
+// Name the likely cause when a file's lengths do not fit its size
+fn corrupt(error: bincode::Error) -> LumaError {
+    match *error {
+        bincode::ErrorKind::SizeLimit => {
+            LumaError::BytecodeError("corrupt bytecode file: a length runs past the end of the file".to_string())
+        }
+        bincode::ErrorKind::Io(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            LumaError::BytecodeError("truncated bytecode file".to_string())
+        }
+        _ => error.into(),
+    }
+}
+
+fn read_magic(reader: &mut Cursor<&[u8]>) -> Result<()> {
+    let mut magic = [0u8; MAGIC.len()];
+    if reader.read_exact(&mut magic).is_err() || &magic != MAGIC {
+        return Err(LumaError::BytecodeError("not a Luma bytecode file".to_string()));
+    }
+    Ok(())
+}
+
+pub fn write_cache(path: impl AsRef<Path>, chunk: &Chunk, source: &str) -> Result<()> {
+    fs::write(path, encode(chunk, source)?)?;
+    Ok(())
+}
+
+pub fn read_cache(path: impl AsRef<Path>) -> Result<(CacheHeader, Chunk)> {
+    decode(&fs::read(path)?)
+}
+
+/// Load the cached chunk for `source` if the cache exists, was produced
+/// from exactly this source by this compiler, and passes verification.
+/// Any mismatch, unreadable file or invalid chunk yields `None` so the
+/// caller can fall back to compiling.
+pub fn load_fresh(path: impl AsRef<Path>, source: &str) -> Option<Chunk> {
+    let bytes = fs::read(path).ok()?;
+    if !decode_header(&bytes).ok()?.is_fresh_for(source) {
+        return None;
+    }
+    let (_, chunk) = decode(&bytes).ok()?;
+    verify(&chunk).ok()?;
+    Some(chunk)
+}
@@ -1,14 +1,83 @@
 use crate::backend::vm::OpCode;
-use crate::shared::Value;
+use crate::shared::{SharedStr, Span, Value};
 use serde::{Deserialize, Serialize};
+use hashbrown::Equivalent;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
     pub code: Vec<u8>,
     pub constants: Vec<Value>,
-    pub lines: Vec<usize>,
+    pub lines: Vec<Span>, // Source span of each byte in `code`
     pub globals: HashMap<String, usize>, // Variable name -> constant pool index
+    #[serde(skip)]
+    constant_index: hashbrown::HashMap<ConstantKey, usize>, // Constant identity -> constant pool index
+    #[serde(skip)]
+    indexed_constants: usize, // Number of constants already recorded in constant_index
+}
+
+/// Hashable identity of a constant. Numbers are keyed by their bit pattern so
+/// `0.0` and `-0.0` stay distinct and identical NaNs share one slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConstantKey {
+    Number(u64),
+    String(SharedStr),
+    Boolean(bool),
+    Nil,
+}
+
+/// Borrowed form of `ConstantKey`, used for lookups without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ConstantRef<'a> {
+    Number(u64),
+    String(&'a str),
+    Boolean(bool),
+    Nil,
+}
+
+impl ConstantKey {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Number(n) => ConstantKey::Number(n.to_bits()),
+            Value::String(s) => ConstantKey::String(s.clone()),
+            Value::Boolean(b) => ConstantKey::Boolean(*b),
+            Value::Nil => ConstantKey::Nil,
+        }
+    }
+
+    fn as_ref(&self) -> ConstantRef<'_> {
+        match self {
+            ConstantKey::Number(bits) => ConstantRef::Number(*bits),
+            ConstantKey::String(s) => ConstantRef::String(s),
+            ConstantKey::Boolean(b) => ConstantRef::Boolean(*b),
+            ConstantKey::Nil => ConstantRef::Nil,
+        }
+    }
+}
+
+impl<'a> ConstantRef<'a> {
+    fn of(value: &'a Value) -> Self {
+        match value {
+            Value::Number(n) => ConstantRef::Number(n.to_bits()),
+            Value::String(s) => ConstantRef::String(s),
+            Value::Boolean(b) => ConstantRef::Boolean(*b),
+            Value::Nil => ConstantRef::Nil,
+        }
+    }
+}
+
+// Both forms must hash identically for borrowed lookups to find owned keys
+impl Hash for ConstantKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
+impl Equivalent<ConstantKey> for ConstantRef<'_> {
+    fn equivalent(&self, key: &ConstantKey) -> bool {
+        *self == key.as_ref()
+    }
 }
 
 impl Chunk {
@@ -18,35 +87,63 @@ impl Chunk {
             constants: Vec::new(),
             lines: Vec::new(),
             globals: HashMap::new(),
+            constant_index: hashbrown::HashMap::new(),
+            indexed_constants: 0,
         }
     }
 
-    pub fn write_byte(&mut self, byte: u8, line: usize) {
+    pub fn write_byte(&mut self, byte: u8, span: Span) {
         self.code.push(byte);
-        self.lines.push(line);
+        self.lines.push(span);
     }
 
-    pub fn write_opcode(&mut self, opcode: OpCode, line: usize) {
-        self.write_byte(opcode.to_byte(), line);
+    pub fn write_opcode(&mut self, opcode: OpCode, span: Span) {
+        self.write_byte(opcode.to_byte(), span);
     }
 
     pub fn add_constant(&mut self, value: Value) -> usize {
-        // Check if constant already exists to avoid duplicates
-        for (i, existing) in self.constants.iter().enumerate() {
-            if *existing == value {
-                return i;
-            }
+        self.sync_constant_index();
+
+        if let Some(&index) = self.constant_index.get(&ConstantRef::of(&value)) {
+            return index;
         }
-        
+
+        let index = self.constants.len();
+        self.constant_index.insert(ConstantKey::of(&value), index);
         self.constants.push(value);
-        self.constants.len() - 1
+        self.indexed_constants = self.constants.len();
+        index
+    }
+
+    /// Add a string constant, reusing the existing slot if the text was seen before.
+    pub fn intern_string(&mut self, s: &str) -> usize {
+        self.sync_constant_index();
+
+        if let Some(&index) = self.constant_index.get(&ConstantRef::String(s)) {
+            return index;
+        }
+
+        self.add_constant(Value::string(s))
+    }
+
+    // The index is not serialized, and `constants` is public, so catch up on
+    // any entries added behind our back (e.g. after loading a cached chunk)
+    fn sync_constant_index(&mut self) {
+        if self.indexed_constants == self.constants.len() {
+            return;
+        }
+        for i in self.indexed_constants..self.constants.len() {
+            let key = ConstantKey::of(&self.constants[i]);
+            self.constant_index.entry(key).or_insert(i);
+        }
+        self.indexed_constants = self.constants.len();
     }
 
     #[allow(dead_code)]
-    pub fn write_constant(&mut self, value: Value, line: usize) -> usize {
+    pub fn write_constant(&mut self, value: Value, span: Span) -> usize {
         let constant_index = self.add_constant(value);
-        self.write_opcode(OpCode::OpConstant, line);
-        self.write_byte(constant_index as u8, line);
+        self.write_opcode(OpCode::OpConstant, span);
+        self.write_byte(constant_index as u8, span);
         constant_index
     }
 
@@ -58,19 +155,19 @@ impl Chunk {
         self.code[offset] = jump as u8;
     }
 
-    pub fn emit_jump(&mut self, opcode: OpCode, line: usize) -> usize {
-        self.write_opcode(opcode, line);
-        self.write_byte(0, line); // Placeholder for jump offset
+    pub fn emit_jump(&mut self, opcode: OpCode, span: Span) -> usize {
+        self.write_opcode(opcode, span);
+        self.write_byte(0, span); // Placeholder for jump offset
         self.code.len() - 1
     }
 
-    pub fn emit_loop(&mut self, loop_start: usize, line: usize) {
-        self.write_opcode(OpCode::OpLoop, line);
+    pub fn emit_loop(&mut self, loop_start: usize, span: Span) {
+        self.write_opcode(OpCode::OpLoop, span);
         let offset = self.code.len() - loop_start + 1;
         if offset > u8::MAX as usize {
             panic!("Loop body too large");
         }
-        self.write_byte(offset as u8, line);
+        self.write_byte(offset as u8, span);
     }
 
     #[allow(dead_code)]
@@ -82,84 +179,168 @@ impl Chunk {
 
     #[allow(dead_code)]
     pub fn get_line(&self, instruction: usize) -> usize {
-        if instruction < self.lines.len() {
-            self.lines[instruction]
-        } else {
-            0
+        self.get_span(instruction).line
+    }
+
+    pub fn get_span(&self, instruction: usize) -> Span {
+        self.lines.get(instruction).copied().unwrap_or_default()
+    }
+
+    /// Start offsets of the loops containing the instruction at `offset`,
+    /// innermost first.
+    pub fn enclosing_loops(&self, offset: usize) -> Vec<usize> {
+        // A loop is the code from its OpLoop's target through the OpLoop, so
+        // the enclosing loops are the ranges that contain `offset`
+        let mut loops = Vec::new();
+        let mut at = 0;
+        while at < self.code.len() {
+            let Some(opcode) = OpCode::from_byte(self.code[at]) else { break };
+            let next = at + 1 + opcode.operand_count();
+            if opcode == OpCode::OpLoop && next <= self.code.len() {
+                let start = next.saturating_sub(self.code[at + 1] as usize);
+                if (start..next).contains(&offset) {
+                    loops.push(start);
+                }
+            }
+            at = next;
         }
+        loops.sort_unstable_by(|a, b| b.cmp(a)); // Innermost (latest start) first
+        loops
     }
 
-    #[allow(dead_code)]
+    /// Render the chunk as text: the constant pool followed by one line per
+    /// instruction with its offset, source span and decoded operands.
+    ///
+    /// ```text
+    /// == script.luma ==
+    /// -- constants --
+    ///    0  number   10.0
+    ///    1  string   "x"
+    /// -- code --
+    /// 0000  1:10+2    OpConstant          0  10
+    /// 0002  1:1+11    OpDefineGlobal      1  x
+    /// 0004  |         OpReturn
+    /// ```
     pub fn disassemble(&self, name: &str) -> String {
         let mut result = format!("== {} ==\n", name);
+
+        result.push_str("-- constants --\n");
+        for (index, constant) in self.constants.iter().enumerate() {
+            let (kind, literal) = constant_literal(constant);
+            result.push_str(format!("{:4}  {:<7}  {}", index, kind, literal).trim_end());
+            result.push('\n');
+        }
+
+        if !self.globals.is_empty() {
+            let mut globals: Vec<_> = self.globals.iter().collect();
+            globals.sort_by_key(|&(name, index)| (*index, name));
+            result.push_str("-- globals --\n");
+            for (name, index) in globals {
+                result.push_str(&format!("{:4}  {}\n", index, name));
+            }
+        }
+
+        result.push_str("-- code --\n");
         let mut offset = 0;
-        
         while offset < self.code.len() {
             offset = self.disassemble_instruction(offset, &mut result);
         }
-        
+
         result
     }
 
-    #[allow(dead_code)]
-    fn disassemble_instruction(&self, offset: usize, result: &mut String) -> usize {
-        result.push_str(&format!("{:04} ", offset));
-        
-        if offset > 0 && self.lines[offset] == self.lines[offset - 1] {
-            result.push_str("   | ");
+    /// Disassemble the instruction at `offset`, returning the offset of the next one.
+    pub fn disassemble_instruction(&self, offset: usize, result: &mut String) -> usize {
+        result.push_str(&format!("{:04}  ", offset));
+
+        let span = self.get_span(offset);
+        if offset > 0 && self.get_span(offset - 1) == span {
+            result.push_str(&format!("{:<10}", "|"));
         } else {
-            result.push_str(&format!("{:4} ", self.lines[offset]));
+            result.push_str(&format!("{:<10}", format!("{}+{}", span, span.length)));
         }
 
+        let (text, next) = self.describe_instruction(offset);
+        result.push_str(&text);
+        result.push('\n');
+        next
+    }
+
+    /// The opcode and decoded operand of the instruction at `offset`, as
+    /// `disassemble` shows them, and the offset of the next instruction.
+    pub fn describe_instruction(&self, offset: usize) -> (String, usize) {
         let instruction = self.code[offset];
-        match OpCode::from_byte(instruction) {
-            Some(OpCode::OpConstant) => self.constant_instruction("OpConstant", offset, result),
-            Some(OpCode::OpNil) => self.simple_instruction("OpNil", offset, result),
-            Some(OpCode::OpTrue) => self.simple_instruction("OpTrue", offset, result),
-            Some(OpCode::OpFalse) => self.simple_instruction("OpFalse", offset, result),
-            Some(OpCode::OpAdd) => self.simple_instruction("OpAdd", offset, result),
-            Some(OpCode::OpSubtract) => self.simple_instruction("OpSubtract", offset, result),
-            Some(OpCode::OpMultiply) => self.simple_instruction("OpMultiply", offset, result),
-            Some(OpCode::OpDivide) => self.simple_instruction("OpDivide", offset, result),
-            Some(OpCode::OpNegate) => self.simple_instruction("OpNegate", offset, result),
-            Some(OpCode::OpPrint) => self.simple_instruction("OpPrint", offset, result),
-            Some(OpCode::OpJump) => self.jump_instruction("OpJump", 1, offset, result),
-            Some(OpCode::OpJumpIfFalse) => self.jump_instruction("OpJumpIfFalse", 1, offset, result),
-            Some(OpCode::OpLoop) => self.jump_instruction("OpLoop", -1, offset, result),
-            Some(op) => {
-                result.push_str(&format!("{:?}\n", op));
-                offset + 1
-            }
-            None => {
-                result.push_str(&format!("Unknown opcode {}\n", instruction));
-                offset + 1
-            }
+        let opcode = match OpCode::from_byte(instruction) {
+            Some(opcode) => opcode,
+            None => return (format!("Unknown opcode {}", instruction), offset + 1),
+        };
+
+        if opcode.operand_count() == 0 {
+            return (format!("{:?}", opcode), offset + 1);
         }
-    }
 
-    #[allow(dead_code)]
-    fn simple_instruction(&self, name: &str, offset: usize, result: &mut String) -> usize {
-        result.push_str(&format!("{}\n", name));
-        offset + 1
+        let operand = match self.code.get(offset + 1) {
+            Some(&operand) => operand,
+            None => return (format!("{:<18} <missing operand>", format!("{:?}", opcode)), offset + 1),
+        };
+
+        let detail = self.describe_operand(opcode, offset, operand);
+        let text = format!("{:<18} {:3}  {}", format!("{:?}", opcode), operand, detail);
+        (text.trim_end().to_string(), offset + 2)
     }
 
-    #[allow(dead_code)]
-    fn constant_instruction(&self, name: &str, offset: usize, result: &mut String) -> usize {
-        let constant = self.code[offset + 1];
-        result.push_str(&format!("{:<16} {:4} '", name, constant));
-        if let Some(value) = self.constants.get(constant as usize) {
-            result.push_str(&value.to_string());
+    // Human-readable meaning of an operand byte
+    fn describe_operand(&self, opcode: OpCode, offset: usize, operand: u8) -> String {
+        let operand = operand as usize;
+        match opcode {
+            OpCode::OpConstant => match self.constants.get(operand) {
+                Some(Value::String(s)) => format!("{:?}", s),
+                Some(value) => value.to_string(),
+                None => "<invalid constant>".to_string(),
+            },
+            OpCode::OpDefineGlobal | OpCode::OpGetGlobal | OpCode::OpSetGlobal => {
+                match self.constants.get(operand) {
+                    Some(Value::String(name)) => name.to_string(),
+                    _ => "<invalid name>".to_string(),
+                }
+            }
+            OpCode::OpGetLocal | OpCode::OpSetLocal => format!("slot {}", operand),
+            OpCode::OpJump | OpCode::OpJumpIfFalse => format!("-> {:04}", offset + 2 + operand),
+            OpCode::OpLoop => match (offset + 2).checked_sub(operand) {
+                Some(target) => format!("-> {:04}", target),
+                None => "-> <before start>".to_string(),
+            },
+            OpCode::OpCall => format!("{} args", operand),
+            _ => String::new(),
         }
-        result.push_str("'\n");
-        offset + 2
     }
+}
+
+// Type tag and exact literal for a constant pool entry. Numbers use Rust's
+// shortest round-trip formatting so the text reproduces the same bits
+fn constant_literal(value: &Value) -> (&'static str, String) {
+    match value {
+        Value::Number(n) => ("number", format!("{:?}", n)),
+        Value::String(s) => ("string", format!("{:?}", s)),
+        Value::Boolean(b) => ("boolean", b.to_string()),
+        Value::Nil => ("nil", String::new()),
+    }
+}
 
-    #[allow(dead_code)]
-    fn jump_instruction(&self, name: &str, sign: i32, offset: usize, result: &mut String) -> usize {
-        let jump = self.code[offset + 1] as i32;
-        let target = offset as i32 + 2 + sign * jump;
-        result.push_str(&format!("{:<16} {:4} -> {}\n", name, offset, target));
-        offset + 2
+// Chunks are equal when they would execute and report errors identically.
+// Constants compare by identity, so `0.0` and `-0.0` differ and NaN equals
+// itself; the lookup index is a cache and is ignored.
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+            && self.lines == other.lines
+            && self.globals == other.globals
+            && self.constants.len() == other.constants.len()
+            && self
+                .constants
+                .iter()
+                .zip(&other.constants)
+                .all(|(a, b)| ConstantRef::of(a) == ConstantRef::of(b))
     }
 }
 
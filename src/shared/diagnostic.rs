@@ -0,0 +1,177 @@
+use crate::shared::{LumaError, Span};
+
+// ANSI styles used for terminal output
+const RED_BOLD: &str = "\x1b[1;31m";
+const BLUE_BOLD: &str = "\x1b[1;34m";
+const CYAN_BOLD: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A user-facing report of an error, with enough context to show the
+/// offending source line and point at the exact span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub title: String,
+    pub code: Option<&'static str>, // Stable error code, e.g. "E0101"
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            code: None,
+            message: message.into(),
+            span: None,
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    /// Build a diagnostic for an error. Variable-name suggestions are drawn
+    /// from `known_names`, typically the globals defined when the error happened.
+    pub fn from_error<'a>(error: &LumaError, known_names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut diagnostic = Diagnostic::new(error.category(), error.message());
+        diagnostic.code = Some(error.code());
+        diagnostic.span = error.span().filter(|span| span.is_known());
+        if let Some(trace) = error.trace() {
+            diagnostic.notes = trace.notes();
+        }
+
+        if let LumaError::UndefinedVariable { name, .. } = error.cause() {
+            match suggest_name(name, known_names) {
+                Some(suggestion) => diagnostic.help.push(format!("did you mean `{}`?", suggestion)),
+                None => diagnostic
+                    .help
+                    .push(format!("define it first with `let {} be ...`", name)),
+            }
+        }
+
+        diagnostic
+    }
+
+    #[allow(dead_code)]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Render the diagnostic against the source it refers to.
+    ///
+    /// ```text
+    /// runtime error[E0101]: Undefined variable 'countr'
+    ///  --> script.luma:3:6
+    ///   |
+    /// 3 | show countr + 1
+    ///   |      ^^^^^^
+    ///   |
+    ///   = help: did you mean `counter`?
+    /// ```
+    pub fn render(&self, source: &str, file_name: &str, color: bool) -> String {
+        let style = |code: &'static str| if color { code } else { "" };
+        let (red, blue, cyan, bold, reset) =
+            (style(RED_BOLD), style(BLUE_BOLD), style(CYAN_BOLD), style(BOLD), style(RESET));
+
+        let code = self.code.map(|code| format!("[{}]", code)).unwrap_or_default();
+        let mut out = format!("{}{}{}{}: {}{}{}\n", red, self.title, code, reset, bold, self.message, reset);
+
+        let source_line = self
+            .span
+            .and_then(|span| source.lines().nth(span.line - 1).map(|line| (span, line)));
+
+        let gutter = match source_line {
+            Some((span, _)) => " ".repeat(span.line.to_string().len()),
+            None => String::new(),
+        };
+
+        if let Some(span) = self.span {
+            out.push_str(&format!("{}{}-->{} {}:{}:{}\n", gutter, blue, reset, file_name, span.line, span.column));
+        }
+
+        if let Some((span, line)) = source_line {
+            let line = line.trim_end_matches('\r');
+            let (padding, width) = underline(line, span);
+            out.push_str(&format!("{} {}|{}\n", gutter, blue, reset));
+            out.push_str(&format!("{}{} |{} {}\n", blue, span.line, reset, line));
+            out.push_str(&format!(
+                "{} {}|{} {}{}{}{}\n",
+                gutter,
+                blue,
+                reset,
+                padding,
+                red,
+                "^".repeat(width),
+                reset
+            ));
+        }
+
+        if !self.notes.is_empty() || !self.help.is_empty() {
+            if source_line.is_some() {
+                out.push_str(&format!("{} {}|{}\n", gutter, blue, reset));
+            }
+            for note in &self.notes {
+                out.push_str(&format!("{} {}={} {}note{}: {}\n", gutter, blue, reset, bold, reset, note));
+            }
+            for help in &self.help {
+                out.push_str(&format!("{} {}={} {}help{}: {}\n", gutter, blue, reset, cyan, reset, help));
+            }
+        }
+
+        out
+    }
+}
+
+// Whitespace that lines the caret up under the span (keeping tabs so the
+// terminal expands them the same way) and the caret count, clamped to the line
+fn underline(line: &str, span: Span) -> (String, usize) {
+    let chars: Vec<char> = line.chars().collect();
+    let start = span.column.saturating_sub(1).min(chars.len());
+    let padding = chars[..start]
+        .iter()
+        .map(|&c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = span.length.min(chars.len() - start).max(1);
+    (padding, width)
+}
+
+/// Find the closest match for `name` among `candidates`, if any is close
+/// enough to plausibly be a typo.
+pub fn suggest_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    // Allow roughly one edit per three characters, and at least one
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name && !candidate.starts_with("__"))
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
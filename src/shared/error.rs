@@ -1,21 +1,66 @@
+use crate::shared::{RuntimeTrace, Span};
+use std::fmt;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum LumaError {
-    #[error("Lexical error at line {line}: {message}")]
-    LexError { message: String, line: usize },
+    #[error("Lexical error at line {}: {message}", .span.line)]
+    LexError { message: String, span: Span },
 
-    #[error("Parse error at line {line}: {message}")]
-    ParseError { message: String, line: usize },
+    #[error("Parse error at line {}: {message}", .span.line)]
+    ParseError { message: String, span: Span },
 
-    #[error("Compile error at line {line}: {message}")]
-    CompileError { message: String, line: usize },
+    #[error("{}", join_errors(.0))]
+    ParseErrors(Vec<LumaError>),
 
-    #[error("Runtime error: {0}")]
-    RuntimeError(String),
+    #[error("Compile error at line {}: {message}", .span.line)]
+    CompileError { message: String, span: Span },
 
-    #[error("Stack error: {0}")]
-    StackError(String),
+    #[error("Runtime error{}: {message}", at_line(.span))]
+    RuntimeError { message: String, span: Option<Span> },
+
+    #[error("Runtime error{}: Undefined variable '{name}'", at_line(.span))]
+    UndefinedVariable { name: String, span: Option<Span> },
+
+    /// A binary operator applied to values it cannot combine. `left` and
+    /// `right` are the operands' type names.
+    #[error("Runtime error{}: Cannot apply '{op}' to {left} and {right}", at_line(.span))]
+    TypeMismatch { op: &'static str, left: &'static str, right: &'static str, span: Option<Span> },
+
+    /// A unary operator applied to a value it does not accept.
+    #[error("Runtime error{}: Cannot apply '{op}' to {operand}", at_line(.span))]
+    InvalidOperand { op: &'static str, operand: &'static str, span: Option<Span> },
+
+    /// `/` or `%` with a zero divisor; `op` says which.
+    #[error("Runtime error{}: {} by zero", at_line(.span), if *.op == "%" { "Modulo" } else { "Division" })]
+    DivisionByZero { op: &'static str, span: Option<Span> },
+
+    #[error("Runtime error{}: Unsupported opcode {opcode}", at_line(.span))]
+    UnsupportedOpcode { opcode: String, span: Option<Span> },
+
+    #[error("Stack error{}: {message}", at_line(.span))]
+    StackError { message: String, span: Option<Span> },
+
+    #[error("Stack overflow{}: more than {limit} values at call depth {call_depth}", at_line(.span))]
+    StackOverflow { limit: usize, call_depth: usize, span: Option<Span> },
+
+    #[error("Execution limit exceeded{}: {limit}", at_line(.span))]
+    LimitExceeded { limit: Limit, span: Option<Span> },
+
+    #[error("Interrupted{}", at_line(.span))]
+    Interrupted { span: Option<Span> },
+
+    /// A runtime error together with where the VM was when it happened.
+    #[error("{error}")]
+    Traced { error: Box<LumaError>, trace: Box<RuntimeTrace> },
+
+    #[error("Bytecode error: {0}")]
+    BytecodeError(String),
+
+    #[error("Bytecode verification failed at offset {offset}: {message}")]
+    VerifyError { offset: usize, message: String },
 
     #[error("JIT error: {0}")]
     #[allow(dead_code)]
@@ -28,30 +73,245 @@ pub enum LumaError {
     SerializationError(#[from] bincode::Error),
 }
 
+/// A resource bound a run went past; see `VmConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Instructions(u64),
+    Timeout(Duration),
+    Memory(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Instructions(limit) => write!(f, "ran more than {} instructions", limit),
+            Limit::Timeout(timeout) => write!(f, "ran longer than {}ms", timeout.as_millis()),
+            Limit::Memory(limit) => write!(f, "used more than {} bytes of string memory", limit),
+        }
+    }
+}
+
+fn join_errors(errors: &[LumaError]) -> String {
+    errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+fn at_line(span: &Option<Span>) -> String {
+    match span {
+        Some(span) if span.is_known() => format!(" at line {}", span.line),
+        _ => String::new(),
+    }
+}
+
 impl LumaError {
-    pub fn parse_error(message: String, line: usize) -> Self {
-        LumaError::ParseError { message, line }
+    pub fn parse_error(message: String, span: Span) -> Self {
+        LumaError::ParseError { message, span }
     }
-    
-    pub fn lex_error(message: String, line: usize) -> Self {
-        LumaError::LexError { message, line }
+
+    pub fn lex_error(message: String, span: Span) -> Self {
+        LumaError::LexError { message, span }
     }
-    
-    pub fn compile_error(message: String, line: usize) -> Self {
-        LumaError::CompileError { message, line }
+
+    pub fn compile_error(message: String, span: Span) -> Self {
+        LumaError::CompileError { message, span }
     }
-}
 
-impl From<String> for LumaError {
-    fn from(msg: String) -> Self {
-        LumaError::RuntimeError(msg)
+    pub fn runtime_error(message: impl Into<String>) -> Self {
+        LumaError::RuntimeError { message: message.into(), span: None }
+    }
+
+    pub fn stack_error(message: impl Into<String>) -> Self {
+        LumaError::StackError { message: message.into(), span: None }
+    }
+
+    /// Source location the error points at, if it has one.
+    #[allow(dead_code)]
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LumaError::LexError { span, .. }
+            | LumaError::ParseError { span, .. }
+            | LumaError::CompileError { span, .. } => Some(*span),
+            LumaError::RuntimeError { span, .. }
+            | LumaError::UndefinedVariable { span, .. }
+            | LumaError::TypeMismatch { span, .. }
+            | LumaError::InvalidOperand { span, .. }
+            | LumaError::DivisionByZero { span, .. }
+            | LumaError::UnsupportedOpcode { span, .. }
+            | LumaError::StackError { span, .. }
+            | LumaError::StackOverflow { span, .. }
+            | LumaError::LimitExceeded { span, .. }
+            | LumaError::Interrupted { span } => *span,
+            LumaError::ParseErrors(errors) => errors.first().and_then(|e| e.span()),
+            LumaError::Traced { error, .. } => error.span(),
+            _ => None,
+        }
+    }
+
+    /// Short name of the error category, e.g. "parse error".
+    pub fn category(&self) -> &'static str {
+        match self {
+            LumaError::LexError { .. } => "lexical error",
+            LumaError::ParseError { .. } | LumaError::ParseErrors(_) => "parse error",
+            LumaError::CompileError { .. } => "compile error",
+            LumaError::RuntimeError { .. }
+            | LumaError::UndefinedVariable { .. }
+            | LumaError::TypeMismatch { .. }
+            | LumaError::InvalidOperand { .. }
+            | LumaError::DivisionByZero { .. }
+            | LumaError::UnsupportedOpcode { .. } => "runtime error",
+            LumaError::StackError { .. } | LumaError::StackOverflow { .. } => "stack error",
+            LumaError::LimitExceeded { .. } => "limit exceeded",
+            LumaError::Interrupted { .. } => "interrupted",
+            LumaError::Traced { error, .. } => error.category(),
+            LumaError::BytecodeError(_) => "bytecode error",
+            LumaError::VerifyError { .. } => "verification error",
+            LumaError::JitError(_) => "JIT error",
+            LumaError::IoError(_) => "I/O error",
+            LumaError::SerializationError(_) => "serialization error",
+        }
+    }
+
+    /// The error description without the category and location prefix.
+    pub fn message(&self) -> String {
+        match self {
+            LumaError::LexError { message, .. }
+            | LumaError::ParseError { message, .. }
+            | LumaError::CompileError { message, .. }
+            | LumaError::RuntimeError { message, .. }
+            | LumaError::StackError { message, .. } => message.clone(),
+            LumaError::UndefinedVariable { name, .. } => format!("Undefined variable '{}'", name),
+            LumaError::TypeMismatch { op, left, right, .. } => {
+                format!("Cannot apply '{}' to {} and {}", op, left, right)
+            }
+            LumaError::InvalidOperand { op, operand, .. } => format!("Cannot apply '{}' to {}", op, operand),
+            LumaError::DivisionByZero { op, .. } => {
+                format!("{} by zero", if *op == "%" { "Modulo" } else { "Division" })
+            }
+            LumaError::UnsupportedOpcode { opcode, .. } => format!("Unsupported opcode {}", opcode),
+            LumaError::StackOverflow { limit, call_depth, .. } => {
+                format!("Stack overflow: more than {} values at call depth {}", limit, call_depth)
+            }
+            LumaError::LimitExceeded { limit, .. } => format!("Execution {}", limit),
+            LumaError::Interrupted { .. } => "Interrupted".to_string(),
+            LumaError::Traced { error, .. } => error.message(),
+            LumaError::ParseErrors(errors) => {
+                errors.iter().map(|e| e.message()).collect::<Vec<_>>().join("\n")
+            }
+            LumaError::BytecodeError(message) | LumaError::JitError(message) => message.clone(),
+            LumaError::VerifyError { offset, message } => format!("offset {}: {}", offset, message),
+            LumaError::IoError(e) => e.to_string(),
+            LumaError::SerializationError(e) => e.to_string(),
+        }
+    }
+
+    /// Stable identifier for the kind of error, for tools to match on.
+    ///
+    /// | Code  | Error |
+    /// |-------|-------|
+    /// | E0001 | lexical error |
+    /// | E0002 | parse error |
+    /// | E0003 | compile error |
+    /// | E0100 | other runtime error |
+    /// | E0101 | undefined variable |
+    /// | E0102 | type mismatch in a binary operator |
+    /// | E0103 | invalid operand for a unary operator |
+    /// | E0104 | division or modulo by zero |
+    /// | E0105 | unsupported opcode |
+    /// | E0200 | stack underflow |
+    /// | E0201 | stack overflow |
+    /// | E0301 | instruction limit exceeded |
+    /// | E0302 | timeout |
+    /// | E0303 | memory limit exceeded |
+    /// | E0304 | interrupted |
+    /// | E0400 | invalid bytecode |
+    /// | E0401 | bytecode verification failed |
+    /// | E0500 | JIT error |
+    /// | E0600 | I/O error |
+    /// | E0601 | serialization error |
+    ///
+    /// Codes are never reused; a retired error keeps its number.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LumaError::LexError { .. } => "E0001",
+            LumaError::ParseError { .. } => "E0002",
+            LumaError::ParseErrors(errors) => errors.first().map_or("E0002", |e| e.code()),
+            LumaError::CompileError { .. } => "E0003",
+            LumaError::RuntimeError { .. } => "E0100",
+            LumaError::UndefinedVariable { .. } => "E0101",
+            LumaError::TypeMismatch { .. } => "E0102",
+            LumaError::InvalidOperand { .. } => "E0103",
+            LumaError::DivisionByZero { .. } => "E0104",
+            LumaError::UnsupportedOpcode { .. } => "E0105",
+            LumaError::StackError { .. } => "E0200",
+            LumaError::StackOverflow { .. } => "E0201",
+            LumaError::LimitExceeded { limit: Limit::Instructions(_), .. } => "E0301",
+            LumaError::LimitExceeded { limit: Limit::Timeout(_), .. } => "E0302",
+            LumaError::LimitExceeded { limit: Limit::Memory(_), .. } => "E0303",
+            LumaError::Interrupted { .. } => "E0304",
+            LumaError::Traced { error, .. } => error.code(),
+            LumaError::BytecodeError(_) => "E0400",
+            LumaError::VerifyError { .. } => "E0401",
+            LumaError::JitError(_) => "E0500",
+            LumaError::IoError(_) => "E0600",
+            LumaError::SerializationError(_) => "E0601",
+        }
+    }
+
+    /// The individual errors this error stands for: the bundled errors of
+    /// `ParseErrors`, or just `self` for everything else.
+    pub fn errors(&self) -> Vec<&LumaError> {
+        match self {
+            LumaError::ParseErrors(errors) => errors.iter().collect(),
+            _ => vec![self],
+        }
+    }
+
+    /// The error itself, looking through any runtime trace around it.
+    pub fn cause(&self) -> &LumaError {
+        match self {
+            LumaError::Traced { error, .. } => error.cause(),
+            error => error,
+        }
+    }
+
+    /// Where the VM was when this error happened, for runtime errors.
+    pub fn trace(&self) -> Option<&RuntimeTrace> {
+        match self {
+            LumaError::Traced { trace, .. } => Some(trace),
+            _ => None,
+        }
+    }
+
+    /// Wrap the error with a runtime trace, replacing any it already has.
+    pub fn with_trace(self, trace: RuntimeTrace) -> Self {
+        let error = match self {
+            LumaError::Traced { error, .. } => error,
+            error => Box::new(error),
+        };
+        LumaError::Traced { error, trace: Box::new(trace) }
     }
-}
 
-impl From<&str> for LumaError {
-    fn from(msg: &str) -> Self {
-        LumaError::RuntimeError(msg.to_string())
+    /// Attach a location to a runtime error that does not have one yet.
+    pub fn with_span(mut self, location: Span) -> Self {
+        if let LumaError::Traced { error, trace } = self {
+            return LumaError::Traced { error: Box::new(error.with_span(location)), trace };
+        }
+        if let LumaError::RuntimeError { span, .. }
+        | LumaError::UndefinedVariable { span, .. }
+        | LumaError::TypeMismatch { span, .. }
+        | LumaError::InvalidOperand { span, .. }
+        | LumaError::DivisionByZero { span, .. }
+        | LumaError::UnsupportedOpcode { span, .. }
+        | LumaError::StackError { span, .. }
+        | LumaError::StackOverflow { span, .. }
+        | LumaError::LimitExceeded { span, .. }
+        | LumaError::Interrupted { span } = &mut self
+        {
+            if span.is_none() {
+                *span = Some(location);
+            }
+        }
+        self
     }
 }
 
-pub type Result<T> = std::result::Result<T, LumaError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, LumaError>;
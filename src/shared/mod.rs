@@ -1,7 +1,15 @@
 pub mod value;
 pub mod chunk;
 pub mod error;
+pub mod span;
+pub mod diagnostic;
+pub mod bytecode_cache;
+pub mod assembler;
+pub mod trace;
 
 pub use value::*;
 pub use chunk::*;
-pub use error::*;
\ No newline at end of file
+pub use error::*;
+pub use span::*;
+pub use diagnostic::*;
+pub use trace::*;
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Location of a piece of source text.
+///
+/// `line` and `column` are 1-based and `length` is counted in characters.
+/// A zero line means the location is unknown (e.g. synthesized code).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize, length: usize) -> Self {
+        Self { line, column, length }
+    }
+
+    /// Span covering `self` through the end of `other`.
+    ///
+    /// Spans only describe a single line, so if `other` ends on a later line
+    /// the result stays anchored to the start of `self`.
+    pub fn to(self, other: Span) -> Span {
+        if other.line == self.line && other.column >= self.column {
+            let end = (other.column + other.length).max(self.column + self.length);
+            Span::new(self.line, self.column, end - self.column)
+        } else {
+            self
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.line > 0
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
@@ -0,0 +1,77 @@
+use crate::shared::{Span, Value};
+use std::fmt;
+
+/// Where a runtime error happened and what the VM was working on.
+///
+/// Attached to errors from `VM::interpret`; read it back with
+/// `LumaError::trace`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeTrace {
+    /// Source span of the failing instruction.
+    pub span: Span,
+    /// Bytecode offset of the failing instruction.
+    pub offset: usize,
+    /// Enclosing contexts, innermost first.
+    pub frames: Vec<TraceFrame>,
+    /// Operands the failing instruction had popped, left to right.
+    pub values: Vec<Value>,
+}
+
+/// A context the failing instruction was running inside.
+///
+/// Calls will get a variant of their own once the VM implements them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFrame {
+    /// A `while` or `repeat` loop; the span is its header.
+    Loop { span: Span },
+}
+
+impl RuntimeTrace {
+    pub fn new(span: Span, offset: usize) -> Self {
+        Self { span, offset, frames: Vec::new(), values: Vec::new() }
+    }
+
+    /// One line per piece of context, for display under the error.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        match self.values.as_slice() {
+            [] => {}
+            [value] => notes.push(format!("operand was {}", ValueSnippet(value))),
+            [left, right] => notes.push(format!("operands were {} and {}", ValueSnippet(left), ValueSnippet(right))),
+            values => {
+                let values: Vec<_> = values.iter().map(|value| ValueSnippet(value).to_string()).collect();
+                notes.push(format!("operands were {}", values.join(", ")));
+            }
+        }
+        for frame in &self.frames {
+            notes.push(frame.to_string());
+        }
+        notes
+    }
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceFrame::Loop { span } => write!(f, "inside the loop at line {}", span.line),
+        }
+    }
+}
+
+// A value as it would be written in source, shortened if it is long
+struct ValueSnippet<'a>(&'a Value);
+
+impl fmt::Display for ValueSnippet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const MAX_CHARS: usize = 32;
+
+        match self.0 {
+            Value::String(s) if s.chars().count() > MAX_CHARS => {
+                let prefix: String = s.chars().take(MAX_CHARS).collect();
+                write!(f, "{:?}... ({} characters)", prefix, s.chars().count())
+            }
+            Value::String(s) => write!(f, "{:?}", s),
+            value => write!(f, "{}", value),
+        }
+    }
+}
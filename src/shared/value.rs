@@ -1,25 +1,118 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
 use std::fmt;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
 
+/// Runtime value.
+///
+/// Strings are immutable and reference-counted, so loading a constant,
+/// reading a global or duplicating the top of the stack only bumps a
+/// refcount instead of copying the bytes. A `SharedStr` is one pointer
+/// wide, which keeps a `Value` at 16 bytes.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     Number(f64),
-    String(String),
+    String(SharedStr),
     Boolean(bool),
     Nil,
 }
 
+/// An immutable, reference-counted string behind a thin pointer.
+///
+/// `Rc<str>` stores the length next to the pointer, which would make
+/// `Value` 24 bytes; here the length lives in the shared allocation. It
+/// derefs, compares, hashes and serializes as a plain `str`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SharedStr(Rc<String>);
+
+impl SharedStr {
+    /// Identifies the allocation while any reference to it, weak or
+    /// strong, is alive.
+    pub(crate) fn id(&self) -> *const String {
+        Rc::as_ptr(&self.0)
+    }
+
+    pub(crate) fn downgrade(&self) -> Weak<String> {
+        Rc::downgrade(&self.0)
+    }
+
+    pub(crate) fn strong_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for SharedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(s: &str) -> Self {
+        SharedStr(Rc::new(s.to_string()))
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(s: String) -> Self {
+        SharedStr(Rc::new(s))
+    }
+}
+
+impl fmt::Debug for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl Serialize for SharedStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SharedStr::from)
+    }
+}
+
 impl Value {
-    #[allow(dead_code)]
     pub fn type_name(&self) -> &'static str {
         match self {
             Value::Number(_) => "number",
-            Value::String(_) => "string", 
+            Value::String(_) => "string",
             Value::Boolean(_) => "boolean",
             Value::Nil => "nil",
         }
     }
 
+    /// Create a string value from anything that converts into a shared string.
+    pub fn string(s: impl Into<SharedStr>) -> Self {
+        Value::String(s.into())
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::Boolean(b) => *b,
@@ -37,25 +130,45 @@ impl Value {
             Value::Nil => Err("Cannot convert nil to number".to_string()),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::Number(n) => {
                 if n.fract() == 0.0 {
-                    format!("{}", *n as i64)
+                    write!(f, "{}", *n as i64)
                 } else {
-                    format!("{}", n)
+                    write!(f, "{}", n)
                 }
             }
-            Value::String(s) => s.clone(),
-            Value::Boolean(b) => b.to_string(),
-            Value::Nil => "nil".to_string(),
+            Value::String(s) => f.write_str(s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Nil => f.write_str("nil"),
         }
     }
 }
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_string())
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
     }
-}
\ No newline at end of file
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s.into())
+    }
+}
@@ -0,0 +1,182 @@
+// Tests for the .lumac bytecode cache format
+mod common;
+
+use luma::backend::vm::vm::VM;
+use luma::shared::bytecode_cache::{self, CacheHeader, CODEGEN_VERSION, COMPILER_VERSION, FORMAT_VERSION, MAGIC};
+use luma::shared::chunk::Chunk;
+use luma::shared::error::LumaError;
+use luma::shared::value::Value;
+use std::path::PathBuf;
+use common::compile;
+
+// Hand-build a cache file with an arbitrary header
+fn encode_with_header(header: &CacheHeader, chunk: &Chunk) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, header).unwrap();
+    bincode::serialize_into(&mut bytes, chunk).unwrap();
+    bytes
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("luma_cache_test_{}_{}", std::process::id(), name))
+}
+
+#[test]
+fn test_round_trip_preserves_chunk() {
+    let source = "let x be 40\nshow x + 2";
+    let chunk = compile(source);
+
+    let bytes = bytecode_cache::encode(&chunk, source).unwrap();
+    assert!(bytes.starts_with(MAGIC));
+
+    let (header, decoded) = bytecode_cache::decode(&bytes).unwrap();
+    assert_eq!(header, CacheHeader::for_source(source));
+    assert_eq!(decoded.code, chunk.code);
+    assert_eq!(decoded.constants, chunk.constants);
+    assert_eq!(decoded.lines, chunk.lines);
+
+    let result = VM::new().interpret(decoded).unwrap();
+    assert_eq!(result, Value::Number(42.0));
+}
+
+#[test]
+fn test_load_fresh_checks_source_hash() {
+    let source = "show 1";
+    let path = temp_path("fresh.lumac");
+    bytecode_cache::write_cache(&path, &compile(source), source).unwrap();
+
+    assert!(bytecode_cache::load_fresh(&path, source).is_some());
+    assert!(bytecode_cache::load_fresh(&path, "show 2").is_none());
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(bytecode_cache::load_fresh(&path, source).is_none());
+}
+
+#[test]
+fn test_load_fresh_rejects_chunks_that_fail_verification() {
+    let source = "show 1";
+    let mut chunk = compile(source);
+    chunk.code[1] = 200; // Constant index past the pool
+    let path = temp_path("unverified.lumac");
+    bytecode_cache::write_cache(&path, &chunk, source).unwrap();
+
+    assert!(bytecode_cache::load_fresh(&path, source).is_none());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_fresh_rejects_oversized_length_prefixes() {
+    let source = "show 1";
+    let path = temp_path("oversized.lumac");
+
+    // A compiler version string claiming 2^62 bytes
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &(FORMAT_VERSION, 1u64 << 62)).unwrap();
+    assert!(matches!(bytecode_cache::decode(&bytes).unwrap_err(), LumaError::BytecodeError(_)));
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(bytecode_cache::load_fresh(&path, source).is_none());
+
+    // A fresh header followed by bytecode claiming 2^62 bytes
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &CacheHeader::for_source(source)).unwrap();
+    bincode::serialize_into(&mut bytes, &(1u64 << 62)).unwrap();
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(bytecode_cache::load_fresh(&path, source).is_none());
+
+    // Recompiling replaces the bad cache, as a run of the script does
+    bytecode_cache::write_cache(&path, &compile(source), source).unwrap();
+    assert!(bytecode_cache::load_fresh(&path, source).is_some());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_other_compiler_version_is_not_reused() {
+    let source = "show 1";
+    let header = CacheHeader {
+        compiler_version: "0.0.1".to_string(),
+        ..CacheHeader::for_source(source)
+    };
+    let path = temp_path("old_compiler.lumac");
+    std::fs::write(&path, encode_with_header(&header, &compile(source))).unwrap();
+
+    assert!(bytecode_cache::load_fresh(&path, source).is_none());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_older_codegen_is_not_reused() {
+    let source = "show 1";
+    let header = CacheHeader {
+        codegen_version: CODEGEN_VERSION - 1,
+        ..CacheHeader::for_source(source)
+    };
+    let path = temp_path("old_codegen.lumac");
+    std::fs::write(&path, encode_with_header(&header, &compile(source))).unwrap();
+
+    assert!(bytecode_cache::load_fresh(&path, source).is_none());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_format_version_1_files_are_rejected_by_version() {
+    // Version 1 headers had no codegen version
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &(1u32, COMPILER_VERSION, bytecode_cache::hash_source("show 1"))).unwrap();
+    bincode::serialize_into(&mut bytes, &compile("show 1")).unwrap();
+
+    let error = bytecode_cache::decode(&bytes).unwrap_err();
+    assert!(error.to_string().contains("unsupported bytecode format version 1"), "{}", error);
+}
+
+#[test]
+fn test_other_format_version_is_rejected() {
+    let source = "show 1";
+    let header = CacheHeader {
+        format_version: FORMAT_VERSION + 1,
+        ..CacheHeader::for_source(source)
+    };
+    let bytes = encode_with_header(&header, &compile(source));
+
+    // The header is still readable, but the chunk is not trusted
+    assert_eq!(bytecode_cache::decode_header(&bytes).unwrap().format_version, FORMAT_VERSION + 1);
+    let error = bytecode_cache::decode(&bytes).unwrap_err();
+    assert!(matches!(error, LumaError::BytecodeError(_)));
+    assert!(error.to_string().contains("format version"));
+}
+
+#[test]
+fn test_crafted_files_are_reported() {
+    // Running or disassembling a `.lumac` reads it with `read_cache`
+    let path = temp_path("crafted.lumac");
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &(FORMAT_VERSION, u64::MAX)).unwrap();
+    std::fs::write(&path, &bytes).unwrap();
+    let error = bytecode_cache::read_cache(&path).unwrap_err();
+    assert_eq!(error.to_string(), "Bytecode error: corrupt bytecode file: a length runs past the end of the file");
+
+    let mut bytes = bytecode_cache::encode(&compile("show 1"), "show 1").unwrap();
+    bytes.truncate(bytes.len() - 4);
+    std::fs::write(&path, &bytes).unwrap();
+    let error = bytecode_cache::read_cache(&path).unwrap_err();
+    assert_eq!(error.to_string(), "Bytecode error: truncated bytecode file");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_bad_magic_is_rejected() {
+    let error = bytecode_cache::decode(b"#!/bin/luma\nshow 1").unwrap_err();
+    assert_eq!(error.to_string(), "Bytecode error: not a Luma bytecode file");
+    assert!(bytecode_cache::decode(b"LU").is_err());
+}
+
+#[test]
+fn test_source_hash_is_stable() {
+    assert_eq!(bytecode_cache::hash_source(""), 0xcbf2_9ce4_8422_2325);
+    assert_ne!(bytecode_cache::hash_source("show 1"), bytecode_cache::hash_source("show 2"));
+    assert_eq!(CacheHeader::for_source("x").compiler_version, COMPILER_VERSION);
+}
+
+#[test]
+fn test_cache_path_replaces_extension() {
+    assert_eq!(bytecode_cache::cache_path("examples/hello.luma"), PathBuf::from("examples/hello.lumac"));
+}
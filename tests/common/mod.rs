@@ -0,0 +1,12 @@
+// Helpers shared by the integration tests
+use luma::frontend::compiler::Compiler;
+use luma::frontend::lexer::Lexer;
+use luma::frontend::parser::Parser;
+use luma::shared::chunk::Chunk;
+
+// Run the front end over `source`, panicking on any error
+pub fn compile(source: &str) -> Chunk {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let statements = Parser::new(tokens).parse().unwrap();
+    Compiler::new().compile(&statements).unwrap()
+}
@@ -0,0 +1,95 @@
+// Integration tests for line and branch coverage
+mod common;
+
+use luma::backend::vm::{BranchCoverage, BufferOutput, Coverage, VmConfig, VM};
+use common::compile;
+
+fn coverage(source: &str) -> Coverage {
+    let chunk = compile(source);
+
+    let mut vm = VM::with_config(VmConfig::default().with_coverage(true));
+    vm.set_output(BufferOutput::new());
+    vm.interpret(chunk).unwrap();
+    vm.coverage().unwrap()
+}
+
+fn branch(line: usize, taken: u64, not_taken: u64) -> BranchCoverage {
+    BranchCoverage { line, block: 0, taken, not_taken }
+}
+
+const GRADES: &str = "if score >= 90 then\n    show \"A\"\nelse if score >= 70 then\n    show \"B\"\nelse\n    show \"C\"";
+
+#[test]
+fn test_coverage_is_off_by_default() {
+    let mut vm = VM::new();
+    vm.set_output(BufferOutput::new());
+    vm.interpret(compile("show 1")).unwrap();
+    assert!(vm.coverage().is_none());
+}
+
+#[test]
+fn test_lines_never_reached_have_zero_hits() {
+    let coverage = coverage(&format!("let score be 75\n{}", GRADES));
+    let lines: Vec<_> = coverage.lines.iter().map(|(&line, &hits)| (line, hits)).collect();
+    // The `else` line itself has no code
+    assert_eq!(lines, vec![(1, 1), (2, 1), (3, 0), (4, 1), (5, 1), (7, 0)]);
+}
+
+#[test]
+fn test_if_else_if_else_branches() {
+    let coverage = coverage(&format!("let score be 95\n{}", GRADES));
+    assert_eq!(coverage.branches, vec![branch(2, 1, 0), branch(4, 0, 0)]);
+    assert!(!coverage.branches[1].reached());
+
+    let coverage = self::coverage(&format!("let score be 10\n{}", GRADES));
+    assert_eq!(coverage.branches, vec![branch(2, 0, 1), branch(4, 0, 1)]);
+}
+
+#[test]
+fn test_loop_conditions_count_every_test() {
+    let coverage = coverage("let i be 0\nwhile i < 3 then\n    i = i + 1\nrepeat 0 times then\n    show i");
+    assert_eq!(coverage.branches[0], branch(2, 3, 1));
+    // The repeat sits inside the while body; it runs each iteration and
+    // never enters its own body
+    assert_eq!(coverage.branches[1], branch(4, 0, 3));
+}
+
+#[test]
+fn test_conditions_on_one_line_get_separate_blocks() {
+    let coverage = coverage("let x be 1\nif x > 0 then if x > 1 then show x");
+    let blocks: Vec<_> = coverage.branches.iter().map(|branch| (branch.line, branch.block)).collect();
+    assert_eq!(blocks, vec![(2, 0), (2, 1)]);
+    assert_eq!(coverage.branches[1].not_taken, 1);
+}
+
+#[test]
+fn test_lcov_output() {
+    let coverage = coverage(&format!("let score be 75\n{}", GRADES));
+    assert_eq!(
+        coverage.lcov("grades.luma"),
+        "TN:\n\
+         SF:grades.luma\n\
+         BRDA:2,0,0,0\n\
+         BRDA:2,0,1,1\n\
+         BRDA:4,0,0,1\n\
+         BRDA:4,0,1,0\n\
+         BRF:4\n\
+         BRH:2\n\
+         DA:1,1\n\
+         DA:2,1\n\
+         DA:3,0\n\
+         DA:4,1\n\
+         DA:5,1\n\
+         DA:7,0\n\
+         LF:6\n\
+         LH:4\n\
+         end_of_record\n"
+    );
+}
+
+#[test]
+fn test_lcov_marks_unreached_conditions() {
+    let coverage = coverage(&format!("let score be 95\n{}", GRADES));
+    let lcov = coverage.lcov("grades.luma");
+    assert!(lcov.contains("BRDA:4,0,0,-\nBRDA:4,0,1,-\n"), "{}", lcov);
+}
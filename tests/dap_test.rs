@@ -0,0 +1,285 @@
+// Integration tests for the Debug Adapter Protocol server, driven by
+// scripted message exchanges
+use luma::debugger::dap::{read_message, serve, write_message, MAX_MESSAGE_LENGTH};
+use serde_json::{json, Value as Json};
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// A script on disk for `launch` to read; the name keeps tests apart
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("luma_dap_{}_{}.luma", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+// Send `requests` (command, arguments) in order and collect every message
+// the server writes back
+fn exchange(requests: &[(&str, Json)]) -> Vec<Json> {
+    let mut input = Vec::new();
+    for (seq, (command, arguments)) in requests.iter().enumerate() {
+        let request = json!({ "seq": seq + 1, "type": "request", "command": command, "arguments": arguments });
+        write_message(&mut input, &request).unwrap();
+    }
+
+    let output = SharedBuffer::default();
+    serve(Cursor::new(input), output.clone()).unwrap();
+
+    let bytes = output.0.borrow().clone();
+    let mut reader = Cursor::new(bytes);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    messages
+}
+
+fn launch(path: &Path, stop_on_entry: bool) -> (&'static str, Json) {
+    ("launch", json!({ "program": path.to_str().unwrap(), "stopOnEntry": stop_on_entry }))
+}
+
+fn breakpoints(lines: &[usize]) -> (&'static str, Json) {
+    let breakpoints: Vec<_> = lines.iter().map(|line| json!({ "line": line })).collect();
+    ("setBreakpoints", json!({ "source": { "path": "script.luma" }, "breakpoints": breakpoints }))
+}
+
+fn response<'a>(messages: &'a [Json], command: &str) -> &'a Json {
+    messages
+        .iter()
+        .find(|message| message["type"] == "response" && message["command"] == command)
+        .unwrap_or_else(|| panic!("no response to {}", command))
+}
+
+fn responses<'a>(messages: &'a [Json], command: &str) -> Vec<&'a Json> {
+    messages
+        .iter()
+        .filter(|message| message["type"] == "response" && message["command"] == command)
+        .collect()
+}
+
+fn events<'a>(messages: &'a [Json], event: &str) -> Vec<&'a Json> {
+    messages
+        .iter()
+        .filter(|message| message["type"] == "event" && message["event"] == event)
+        .collect()
+}
+
+fn program_output(messages: &[Json]) -> String {
+    events(messages, "output")
+        .iter()
+        .filter(|event| event["body"]["category"] == "stdout")
+        .map(|event| event["body"]["output"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_runs_to_completion_without_breakpoints() {
+    let path = script("complete", "let x be 2\nshow x * 21");
+    let messages = exchange(&[
+        ("initialize", json!({ "adapterID": "luma" })),
+        launch(&path, false),
+        ("configurationDone", json!({})),
+        ("disconnect", json!({})),
+    ]);
+
+    assert_eq!(response(&messages, "initialize")["body"]["supportsConfigurationDoneRequest"], true);
+    assert_eq!(events(&messages, "initialized").len(), 1);
+    assert_eq!(program_output(&messages), "42\n");
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 0);
+    assert_eq!(events(&messages, "terminated").len(), 1);
+    assert_eq!(response(&messages, "disconnect")["success"], true);
+
+    // Every message the server sends is numbered in order
+    let seqs: Vec<_> = messages.iter().map(|message| message["seq"].as_u64().unwrap()).collect();
+    assert_eq!(seqs, (1..=messages.len() as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_breakpoint_stack_trace_and_variables() {
+    let path = script("breakpoint", "let name be \"Ada\"\nlet n be 3\nshow name");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, false),
+        breakpoints(&[3]),
+        ("configurationDone", json!({})),
+        ("threads", json!({})),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("scopes", json!({ "frameId": 1 })),
+        ("variables", json!({ "variablesReference": 1 })),
+        ("continue", json!({ "threadId": 1 })),
+        ("disconnect", json!({})),
+    ]);
+
+    let verified = &response(&messages, "setBreakpoints")["body"]["breakpoints"];
+    assert_eq!(verified, &json!([{ "verified": true, "line": 3 }]));
+
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0]["body"]["reason"], "breakpoint");
+
+    let frames = &response(&messages, "stackTrace")["body"]["stackFrames"];
+    assert_eq!(frames[0]["line"], 3);
+    assert_eq!(frames[0]["source"]["path"], path.to_str().unwrap());
+
+    let scopes = &response(&messages, "scopes")["body"]["scopes"];
+    assert_eq!(scopes[0]["name"], "Globals");
+    assert_eq!(scopes[1]["name"], "Stack");
+
+    let variables = &response(&messages, "variables")["body"]["variables"];
+    assert_eq!(variables[0]["name"], "n");
+    assert_eq!(variables[0]["value"], "3");
+    assert_eq!(variables[1]["name"], "name");
+    assert_eq!(variables[1]["value"], "\"Ada\"");
+    assert_eq!(variables[1]["type"], "string");
+
+    assert_eq!(program_output(&messages), "Ada\n");
+}
+
+#[test]
+fn test_stop_on_entry_and_stepping() {
+    let path = script("stepping", "let i be 0\nwhile i < 2 then\n    i = i + 1\nshow i");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, true),
+        ("configurationDone", json!({})),
+        ("next", json!({ "threadId": 1 })),
+        ("stepIn", json!({ "threadId": 1 })),
+        ("next", json!({ "threadId": 1 })),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("continue", json!({ "threadId": 1 })),
+        ("disconnect", json!({})),
+    ]);
+
+    let reasons: Vec<_> = events(&messages, "stopped").iter().map(|event| event["body"]["reason"].clone()).collect();
+    assert_eq!(reasons, vec!["entry", "step", "step", "step"]);
+    assert_eq!(response(&messages, "stackTrace")["body"]["stackFrames"][0]["line"], 4);
+    assert_eq!(program_output(&messages), "1\n2\n");
+}
+
+#[test]
+fn test_set_variable_changes_the_program() {
+    let path = script("set_variable", "let x be 1\nshow x");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, false),
+        breakpoints(&[2]),
+        ("configurationDone", json!({})),
+        ("setVariable", json!({ "variablesReference": 1, "name": "x", "value": "99" })),
+        ("continue", json!({ "threadId": 1 })),
+        ("disconnect", json!({})),
+    ]);
+
+    assert_eq!(response(&messages, "setVariable")["body"]["value"], "99");
+    assert_eq!(program_output(&messages), "99\n");
+}
+
+#[test]
+fn test_breakpoints_without_code_are_unverified() {
+    let path = script("unverified", "let x be 1\n\nshow x");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, false),
+        breakpoints(&[2, 3]),
+        ("configurationDone", json!({})),
+        ("continue", json!({ "threadId": 1 })),
+        ("disconnect", json!({})),
+    ]);
+
+    let verified = &response(&messages, "setBreakpoints")["body"]["breakpoints"];
+    assert_eq!(verified, &json!([{ "verified": false, "line": 2 }, { "verified": true, "line": 3 }]));
+    assert_eq!(events(&messages, "stopped").len(), 1);
+}
+
+#[test]
+fn test_requests_that_need_a_pause_fail_while_not_paused() {
+    let path = script("not_paused", "show 1");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, false),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("evaluate", json!({ "expression": "1" })),
+        ("configurationDone", json!({})),
+        ("disconnect", json!({})),
+    ]);
+
+    let stack_trace = response(&messages, "stackTrace");
+    assert_eq!(stack_trace["success"], false);
+    assert_eq!(stack_trace["message"], "the program is not paused");
+    assert_eq!(response(&messages, "evaluate")["success"], false);
+}
+
+#[test]
+fn test_disconnect_while_paused_stops_the_program() {
+    let path = script("disconnect", "show 1\nshow 2");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, false),
+        breakpoints(&[2]),
+        ("configurationDone", json!({})),
+        ("disconnect", json!({})),
+    ]);
+
+    assert_eq!(program_output(&messages), "1\n");
+    assert_eq!(responses(&messages, "disconnect").len(), 1);
+    assert!(events(&messages, "exited").is_empty());
+}
+
+#[test]
+fn test_runtime_error_is_reported_with_exit_code() {
+    let path = script("runtime_error", "show missing");
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        launch(&path, false),
+        ("configurationDone", json!({})),
+        ("disconnect", json!({})),
+    ]);
+
+    let stderr: Vec<_> = events(&messages, "output")
+        .into_iter()
+        .filter(|event| event["body"]["category"] == "stderr")
+        .collect();
+    assert!(stderr[0]["body"]["output"].as_str().unwrap().contains("missing"));
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 1);
+}
+
+#[test]
+fn test_launch_of_missing_script_fails() {
+    let messages = exchange(&[
+        ("initialize", json!({})),
+        ("launch", json!({ "program": "/nonexistent/script.luma" })),
+        ("disconnect", json!({})),
+    ]);
+
+    let launch = response(&messages, "launch");
+    assert_eq!(launch["success"], false);
+    assert!(launch["message"].as_str().unwrap().starts_with("cannot read"));
+}
+
+#[test]
+fn test_oversized_message_is_rejected_without_reading_it() {
+    let header = format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX);
+    let error = read_message(&mut Cursor::new(header.clone())).unwrap_err();
+    assert!(error.to_string().contains("byte limit"), "{}", error);
+
+    // The server tells the client before it stops
+    let output = SharedBuffer::default();
+    assert!(serve(Cursor::new(header.into_bytes()), output.clone()).is_err());
+    let bytes = output.0.borrow().clone();
+    let message = read_message(&mut Cursor::new(bytes)).unwrap().unwrap();
+    assert_eq!(message["event"], "output");
+    assert!(message["body"]["output"].as_str().unwrap().contains(&MAX_MESSAGE_LENGTH.to_string()));
+}
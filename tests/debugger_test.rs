@@ -0,0 +1,232 @@
+// Integration tests for the line debugger and the VM's debug hook
+mod common;
+
+use luma::backend::vm::{BufferOutput, DebugHook, VM};
+use luma::debugger::{parse_value, CliDebugger, Resume, Stepper};
+use luma::shared::error::{LumaError, Result};
+use luma::shared::value::Value;
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+use common::compile;
+
+// Debugger replies, readable after the VM has taken the debugger
+#[derive(Clone, Default)]
+struct Transcript(Rc<RefCell<Vec<u8>>>);
+
+impl Transcript {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for Transcript {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Run `source` under the CLI debugger, typing `commands` at its prompts.
+// Returns the result, the transcript with prompts removed, and the output.
+fn debug(source: &str, commands: &str) -> (Result<Value>, String, String) {
+    let transcript = Transcript::default();
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    let debugger = CliDebugger::new(source, Cursor::new(commands.to_string()), transcript.clone());
+    vm.set_debug_hook(Some(Box::new(debugger)));
+    let result = vm.interpret(compile(source));
+    (result, transcript.contents().replace("(debug) ", ""), output.contents())
+}
+
+// Records every line the VM reports
+struct LineRecorder(Rc<RefCell<Vec<usize>>>);
+
+impl DebugHook for LineRecorder {
+    fn on_line(&mut self, _vm: &mut VM, line: usize) -> Result<()> {
+        self.0.borrow_mut().push(line);
+        Ok(())
+    }
+}
+
+fn reported_lines(source: &str) -> Vec<usize> {
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let mut vm = VM::new();
+    vm.set_output(BufferOutput::new());
+    vm.set_debug_hook(Some(Box::new(LineRecorder(lines.clone()))));
+    vm.interpret(compile(source)).unwrap();
+    let lines = lines.borrow().clone();
+    lines
+}
+
+// ============================================================================
+// Debug Hook Tests
+// ============================================================================
+
+#[test]
+fn test_hook_reports_each_line_in_order() {
+    assert_eq!(reported_lines("let x be 1\nlet y be 2\nshow x + y"), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_hook_reports_loop_lines_every_iteration() {
+    let lines = reported_lines("let i be 0\nwhile i < 2 then\n    i = i + 1\nshow i");
+    assert_eq!(lines, vec![1, 2, 3, 4, 2, 3, 4, 2]);
+}
+
+#[test]
+fn test_hook_reports_repeat_header_once_per_iteration() {
+    let lines = reported_lines("let x be 0\nrepeat 2 times then\n    x = x + 1");
+    assert_eq!(lines, vec![1, 2, 3, 2, 3, 2]);
+}
+
+#[test]
+fn test_hook_reports_single_line_loop_every_iteration() {
+    let lines = reported_lines("let i be 0\nwhile i < 3 then i = i + 1");
+    assert_eq!(lines, vec![1, 2, 2, 2, 2]);
+}
+
+#[test]
+fn test_hook_error_stops_the_run() {
+    struct StopAt(usize);
+    impl DebugHook for StopAt {
+        fn on_line(&mut self, _vm: &mut VM, line: usize) -> Result<()> {
+            if line == self.0 {
+                return Err(LumaError::Interrupted { span: None });
+            }
+            Ok(())
+        }
+    }
+
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    vm.set_debug_hook(Some(Box::new(StopAt(2))));
+    let err = vm.interpret(compile("show 1\nshow 2")).unwrap_err();
+    assert!(matches!(err.cause(), LumaError::Interrupted { .. }));
+    assert_eq!(output.contents(), "1\n");
+}
+
+// ============================================================================
+// Stepper Tests
+// ============================================================================
+
+#[test]
+fn test_stepper_rules() {
+    let mut stepper = Stepper::new(Resume::Continue);
+    assert!(!stepper.should_pause(1, 0));
+    assert!(stepper.add_breakpoint(3));
+    assert!(!stepper.add_breakpoint(3));
+    assert!(stepper.should_pause(3, 0));
+
+    stepper.resume(Resume::StepOver, 1);
+    assert!(stepper.should_pause(5, 1));
+    assert!(stepper.should_pause(5, 0));
+    assert!(!stepper.should_pause(5, 2));
+
+    stepper.resume(Resume::StepInto, 1);
+    assert!(stepper.should_pause(5, 2));
+
+    assert!(stepper.remove_breakpoint(3));
+    assert!(!stepper.remove_breakpoint(3));
+}
+
+#[test]
+fn test_parse_value() {
+    assert_eq!(parse_value("42"), Some(Value::Number(42.0)));
+    assert_eq!(parse_value(" -1.5 "), Some(Value::Number(-1.5)));
+    assert_eq!(parse_value("true"), Some(Value::Boolean(true)));
+    assert_eq!(parse_value("nil"), Some(Value::Nil));
+    assert_eq!(parse_value("\"hi there\""), Some(Value::String("hi there".into())));
+    assert_eq!(parse_value("hi"), None);
+    assert_eq!(parse_value("inf"), None);
+}
+
+// ============================================================================
+// CLI Debugger Tests
+// ============================================================================
+
+#[test]
+fn test_cli_pauses_at_first_line_and_steps() {
+    let (result, transcript, output) = debug("let x be 1\nshow x + 1", "step\nstep\n");
+    assert_eq!(result.unwrap(), Value::Number(2.0));
+    assert_eq!(transcript, "> 1 | let x be 1\n> 2 | show x + 1\n");
+    assert_eq!(output, "2\n");
+}
+
+#[test]
+fn test_cli_breakpoint_and_continue() {
+    let source = "let i be 0\nwhile i < 3 then\n    i = i + 1\n    show i";
+    let (_, transcript, output) = debug(source, "break 4\ncontinue\nprint i\ncontinue\nprint i\ndelete 4\ncontinue\n");
+    assert_eq!(
+        transcript,
+        "> 1 | let i be 0\n\
+         Breakpoint set at line 4\n\
+         > 4 |     show i\n\
+         i = 1\n\
+         > 4 |     show i\n\
+         i = 2\n\
+         Breakpoint at line 4 removed\n"
+    );
+    assert_eq!(output, "1\n2\n3\n");
+}
+
+#[test]
+fn test_cli_set_global_changes_the_program() {
+    let (result, transcript, output) = debug("let x be 1\nshow x * 2", "next\nset x = 20\nglobals\ncontinue\n");
+    assert_eq!(result.unwrap(), Value::Number(40.0));
+    assert!(transcript.contains("x = 20\nx = 20\n"), "{}", transcript);
+    assert_eq!(output, "40\n");
+}
+
+#[test]
+fn test_cli_set_global_accepts_strings() {
+    let (_, transcript, output) = debug("let name be \"Ada\"\nshow name", "n\nset name \"Grace\"\np name\nc\n");
+    assert!(transcript.contains("name = \"Grace\"\nname = \"Grace\"\n"), "{}", transcript);
+    assert_eq!(output, "Grace\n");
+}
+
+#[test]
+fn test_cli_globals_hide_compiler_names() {
+    let (_, transcript, _) = debug("let x be 0\nrepeat 2 times then\n    x = x + 1", "b 3\nc\ng\nc\n");
+    assert!(transcript.contains("x = 0\n"), "{}", transcript);
+    assert!(!transcript.contains("__repeat"), "{}", transcript);
+}
+
+#[test]
+fn test_cli_shows_the_stack() {
+    let (_, transcript, _) = debug("show 1", "stack\nc\n");
+    assert_eq!(transcript, "> 1 | show 1\n(empty)\n");
+}
+
+#[test]
+fn test_cli_line_and_unknown_commands() {
+    let (_, transcript, _) = debug("let x be 1", "line\nfrobnicate\nprint y\nbreak 9\nc\n");
+    assert_eq!(
+        transcript,
+        "> 1 | let x be 1\n\
+         > 1 | let x be 1\n\
+         Unknown command 'frobnicate'; type 'help' for a list\n\
+         No global named 'y'\n\
+         Expected a line between 1 and 1\n"
+    );
+}
+
+#[test]
+fn test_cli_quit_stops_the_program() {
+    let (result, _, output) = debug("show 1\nshow 2", "n\nquit\n");
+    let err = result.unwrap_err();
+    assert!(matches!(err.cause(), LumaError::Interrupted { .. }));
+    assert_eq!(output, "1\n");
+}
+
+#[test]
+fn test_cli_end_of_input_runs_to_completion() {
+    let (result, _, output) = debug("let x be 1\nshow x\nshow x + 1", "b 3\n");
+    assert_eq!(result.unwrap(), Value::Number(2.0));
+    assert_eq!(output, "1\n2\n");
+}
@@ -0,0 +1,105 @@
+// Tests for diagnostic rendering
+use luma::backend::vm::vm::VM;
+use luma::frontend::compiler::Compiler;
+use luma::frontend::lexer::Lexer;
+use luma::frontend::parser::Parser;
+use luma::shared::diagnostic::{edit_distance, suggest_name, Diagnostic};
+use luma::shared::error::LumaError;
+
+// Run source through the whole pipeline and render the failure as plain text
+fn render_failure(source: &str) -> String {
+    let mut vm = VM::new();
+    let error = run(source, &mut vm).expect_err("expected the program to fail");
+    Diagnostic::from_error(&error, vm.global_names()).render(source, "test.luma", false)
+}
+
+fn run(source: &str, vm: &mut VM) -> Result<(), LumaError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let statements = Parser::new(tokens).parse()?;
+    let chunk = Compiler::new().compile(&statements)?;
+    vm.interpret(chunk)?;
+    Ok(())
+}
+
+#[test]
+fn test_undefined_variable_suggests_close_global() {
+    let source = "let counter be 1\nshow countr + 1";
+    let expected = "\
+runtime error[E0101]: Undefined variable 'countr'
+ --> test.luma:2:6
+  |
+2 | show countr + 1
+  |      ^^^^^^
+  |
+  = help: did you mean `counter`?
+";
+    assert_eq!(render_failure(source), expected);
+}
+
+#[test]
+fn test_undefined_variable_without_close_match() {
+    let output = render_failure("let apples be 1\nshow zebra");
+    assert!(output.contains("help: define it first with `let zebra be ...`"));
+    assert!(!output.contains("did you mean"));
+}
+
+#[test]
+fn test_parse_error_underlines_token() {
+    let expected = "\
+parse error[E0002]: Expected 'be' or 'is' after identifier
+ --> test.luma:1:7
+  |
+1 | let x 5
+  |       ^
+";
+    assert_eq!(render_failure("let x 5"), expected);
+}
+
+#[test]
+fn test_underline_keeps_tabs_aligned() {
+    let output = render_failure("let total be 0\n\ttotal = total - \"x\"");
+    assert!(output.contains("2 | \ttotal = total - \"x\"\n"));
+    assert!(output.contains("  | \t        ^^^^^^^^^^^\n"));
+}
+
+#[test]
+fn test_colored_output_uses_ansi_codes() {
+    let diagnostic = Diagnostic::new("runtime error", "boom").with_note("extra context");
+    let colored = diagnostic.render("", "test.luma", true);
+    let plain = diagnostic.render("", "test.luma", false);
+    assert!(colored.contains("\x1b["));
+    assert!(!plain.contains("\x1b["));
+    assert_eq!(plain, "runtime error: boom\n = note: extra context\n");
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("counter", "countr"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("same", "same"), 0);
+}
+
+#[test]
+fn test_suggest_name_ignores_distant_and_internal_names() {
+    let names = ["total", "__repeat_counter_4", "price"];
+    assert_eq!(suggest_name("totl", names), Some("total"));
+    assert_eq!(suggest_name("__repeat_counter_5", names), None);
+    assert_eq!(suggest_name("quantity", names), None);
+}
+
+#[test]
+fn test_runtime_trace_is_rendered_as_notes() {
+    let source = "let total be 0\nwhile total < 3 then\n    total = total * \"many\"";
+    let expected = "\
+runtime error[E0102]: Cannot apply '*' to number and string
+ --> test.luma:3:13
+  |
+3 |     total = total * \"many\"
+  |             ^^^^^^^^^^^^^^
+  |
+  = note: operands were 0 and \"many\"
+  = note: inside the loop at line 2
+";
+    assert_eq!(render_failure(source), expected);
+}
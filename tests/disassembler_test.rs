@@ -0,0 +1,195 @@
+// Tests for the bytecode disassembler and assembler
+mod common;
+
+use luma::backend::vm::instruction::OpCode;
+use luma::backend::vm::vm::VM;
+use luma::shared::assembler::assemble;
+use luma::shared::chunk::Chunk;
+use luma::shared::error::LumaError;
+use luma::shared::span::Span;
+use luma::shared::value::Value;
+use common::compile;
+
+fn code_lines(text: &str) -> Vec<&str> {
+    text.lines().skip_while(|line| *line != "-- code --").skip(1).collect()
+}
+
+#[test]
+fn test_every_opcode_is_decoded() {
+    let mut chunk = Chunk::new();
+    let name = chunk.intern_string("x");
+    let span = Span::new(1, 1, 1);
+
+    let mut byte = 0;
+    while let Some(opcode) = OpCode::from_byte(byte) {
+        chunk.write_opcode(opcode, span);
+        if opcode.operand_count() == 1 {
+            chunk.write_byte(name as u8, span);
+        }
+        byte += 1;
+    }
+
+    let text = chunk.disassemble("all");
+    let lines = code_lines(&text);
+    assert_eq!(lines.len(), byte as usize);
+    for (line, opcode) in lines.iter().zip((0..byte).filter_map(OpCode::from_byte)) {
+        assert!(line.contains(&format!("{:?}", opcode)), "{}", line);
+        assert!(!line.contains("Unknown"), "{}", line);
+    }
+}
+
+#[test]
+fn test_operands_are_decoded() {
+    let source = "let greeting be \"hi\"\nlet n be 0\nwhile n < 2 then\n    n = n + 1";
+    let text = compile(source).disassemble("test");
+
+    assert!(text.contains("OpConstant           0  \"hi\""), "{}", text);
+    assert!(text.contains("OpSetGlobal          1  greeting"), "{}", text);
+    assert!(text.contains("OpGetGlobal          3  n"), "{}", text);
+
+    // Both jumps point at real instruction offsets
+    let lines = code_lines(&text);
+    let offsets: Vec<&str> = lines.iter().map(|line| &line[..4]).collect();
+    for line in &lines {
+        if let Some(target) = line.split("-> ").nth(1) {
+            assert!(offsets.contains(&target), "{} jumps to a missing offset", line);
+        }
+    }
+    assert!(lines.iter().any(|line| line.contains("OpLoop")));
+}
+
+#[test]
+fn test_spans_and_constant_pool() {
+    let text = compile("show 1.5 + 2").disassemble("spans");
+    let expected = "\
+== spans ==
+-- constants --
+   0  number   1.5
+   1  number   2.0
+-- code --
+0000  1:6+3     OpConstant           0  1.5
+0002  1:12+1    OpConstant           1  2
+0004  1:6+7     OpAdd
+0005  1:1+12    OpPrint
+0006  |         OpReturn
+";
+    assert_eq!(text, expected);
+}
+
+#[test]
+fn test_malformed_code_is_reported() {
+    let mut chunk = Chunk::new();
+    chunk.add_constant(Value::Number(1.0));
+    chunk.write_byte(250, Span::default());
+    chunk.write_opcode(OpCode::OpConstant, Span::default());
+
+    let text = chunk.disassemble("bad");
+    assert!(text.contains("Unknown opcode 250"), "{}", text);
+    assert!(text.contains("<missing operand>"), "{}", text);
+}
+
+// === Assembler ===
+
+#[test]
+fn test_examples_round_trip_through_assembler() {
+    for entry in std::fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("luma") {
+            continue;
+        }
+        let chunk = compile(&std::fs::read_to_string(&path).unwrap());
+        let text = chunk.disassemble(&path.display().to_string());
+        let assembled = assemble(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert!(assembled == chunk, "{} did not round-trip", path.display());
+    }
+}
+
+#[test]
+fn test_round_trip_preserves_unusual_constants() {
+    let mut chunk = Chunk::new();
+    for value in [
+        Value::Number(-0.0),
+        Value::Number(0.0),
+        Value::Number(f64::NAN),
+        Value::Number(1e300),
+        Value::from("tab\there \"quoted\" \\ \u{1b}[0m ünï"),
+        Value::Boolean(false),
+        Value::Nil,
+    ] {
+        chunk.constants.push(value);
+    }
+    chunk.constants.push(Value::Number(0.0)); // Duplicates survive too
+    chunk.globals.insert("answer".to_string(), 3);
+    chunk.write_byte(OpCode::OpNil as u8, Span::default());
+
+    assert!(assemble(&chunk.disassemble("odd")).unwrap() == chunk);
+}
+
+#[test]
+fn test_round_trip_preserves_malformed_code() {
+    let mut chunk = Chunk::new();
+    chunk.write_byte(250, Span::new(2, 3, 4));
+    chunk.write_opcode(OpCode::OpConstant, Span::new(2, 3, 4));
+
+    assert!(assemble(&chunk.disassemble("bad")).unwrap() == chunk);
+}
+
+#[test]
+fn test_hand_written_program_runs() {
+    let source = r#"
+        ; Count i from 0 to 3, then leave it on the stack
+        .const zero 0
+        .const one 1
+        .const limit 3
+        .const i "i"
+        .line 1
+            OpConstant zero
+            OpDefineGlobal i
+        .line 2:1+5
+        top:
+            OpLoopStart
+            OpGetGlobal i
+            OpConstant limit
+            OpLess
+            OpJumpIfFalse done
+            OpPop
+            OpGetGlobal i
+            OpConstant one
+            OpAdd
+            OpSetGlobal i
+            OpPop
+            OpLoop top
+        done:
+            OpPop
+            OpLoopEnd
+            OpGetGlobal i
+            OpReturn
+    "#;
+    let chunk = assemble(source).unwrap();
+    assert_eq!(chunk.get_span(0), Span::new(1, 1, 0));
+    assert_eq!(chunk.get_span(4), Span::new(2, 1, 5));
+
+    let result = VM::new().interpret(chunk).unwrap();
+    assert_eq!(result, Value::Number(3.0));
+}
+
+#[test]
+fn test_assembler_errors_point_at_the_line() {
+    let cases = [
+        ("OpConstant missing", 1, "unknown constant 'missing'"),
+        ("OpNil\nOpJump nowhere", 2, "unknown label 'nowhere'"),
+        ("OpNil\n\nOpFrobnicate", 3, "unknown instruction 'OpFrobnicate'"),
+        ("OpConstant 300", 1, "does not fit in a byte"),
+        (".const s \"open", 1, "unterminated string"),
+    ];
+
+    for (source, line, expected) in cases {
+        match assemble(source) {
+            Err(LumaError::ParseError { message, span }) => {
+                assert_eq!(span.line, line, "{}", source);
+                assert!(message.contains(expected), "{}: {}", source, message);
+            }
+            other => panic!("{}: expected a parse error, got {:?}", source, other),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+// Tests for the register-based VM backend
+use luma::backend::register::{Instruction, RegisterChunk, RegisterCompiler, RegisterVM};
+use luma::backend::vm::vm::VM;
+use luma::backend::vm::BufferOutput;
+use luma::backend::Backend;
+use luma::frontend::ast::Statement;
+use luma::frontend::compiler::Compiler;
+use luma::frontend::lexer::Lexer;
+use luma::frontend::parser::Parser;
+use luma::shared::error::LumaError;
+use luma::shared::span::Span;
+use luma::shared::value::Value;
+
+fn parse(source: &str) -> Vec<Statement> {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    Parser::new(tokens).parse().unwrap()
+}
+
+// Result and captured output of running on each backend
+fn run_register(source: &str) -> (Result<Value, LumaError>, String) {
+    let output = BufferOutput::new();
+    let mut vm = RegisterVM::new();
+    vm.set_output(output.clone());
+    let result = RegisterCompiler::new().compile(&parse(source)).and_then(|chunk| vm.interpret(&chunk));
+    (result, output.contents())
+}
+
+fn run_stack(source: &str) -> (Result<Value, LumaError>, String) {
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    let result = Compiler::new().compile(&parse(source)).and_then(|chunk| vm.interpret(chunk));
+    (result, output.contents())
+}
+
+// Both backends must agree on the output, and on the result or on the
+// error and its location
+fn assert_same(source: &str) {
+    let (stack, stack_output) = run_stack(source);
+    let (register, register_output) = run_register(source);
+    assert_eq!(stack_output, register_output, "{}", source);
+
+    match (stack, register) {
+        (Ok(stack), Ok(register)) => assert_eq!(stack, register, "{}", source),
+        (Err(stack), Err(register)) => {
+            assert_eq!(stack.to_string(), register.to_string(), "{}", source);
+            assert_eq!(stack.span(), register.span(), "{}", source);
+        }
+        (stack, register) => panic!("{}: stack {:?}, register {:?}", source, stack, register),
+    }
+}
+
+#[test]
+fn test_backends_agree() {
+    let programs = [
+        "show 1 + 2 * 3 - 4 / 8",
+        "show 17 % 5",
+        "show -(3 + 4)",
+        "show \"total: \" + 40 + 2",
+        "let a be 5\nlet b be 3\nshow (a + b) * (a - b)",
+        "show 3 > 2 and not (1 == 2)",
+        "show false or 1 != 1",
+        "show 2 >= 2\n",
+        "let x be 0\nwhile x < 10 then\n    x = x + 3\nshow x",
+        "let n be 7\nif n > 10 then\n    show \"big\"\nelse if n > 5 then\n    show \"medium\"\nelse\n    show \"small\"",
+        "show missing + 1",
+        "let x be 1\nshow x / 0",
+        "show 5 % 0",
+        "show -\"abc\"",
+        "show nothing < 3",
+    ];
+    for program in programs {
+        assert_same(program);
+    }
+}
+
+#[test]
+fn test_examples_produce_same_results() {
+    for entry in std::fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) == Some("luma") {
+            assert_same(&std::fs::read_to_string(&path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_repeat_loop() {
+    let mut vm = RegisterVM::new();
+    let chunk = RegisterCompiler::new()
+        .compile(&parse("let total be 0\nrepeat 4 times then\n    total = total + 2"))
+        .unwrap();
+    vm.interpret(&chunk).unwrap();
+    assert_eq!(vm.get_global("total"), Some(&Value::Number(8.0)));
+}
+
+#[test]
+fn test_globals_persist_between_chunks() {
+    let mut vm = RegisterVM::new();
+    let first = RegisterCompiler::new().compile(&parse("let x be 40")).unwrap();
+    let second = RegisterCompiler::new().compile(&parse("show x + 2")).unwrap();
+
+    vm.interpret(&first).unwrap();
+    assert_eq!(vm.interpret(&second).unwrap(), Value::Number(42.0));
+    assert_eq!(vm.global_names().collect::<Vec<_>>(), vec!["x"]);
+}
+
+#[test]
+fn test_three_address_code() {
+    let chunk = RegisterCompiler::new().compile(&parse("let a be 2\nshow a * a + 1")).unwrap();
+    let a = 0;
+    assert_eq!(
+        chunk.code,
+        vec![
+            Instruction::LoadConst { dst: 0, constant: 0 },
+            Instruction::SetGlobal { global: a, src: 0 },
+            Instruction::GetGlobal { dst: 0, global: a },
+            Instruction::GetGlobal { dst: 1, global: a },
+            Instruction::Multiply { dst: 0, lhs: 0, rhs: 1 },
+            Instruction::LoadConst { dst: 1, constant: 1 },
+            Instruction::Add { dst: 0, lhs: 0, rhs: 1 },
+            Instruction::Print { src: 0 },
+            Instruction::Return { src: 0 },
+        ]
+    );
+    assert_eq!(chunk.register_count, 2);
+
+    let text = chunk.disassemble("test");
+    assert!(text.contains("MUL      r0, r0, r1"), "{}", text);
+    assert!(text.contains("SETG     g0, r0         ; a"), "{}", text);
+}
+
+#[test]
+fn test_long_jumps_are_not_limited_to_a_byte() {
+    // The stack VM's 8-bit jump offsets cannot span a body this large
+    let mut source = String::from("let i be 0\nwhile i < 2 then\n");
+    for _ in 0..100 {
+        source.push_str("    i = i + 1 - 1\n");
+    }
+    source.push_str("    i = i + 1\n");
+
+    let mut vm = RegisterVM::new();
+    vm.interpret(&RegisterCompiler::new().compile(&parse(&source)).unwrap()).unwrap();
+    assert_eq!(vm.get_global("i"), Some(&Value::Number(2.0)));
+}
+
+#[test]
+fn test_rejects_malformed_chunk() {
+    let chunk = RegisterChunk {
+        code: vec![Instruction::Print { src: 3 }],
+        spans: vec![Span::default()],
+        register_count: 1,
+        ..Default::default()
+    };
+    let err = RegisterVM::new().interpret(&chunk).unwrap_err();
+    assert!(matches!(err, LumaError::VerifyError { offset: 0, .. }), "{:?}", err);
+}
+
+#[test]
+fn test_backend_names() {
+    assert_eq!("stack".parse::<Backend>(), Ok(Backend::Stack));
+    assert_eq!("register".parse::<Backend>(), Ok(Backend::Register));
+    assert!("heap".parse::<Backend>().is_err());
+    assert_eq!(Backend::default(), Backend::Stack);
+}
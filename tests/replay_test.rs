@@ -0,0 +1,187 @@
+// Integration tests for recording and replaying run inputs
+mod common;
+
+use luma::backend::vm::{BufferOutput, DebugHook, InputEvent, InputLog, RunLog, VmConfig, VM};
+use luma::shared::error::{Limit, LumaError, Result};
+use luma::shared::value::Value;
+use std::time::Duration;
+use common::compile;
+
+// Shows a line every 10 iterations until something stops it
+const SPIN: &str = "let i be 0\nwhile true then\n    i = i + 1\n    if i % 10 == 0 then show i";
+
+// Run `source` with `log` installed; returns the result, the output and
+// the log's events
+fn run(source: &str, config: VmConfig, log: InputLog) -> (Result<Value>, String, Vec<InputEvent>) {
+    let output = BufferOutput::new();
+    let mut vm = VM::with_config(config);
+    vm.set_output(output.clone());
+    vm.set_input_log(Some(log));
+    let result = vm.interpret(compile(source));
+    let events = vm.input_log().unwrap().events().to_vec();
+    (result, output.contents(), events)
+}
+
+fn timeout(ms: u64) -> VmConfig {
+    VmConfig::default().with_timeout(Some(Duration::from_millis(ms)))
+}
+
+#[test]
+fn test_finished_run_records_no_inputs() {
+    let (result, output, events) = run("show 1\nrepeat 3 times then\n    show 2", timeout(60_000), InputLog::record());
+    assert!(result.is_ok());
+    assert_eq!(output, "1\n2\n2\n2\n");
+    assert!(events.is_empty());
+}
+
+#[test]
+fn test_timeout_replays_at_the_recorded_instruction() {
+    let (result, recorded_output, events) = run(SPIN, timeout(20), InputLog::record());
+    assert!(matches!(result.unwrap_err().cause(), LumaError::LimitExceeded { limit: Limit::Timeout(_), .. }));
+    assert!(matches!(events[..], [InputEvent::Timeout { .. }]));
+
+    // Replays stop at the same point however fast they run
+    for _ in 0..3 {
+        let (result, output, replayed) = run(SPIN, timeout(20), InputLog::replay(events.clone()));
+        assert!(matches!(result.unwrap_err().cause(), LumaError::LimitExceeded { .. }));
+        assert_eq!(output, recorded_output);
+        assert_eq!(replayed, events);
+    }
+}
+
+#[test]
+fn test_replay_ignores_the_clock() {
+    // A deadline that has already passed only stops a replay where the
+    // recording says
+    let (_, recorded_output, events) = run(SPIN, timeout(20), InputLog::record());
+    let (result, output, _) = run(SPIN, timeout(0), InputLog::replay(events));
+    assert!(result.is_err());
+    assert_eq!(output, recorded_output);
+
+    let (result, output, _) = run("show 1\nrepeat 5 times then\n    show 2", timeout(0), InputLog::replay(Vec::new()));
+    assert!(result.is_ok());
+    assert_eq!(output, "1\n2\n2\n2\n2\n2\n");
+}
+
+#[test]
+fn test_interrupt_replays_without_a_request() {
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    vm.set_input_log(Some(InputLog::record()));
+    vm.interrupt_handle().interrupt();
+    let err = vm.interpret(compile(SPIN)).unwrap_err();
+    assert!(matches!(err.cause(), LumaError::Interrupted { .. }));
+    let events = vm.input_log().unwrap().events().to_vec();
+    assert!(matches!(events[..], [InputEvent::Interrupt { .. }]));
+
+    let (result, replayed, _) = run(SPIN, VmConfig::default(), InputLog::replay(events));
+    assert!(matches!(result.unwrap_err().cause(), LumaError::Interrupted { .. }));
+    assert_eq!(replayed, output.contents());
+}
+
+// Sets `roll` from a host input named `source` each time line 4 starts
+struct Roll<F: FnMut() -> Value>(&'static str, F);
+
+impl<F: FnMut() -> Value> DebugHook for Roll<F> {
+    fn on_line(&mut self, vm: &mut VM, line: usize) -> Result<()> {
+        if line == 4 {
+            let value = vm.host_input(self.0, &mut self.1)?;
+            vm.set_global("roll", value);
+        }
+        Ok(())
+    }
+}
+
+const ROLLS: &str = "let roll be 0\nlet i be 0\nwhile i < 3 then\n    show roll\n    i = i + 1";
+
+#[test]
+fn test_host_inputs_replay_without_asking_the_host() {
+    let run_rolls = |hook: Box<dyn DebugHook>, log: InputLog| {
+        let output = BufferOutput::new();
+        let mut vm = VM::new();
+        vm.set_output(output.clone());
+        vm.set_input_log(Some(log));
+        vm.set_debug_hook(Some(hook));
+        let result = vm.interpret(compile(ROLLS));
+        (result, output.contents(), vm.input_log().unwrap().events().to_vec())
+    };
+
+    let mut next = 6.0;
+    let dice = move || {
+        next += 1.0;
+        Value::Number(next)
+    };
+    let (result, output, events) = run_rolls(Box::new(Roll("random", dice)), InputLog::record());
+    assert!(result.is_ok());
+    assert_eq!(output, "7\n8\n9\n");
+    assert_eq!(events.len(), 3);
+    assert!(matches!(&events[0], InputEvent::Host { source, value: Value::Number(n), .. } if source == "random" && *n == 7.0));
+
+    let unused = || -> Value { panic!("a replay must not ask the host") };
+    let (result, replayed, replayed_events) = run_rolls(Box::new(Roll("random", unused)), InputLog::replay(events.clone()));
+    assert!(result.is_ok());
+    assert_eq!(replayed, output);
+    assert_eq!(replayed_events, events);
+
+    // Asking for a different input than the recording has is an error
+    let (result, _, _) = run_rolls(Box::new(Roll("clock", unused)), InputLog::replay(events));
+    let err = result.unwrap_err();
+    assert!(err.to_string().contains("no clock input"), "{}", err);
+}
+
+#[test]
+fn test_run_log_round_trip() {
+    let mut log = RunLog::new("spin.luma", SPIN, &timeout(20));
+    log.inputs = vec![
+        InputEvent::Host { instruction: 12, source: "stdin".to_string(), value: Value::from("yes") },
+        InputEvent::Timeout { instruction: 1234 },
+    ];
+    log.output = "10\n20\n".to_string();
+    log.error = Some("Execution limit exceeded: ran longer than 20ms".to_string());
+
+    let decoded = RunLog::decode(&log.encode().unwrap()).unwrap();
+    assert_eq!(decoded, log);
+    assert_eq!(decoded.config().timeout, Some(Duration::from_millis(20)));
+
+    let err = RunLog::decode(b"LUMAS\0not a log").unwrap_err();
+    assert_eq!(err.code(), "E0601");
+    assert!(err.to_string().contains("not a Luma run log"), "{}", err);
+}
+
+#[test]
+fn test_run_log_rejects_oversized_length_prefixes() {
+    // A script path claiming 2^62 bytes
+    let mut bytes = RunLog::new("a.luma", "show 1", &VmConfig::default()).encode().unwrap();
+    bytes.truncate(10);
+    bytes.extend_from_slice(&(1u64 << 62).to_le_bytes());
+    let err = RunLog::decode(&bytes).unwrap_err();
+    assert_eq!(err.code(), "E0601");
+}
+
+#[test]
+fn test_divergence_names_the_first_difference() {
+    let mut recorded = RunLog::new("a.luma", "show 1", &VmConfig::default());
+    recorded.output = "1\n2\n3\n".to_string();
+    assert_eq!(recorded.divergence(&recorded.clone()), None);
+
+    let mut replayed = recorded.clone();
+    replayed.output = "1\n2\n".to_string();
+    assert_eq!(
+        recorded.divergence(&replayed).unwrap(),
+        "output line 3 differs: recorded \"3\\n\", replayed nothing"
+    );
+
+    let mut replayed = recorded.clone();
+    replayed.error = Some("Interrupted".to_string());
+    assert_eq!(
+        recorded.divergence(&replayed).unwrap(),
+        "recorded run ended with success, replay ended with Interrupted"
+    );
+
+    let mut replayed = recorded.clone();
+    recorded.inputs = vec![InputEvent::Interrupt { instruction: 5 }];
+    assert_eq!(recorded.divergence(&replayed).unwrap(), "replay used 0 of the 1 recorded inputs");
+    replayed.inputs = recorded.inputs.clone();
+    assert_eq!(recorded.divergence(&replayed), None);
+}
@@ -0,0 +1,150 @@
+// Integration tests for VM snapshots
+mod common;
+
+use luma::backend::vm::{BufferOutput, DebugHook, VmSnapshot, VM};
+use luma::shared::error::{LumaError, Result};
+use luma::shared::value::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+use common::compile;
+
+fn run(vm: &mut VM, source: &str) {
+    vm.interpret(compile(source)).unwrap();
+}
+
+// Takes a snapshot on reaching `line`, then stops the run
+struct SnapshotAt(usize, Rc<RefCell<Vec<u8>>>);
+
+impl DebugHook for SnapshotAt {
+    fn on_line(&mut self, vm: &mut VM, line: usize) -> Result<()> {
+        if line == self.0 {
+            *self.1.borrow_mut() = vm.snapshot()?;
+            return Err(LumaError::Interrupted { span: None });
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_snapshot_round_trips_globals() {
+    let mut vm = VM::new();
+    vm.set_output(BufferOutput::new());
+    run(&mut vm, "let n be 42\nlet name be \"Ada\"\nlet ok be true");
+    let bytes = vm.snapshot().unwrap();
+
+    let output = BufferOutput::new();
+    let mut restored = VM::new();
+    restored.set_output(output.clone());
+    restored.restore(&bytes).unwrap();
+    assert_eq!(restored.get_global("n"), Some(&Value::Number(42.0)));
+    run(&mut restored, "show name + \"!\"\nshow ok");
+    assert_eq!(output.contents(), "Ada!\ntrue\n");
+}
+
+#[test]
+fn test_snapshot_lists_globals_by_name() {
+    let mut vm = VM::new();
+    vm.set_output(BufferOutput::new());
+    run(&mut vm, "let b be 2\nlet a be 1");
+    let snapshot = VmSnapshot::decode(&vm.snapshot().unwrap()).unwrap();
+    let names: Vec<_> = snapshot.globals.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b"]);
+    assert!(snapshot.paused.is_none());
+}
+
+#[test]
+fn test_restore_replaces_existing_globals() {
+    let mut vm = VM::new();
+    vm.set_output(BufferOutput::new());
+    run(&mut vm, "let kept be 1");
+    let bytes = vm.snapshot().unwrap();
+
+    run(&mut vm, "let extra be 2\nkept = 3");
+    vm.restore(&bytes).unwrap();
+    assert_eq!(vm.get_global("kept"), Some(&Value::Number(1.0)));
+    assert_eq!(vm.get_global("extra"), None);
+}
+
+#[test]
+fn test_paused_run_resumes_in_a_new_vm() {
+    // The pause lands inside the loop, whose counter is a hidden global
+    let source = "let total be 0\nshow \"start\"\nrepeat 3 times then\n    total = total + 1\n    show total";
+    let bytes = Rc::new(RefCell::new(Vec::new()));
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    vm.set_debug_hook(Some(Box::new(SnapshotAt(5, bytes.clone()))));
+    assert!(vm.interpret(compile(source)).is_err());
+    assert_eq!(output.contents(), "start\n");
+
+    let snapshot = VmSnapshot::decode(&bytes.borrow()).unwrap();
+    assert!(snapshot.paused.is_some());
+    assert!(snapshot.globals.iter().any(|(name, _)| name.starts_with("__repeat_counter")));
+
+    let output = BufferOutput::new();
+    let mut restored = VM::new();
+    restored.set_output(output.clone());
+    restored.restore(&bytes.borrow()).unwrap();
+    restored.resume().unwrap();
+    assert_eq!(output.contents(), "1\n2\n3\n");
+
+    // A finished run cannot be resumed again
+    assert!(restored.resume().is_err());
+}
+
+#[test]
+fn test_resume_without_paused_run() {
+    let mut vm = VM::new();
+    let err = vm.resume().unwrap_err();
+    assert!(err.to_string().contains("no paused run"), "{}", err);
+}
+
+#[test]
+fn test_restore_while_running_fails() {
+    struct RestoreAt(Vec<u8>, Rc<RefCell<Option<LumaError>>>);
+    impl DebugHook for RestoreAt {
+        fn on_line(&mut self, vm: &mut VM, _line: usize) -> Result<()> {
+            if let Err(err) = vm.restore(&self.0) {
+                *self.1.borrow_mut() = Some(err);
+            }
+            Ok(())
+        }
+    }
+
+    let mut vm = VM::new();
+    let bytes = vm.snapshot().unwrap();
+    let error = Rc::new(RefCell::new(None));
+    vm.set_output(BufferOutput::new());
+    vm.set_debug_hook(Some(Box::new(RestoreAt(bytes, error.clone()))));
+    run(&mut vm, "show 1");
+    let error = error.borrow_mut().take().unwrap();
+    assert!(error.to_string().contains("while the VM is running"), "{}", error);
+}
+
+#[test]
+fn test_restore_rejects_invalid_snapshots() {
+    let mut vm = VM::new();
+    let err = vm.restore(b"not a snapshot").unwrap_err();
+    assert!(matches!(err, LumaError::SerializationError(_)));
+    assert_eq!(err.code(), "E0601");
+
+    let mut bytes = VM::new().snapshot().unwrap();
+    bytes[6] = 99;
+    let err = vm.restore(&bytes).unwrap_err();
+    assert!(err.to_string().contains("unsupported snapshot format version 99"), "{}", err);
+}
+
+#[test]
+fn test_restore_rejects_oversized_length_prefixes() {
+    // One global whose name claims 2^62 bytes
+    let mut bytes = VM::new().snapshot().unwrap();
+    bytes.truncate(10);
+    bytes.extend_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&(1u64 << 62).to_le_bytes());
+    let mut vm = VM::new();
+    assert!(matches!(vm.restore(&bytes).unwrap_err(), LumaError::SerializationError(_)));
+
+    // A globals list claiming 2^62 entries
+    bytes[10..18].copy_from_slice(&(1u64 << 62).to_le_bytes());
+    assert!(VmSnapshot::decode(&bytes).is_err());
+}
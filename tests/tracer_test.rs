@@ -0,0 +1,60 @@
+// Integration tests for instruction-level tracing
+mod common;
+
+use luma::backend::vm::{BufferOutput, InstructionTracer, VM};
+use common::compile;
+
+// Run `source` with `tracer` writing to a buffer; returns the trace and the
+// program's own output
+fn trace(source: &str, tracer: impl FnOnce(BufferOutput) -> InstructionTracer) -> (String, String) {
+    let trace = BufferOutput::new();
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    vm.set_tracer(Some(tracer(trace.clone())));
+    vm.interpret(compile(source)).unwrap();
+    (trace.contents(), output.contents())
+}
+
+#[test]
+fn test_trace_shows_offset_span_instruction_and_stack() {
+    let (trace, output) = trace("show 1 + 2", InstructionTracer::new);
+    assert_eq!(
+        trace,
+        "0000  1:6+1     OpConstant           0  1\n\
+         0002  1:10+1    OpConstant           1  2            [ 1 ]\n\
+         0004  1:6+5     OpAdd                                [ 1 ][ 2 ]\n\
+         0005  1:1+10    OpPrint                              [ 3 ]\n\
+         0006  1:1+10    OpReturn                             [ 3 ]\n"
+    );
+    assert_eq!(output, "3\n");
+}
+
+#[test]
+fn test_trace_follows_jumps_and_loops() {
+    let (trace, _) = trace("let i be 0\nwhile i < 2 then\n    i = i + 1", InstructionTracer::new);
+    let loops = trace.lines().filter(|line| line.contains("OpLoop ")).count();
+    let exits = trace.lines().filter(|line| line.contains("OpJumpIfFalse")).count();
+    assert_eq!(loops, 2);
+    assert_eq!(exits, 3);
+}
+
+#[test]
+fn test_trace_filtered_to_lines() {
+    let source = "let a be 1\nlet b be 2\nlet c be 3";
+    let (trace, _) = trace(source, |output| InstructionTracer::new(output).with_lines(2..=2));
+    assert!(!trace.is_empty());
+    assert!(trace.lines().all(|line| line[6..].starts_with("2:")), "{}", trace);
+}
+
+#[test]
+fn test_trace_does_not_change_the_program() {
+    let source = "let s be \"a\"\nrepeat 3 times then\n    s = s + \"b\"\nshow s";
+    let (_, traced) = trace(source, InstructionTracer::new);
+
+    let output = BufferOutput::new();
+    let mut vm = VM::new();
+    vm.set_output(output.clone());
+    vm.interpret(compile(source)).unwrap();
+    assert_eq!(traced, output.contents());
+}
@@ -0,0 +1,156 @@
+// Tests for the bytecode verifier
+mod common;
+
+use luma::backend::vm::instruction::OpCode;
+use luma::backend::vm::verifier::verify;
+use luma::backend::vm::vm::VM;
+use luma::shared::chunk::Chunk;
+use luma::shared::error::LumaError;
+use luma::shared::span::Span;
+use luma::shared::value::Value;
+use common::compile;
+
+// Build a chunk from raw bytes, giving every byte a line-1 span
+fn chunk_from(code: &[u8], constants: &[Value]) -> Chunk {
+    let mut chunk = Chunk::new();
+    for constant in constants {
+        chunk.add_constant(constant.clone());
+    }
+    for &byte in code {
+        chunk.write_byte(byte, Span::new(1, 1, 1));
+    }
+    chunk
+}
+
+fn verify_error(chunk: &Chunk) -> (usize, String) {
+    match verify(chunk) {
+        Err(LumaError::VerifyError { offset, message }) => (offset, message),
+        other => panic!("expected a verification error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_compiled_examples_verify() {
+    for entry in std::fs::read_dir("examples").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("luma") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).unwrap();
+        if let Err(e) = verify(&compile(&source)) {
+            panic!("{} failed verification: {}", path.display(), e);
+        }
+    }
+}
+
+#[test]
+fn test_reports_max_stack_depth() {
+    let chunk = compile("show 1 + 2 * 3");
+    assert_eq!(verify(&chunk).unwrap().max_stack_depth, 3);
+}
+
+#[test]
+fn test_rejects_unknown_opcode() {
+    let chunk = chunk_from(&[OpCode::OpNil as u8, 200], &[]);
+    let (offset, message) = verify_error(&chunk);
+    assert_eq!(offset, 1);
+    assert!(message.contains("unknown opcode 200"), "{}", message);
+}
+
+#[test]
+fn test_rejects_missing_operand() {
+    let chunk = chunk_from(&[OpCode::OpConstant as u8], &[Value::Number(1.0)]);
+    let (offset, message) = verify_error(&chunk);
+    assert_eq!(offset, 0);
+    assert!(message.contains("missing its operand"), "{}", message);
+}
+
+#[test]
+fn test_rejects_constant_out_of_range() {
+    let chunk = chunk_from(&[OpCode::OpConstant as u8, 3, OpCode::OpReturn as u8], &[Value::Number(1.0)]);
+    let (offset, message) = verify_error(&chunk);
+    assert_eq!(offset, 0);
+    assert!(message.contains("constant index 3 out of range"), "{}", message);
+}
+
+#[test]
+fn test_rejects_non_string_global_name() {
+    let chunk = chunk_from(&[OpCode::OpGetGlobal as u8, 0], &[Value::Number(1.0)]);
+    let (_, message) = verify_error(&chunk);
+    assert!(message.contains("not a string"), "{}", message);
+}
+
+#[test]
+fn test_rejects_jump_into_operand() {
+    // The jump lands on the operand byte of OpConstant
+    let chunk = chunk_from(
+        &[OpCode::OpJump as u8, 1, OpCode::OpConstant as u8, 0, OpCode::OpReturn as u8],
+        &[Value::Number(1.0)],
+    );
+    let (offset, message) = verify_error(&chunk);
+    assert_eq!(offset, 0);
+    assert!(message.contains("inside another instruction"), "{}", message);
+}
+
+#[test]
+fn test_rejects_jump_outside_chunk() {
+    let chunk = chunk_from(&[OpCode::OpJump as u8, 10], &[]);
+    assert!(verify_error(&chunk).1.contains("outside the chunk"));
+
+    let chunk = chunk_from(&[OpCode::OpLoop as u8, 5], &[]);
+    assert!(verify_error(&chunk).1.contains("outside the chunk"));
+}
+
+#[test]
+fn test_rejects_stack_underflow() {
+    let chunk = chunk_from(&[OpCode::OpNil as u8, OpCode::OpAdd as u8], &[]);
+    let (offset, message) = verify_error(&chunk);
+    assert_eq!(offset, 1);
+    assert!(message.contains("needs 2 value(s) but the stack holds 1"), "{}", message);
+}
+
+#[test]
+fn test_rejects_inconsistent_stack_depth() {
+    // A loop that pushes a value on every iteration
+    let chunk = chunk_from(&[OpCode::OpNil as u8, OpCode::OpLoop as u8, 3], &[]);
+    let (offset, message) = verify_error(&chunk);
+    assert_eq!(offset, 0);
+    assert!(message.contains("inconsistent stack depth"), "{}", message);
+}
+
+#[test]
+fn test_rejects_mismatched_line_table() {
+    let mut chunk = chunk_from(&[OpCode::OpNil as u8], &[]);
+    chunk.lines.pop();
+    assert!(verify_error(&chunk).1.contains("line table"));
+}
+
+#[test]
+fn test_vm_refuses_invalid_chunk() {
+    let chunk = chunk_from(&[OpCode::OpPop as u8], &[]);
+    let err = VM::new().interpret(chunk).unwrap_err();
+    assert!(matches!(err, LumaError::VerifyError { offset: 0, .. }));
+    assert!(err.to_string().starts_with("Bytecode verification failed at offset 0"));
+}
+
+#[test]
+fn test_repeat_loop_runs() {
+    let source = "let total be 0\nrepeat 4 times then\n    total = total + 2";
+    let chunk = compile(source);
+    verify(&chunk).unwrap();
+
+    // The loop body runs to the end of the program, so read the total back
+    // with a second chunk on the same VM
+    let mut vm = VM::new();
+    vm.interpret(chunk).unwrap();
+    assert_eq!(vm.interpret(compile("show total")).unwrap(), Value::Number(8.0));
+}
+
+#[test]
+fn test_show_in_loop_keeps_stack_balanced() {
+    // Would overflow the 256-slot stack if `show` left its value behind
+    let source = "let i be 0\nwhile i < 300 then\n    show i\n    i = i + 1";
+    let chunk = compile(source);
+    verify(&chunk).unwrap();
+    VM::new().interpret(chunk).unwrap();
+}
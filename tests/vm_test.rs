@@ -1,12 +1,25 @@
 // Integration tests for Luma Bytecode VM
+mod common;
+
 use luma::frontend::lexer::Lexer;
 use luma::frontend::parser::Parser;
 use luma::frontend::compiler::Compiler;
 use luma::backend::vm::vm::VM;
+use luma::backend::vm::config::VmConfig;
+use luma::backend::vm::{BufferOutput, CallbackOutput, MemoryUsage};
 use luma::shared::value::Value;
+use luma::shared::chunk::Chunk;
+use luma::shared::error::{Limit, LumaError};
+use luma::shared::trace::TraceFrame;
+use luma::shared::span::Span;
+use luma::frontend::token::Token;
+use luma::frontend::ast::{ExpressionKind, StatementKind};
+use std::time::{Duration, Instant};
+use common::compile;
 
-// Helper function to run code through the complete pipeline
-fn run_code(source: &str) -> Result<Value, String> {
+// Helper function to run code through the complete pipeline, returning the
+// program's result and everything it showed
+fn run_code(source: &str) -> Result<(Value, String), String> {
     let mut lexer = Lexer::new(source);
     let tokens = lexer.tokenize().map_err(|e| e.to_string())?;
 
@@ -16,80 +29,115 @@ fn run_code(source: &str) -> Result<Value, String> {
     let mut compiler = Compiler::new();
     let chunk = compiler.compile_with_source(&statements, source).map_err(|e| e.to_string())?;
 
+    let output = BufferOutput::new();
     let mut vm = VM::new();
-    vm.interpret(chunk).map_err(|e| e.to_string())
+    vm.set_output(output.clone());
+    let result = vm.interpret(chunk).map_err(|e| e.to_string())?;
+    Ok((result, output.contents()))
+}
+
+// Helper function that runs code expected to fail and returns the error
+fn run_code_err(source: &str) -> LumaError {
+    let mut lexer = Lexer::new(source);
+    let tokens = match lexer.tokenize() {
+        Ok(tokens) => tokens,
+        Err(e) => return e,
+    };
+
+    let mut parser = Parser::new(tokens);
+    let statements = match parser.parse() {
+        Ok(statements) => statements,
+        Err(e) => return e,
+    };
+
+    let chunk = match Compiler::new().compile(&statements) {
+        Ok(chunk) => chunk,
+        Err(e) => return e,
+    };
+
+    VM::new().interpret(chunk).expect_err("expected the program to fail")
 }
 
 #[test]
 fn test_simple_number_expression() {
     let source = "show 123";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(123.0));
+    assert_eq!(output, "123\n");
 }
 
 #[test]
 fn test_simple_arithmetic() {
     let source = "show 10 + 5";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(15.0));
+    assert_eq!(output, "15\n");
 }
 
 #[test]
 fn test_operator_precedence() {
     // Test that 2 * 5 is done before 10 +
     let source = "show 10 + 2 * 5"; // 10 + 10 = 20
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(20.0));
+    assert_eq!(output, "20\n");
 }
 
 #[test]
 fn test_parentheses_expression() {
     // Test that parentheses override precedence
     let source = "show (10 + 2) * 5"; // 12 * 5 = 60
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(60.0));
+    assert_eq!(output, "60\n");
 }
 
 #[test]
 fn test_complex_expression() {
     let source = "show (100 - 20) / (2 + 2) + 5 * 2"; // 80 / 4 + 10 -> 20 + 10 = 30
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(30.0));
+    assert_eq!(output, "30\n");
 }
 
 #[test]
 fn test_subtraction() {
     let source = "show 100 - 25";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(75.0));
+    assert_eq!(output, "75\n");
 }
 
 #[test]
 fn test_multiplication() {
     let source = "show 10 * 5";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(50.0));
+    assert_eq!(output, "50\n");
 }
 
 #[test]
 fn test_division() {
     let source = "show 100 / 4";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(25.0));
+    assert_eq!(output, "25\n");
 }
 
 #[test]
 fn test_division_before_subtraction() {
     let source = "show 20 - 8 / 2"; // 8/2=4, 20-4=16
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(16.0));
+    assert_eq!(output, "16\n");
 }
 
 #[test]
 fn test_zero() {
     let source = "show 0";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(0.0));
+    assert_eq!(output, "0\n");
 }
 
 // === Phase 2: Variable Tests ===
@@ -100,8 +148,9 @@ fn test_variable_declaration() {
         let price be 2500
         show price
     "#;
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(2500.0));
+    assert_eq!(output, "2500\n");
 }
 
 #[test]
@@ -111,8 +160,9 @@ fn test_variable_in_expression() {
         let y be 5
         show x + y
     "#;
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(15.0));
+    assert_eq!(output, "15\n");
 }
 
 #[test]
@@ -122,8 +172,9 @@ fn test_complex_variable_expression() {
         let b be 3
         show (a + b) * (a - b)
     "#;
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(16.0)); // (5+3) * (5-3) = 8 * 2 = 16
+    assert_eq!(output, "16\n");
 }
 
 // === Comment Tests ===
@@ -134,15 +185,17 @@ fn test_comments_are_ignored() {
     let source = std::fs::read_to_string("tests/test_comments.luma").unwrap();
     
     // Expected: should execute and return result of "show 20 + 5" = 25
-    let result = run_code(&source).unwrap();
+    let (result, output) = run_code(&source).unwrap();
     assert_eq!(result, Value::Number(25.0));
+    assert_eq!(output, "10\n25\n");
 }
 
 #[test]
 fn test_comment_at_end_of_expression() {
     let source = "show 42 # This is the answer";
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(42.0));
+    assert_eq!(output, "42\n");
 }
 
 #[test]
@@ -152,8 +205,9 @@ fn test_single_line_comment() {
         show 100
         # Another comment
     "#;
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(100.0));
+    assert_eq!(output, "100\n");
 }
 
 #[test]
@@ -168,8 +222,9 @@ fn test_multiline_comment_blocks() {
         ## Another comment block
         ##
     "#;
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(55.0));
+    assert_eq!(output, "55\n");
 }
 
 #[test]
@@ -181,6 +236,607 @@ fn test_mixed_comment_styles() {
         # Pure single line comment
         show x * 2   ## Another comment style ## 
     "#;
-    let result = run_code(source).unwrap();
+    let (result, output) = run_code(source).unwrap();
     assert_eq!(result, Value::Number(20.0));
-}
\ No newline at end of file
+    assert_eq!(output, "20\n");
+}
+
+// === Value Representation Tests ===
+
+#[test]
+fn test_string_concatenation() {
+    let source = r#"
+        let name be "Luma"
+        show "Hello " + name
+    "#;
+    let (result, output) = run_code(source).unwrap();
+    assert_eq!(result, Value::from("Hello Luma"));
+    assert_eq!(output, "Hello Luma\n");
+}
+
+#[test]
+fn test_value_stays_compact() {
+    assert_eq!(std::mem::size_of::<Value>(), 16);
+}
+
+#[test]
+fn test_strings_serialize_as_plain_text() {
+    // Caches and snapshots written before strings went behind a thin
+    // pointer must still load
+    let bytes = bincode::serialize(&Value::from("hi")).unwrap();
+    assert_eq!(bytes, bincode::serialize(&(1u32, "hi")).unwrap());
+    assert_eq!(bincode::deserialize::<Value>(&bytes).unwrap(), Value::from("hi"));
+}
+
+#[test]
+fn test_string_constants_are_interned() {
+    let mut chunk = Chunk::new();
+    let first = chunk.add_constant(Value::from("counter"));
+    let second = chunk.intern_string("counter");
+    assert_eq!(first, second);
+    assert_eq!(chunk.constants.len(), 1);
+}
+
+#[test]
+fn test_chunk_with_strings_round_trips_through_bincode() {
+    let mut chunk = Chunk::new();
+    chunk.add_constant(Value::from("hello"));
+    chunk.add_constant(Value::Number(1.5));
+
+    let bytes = bincode::serialize(&chunk).unwrap();
+    let mut restored: Chunk = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(restored.constants, chunk.constants);
+
+    // The interning table is rebuilt lazily after loading
+    assert_eq!(restored.intern_string("hello"), 0);
+    assert_eq!(restored.constants.len(), 2);
+}
+
+#[test]
+fn test_constants_are_deduplicated_by_bit_pattern() {
+    let mut chunk = Chunk::new();
+    let one = chunk.add_constant(Value::Number(1.0));
+    assert_eq!(chunk.add_constant(Value::Number(1.0)), one);
+
+    // 0.0 == -0.0 numerically, but they must keep separate slots
+    let zero = chunk.add_constant(Value::Number(0.0));
+    let negative_zero = chunk.add_constant(Value::Number(-0.0));
+    assert_ne!(zero, negative_zero);
+
+    // NaN != NaN, but the same NaN bits should still share a slot
+    let nan = chunk.add_constant(Value::Number(f64::NAN));
+    assert_eq!(chunk.add_constant(Value::Number(f64::NAN)), nan);
+
+    // A string that looks like a number is a different constant
+    assert_ne!(chunk.add_constant(Value::from("1")), one);
+    assert_eq!(chunk.constants.len(), 5);
+}
+
+// === Source Span Tests ===
+
+#[test]
+fn test_tokens_carry_spans() {
+    let tokens = Lexer::new("let total be 10\n  show \"hi\"").tokenize().unwrap();
+    assert_eq!(tokens[0].token, Token::Let);
+    assert_eq!(tokens[0].span, Span::new(1, 1, 3));
+    assert_eq!(tokens[1].span, Span::new(1, 5, 5)); // total
+    assert_eq!(tokens[3].span, Span::new(1, 14, 2)); // 10
+    assert_eq!(tokens[5].span, Span::new(2, 3, 4)); // show
+    assert_eq!(tokens[6].span, Span::new(2, 8, 4)); // "hi" including quotes
+}
+
+#[test]
+fn test_expression_spans_cover_operands() {
+    let tokens = Lexer::new("show (a + 2) * b").tokenize().unwrap();
+    let statements = Parser::new(tokens).parse().unwrap();
+    let StatementKind::Show(expression) = &statements[0].kind else {
+        panic!("expected show statement");
+    };
+    assert_eq!(statements[0].span, Span::new(1, 1, 16));
+    assert_eq!(expression.span, Span::new(1, 6, 11));
+    let ExpressionKind::BinaryOp { left, .. } = &expression.kind else {
+        panic!("expected binary operation");
+    };
+    assert_eq!(left.span, Span::new(1, 6, 7));
+}
+
+#[test]
+fn test_parse_error_points_at_token() {
+    let error = run_code_err("let x be 1\nlet y 2");
+    assert!(matches!(error, LumaError::ParseError { .. }));
+    assert_eq!(error.span(), Some(Span::new(2, 7, 1)));
+}
+
+#[test]
+fn test_runtime_error_points_at_failing_expression() {
+    let error = run_code_err("let s be \"a\"\n\n# comment\nshow 1 + (s - 2)");
+    assert!(matches!(error.cause(), LumaError::TypeMismatch { op: "-", .. }));
+    assert_eq!(error.span(), Some(Span::new(4, 10, 7)));
+}
+
+#[test]
+fn test_undefined_variable_reports_line() {
+    let error = run_code_err("let x be 1\n\nshow x + missing");
+    assert_eq!(error.span(), Some(Span::new(3, 10, 7)));
+    assert_eq!(error.to_string(), "Runtime error at line 3: Undefined variable 'missing'");
+}
+
+// === Parser Recovery Tests ===
+
+#[test]
+fn test_parser_reports_every_syntax_error() {
+    let source = "let x be 5\nlet y 3\nshow x +\nwhile x > then\n    show x\n    let z = 2\nshow x\n";
+    let error = run_code_err(source);
+    let LumaError::ParseErrors(errors) = &error else {
+        panic!("expected several parse errors, got {:?}", error);
+    };
+    let lines: Vec<usize> = errors.iter().map(|e| e.span().unwrap().line).collect();
+    assert_eq!(lines, vec![2, 3, 4, 6]);
+    assert_eq!(error.errors().len(), 4);
+}
+
+#[test]
+fn test_parser_recovers_at_statement_keyword() {
+    // The bad expression is followed by 'show' on the same line; parsing resumes there
+    let source = "let a be ) show 1\nlet b be (";
+    let LumaError::ParseErrors(errors) = run_code_err(source) else {
+        panic!("expected several parse errors");
+    };
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[1].span().unwrap().line, 2);
+}
+
+// === Profiling Tests ===
+
+#[test]
+fn test_profiling_is_off_by_default() {
+    let mut vm = VM::new();
+    assert!(!vm.config().profiling);
+    vm.interpret(compile("let i be 0\nwhile i < 50 then\n    i = i + 1")).unwrap();
+    assert!(vm.get_execution_stats().is_empty());
+}
+
+#[test]
+fn test_profiling_counts_loop_back_edges_and_entry() {
+    let chunk = compile("let i be 0\nwhile i < 50 then\n    i = i + 1");
+    let loop_header = chunk
+        .code
+        .iter()
+        .position(|&byte| byte == luma::backend::vm::instruction::OpCode::OpLoopStart as u8)
+        .unwrap();
+
+    let mut vm = VM::with_config(VmConfig::default().with_profiling(true));
+    vm.interpret(chunk).unwrap();
+
+    // One entry per back-edge taken, plus one for entering the chunk
+    assert_eq!(vm.get_execution_stats(), vec![(loop_header, 50), (0, 1)]);
+}
+
+// === Stack Size Tests ===
+
+fn nested_sum(depth: usize) -> String {
+    // `1 + (1 + (1 + ...))` keeps every left operand on the stack
+    let mut source = String::from("show ");
+    for _ in 0..depth {
+        source.push_str("1 + (");
+    }
+    source.push('1');
+    source.push_str(&")".repeat(depth));
+    source
+}
+
+#[test]
+fn test_stack_grows_past_initial_size() {
+    let config = VmConfig::default().with_initial_stack_size(4);
+    let mut vm = VM::with_config(config);
+    assert_eq!(vm.interpret(compile(&nested_sum(60))).unwrap(), Value::Number(61.0));
+}
+
+#[test]
+fn test_stack_overflow_reports_line_and_call_depth() {
+    let config = VmConfig::default().with_max_stack_size(16);
+    let mut vm = VM::with_config(config);
+    let source = format!("let x be 1\n{}", nested_sum(20));
+
+    let err = vm.interpret(compile(&source)).unwrap_err();
+    match err.cause() {
+        LumaError::StackOverflow { limit, call_depth, span } => {
+            assert_eq!(*limit, 16);
+            assert_eq!(*call_depth, 0);
+            assert_eq!(span.map(|s| s.line), Some(2));
+        }
+        other => panic!("expected stack overflow, got {:?}", other),
+    }
+    assert_eq!(
+        err.to_string(),
+        "Stack overflow at line 2: more than 16 values at call depth 0"
+    );
+}
+
+// === Execution Limit Tests ===
+
+#[test]
+fn test_instruction_budget_stops_infinite_loop() {
+    let config = VmConfig::default().with_max_instructions(Some(1_000));
+    let mut vm = VM::with_config(config);
+
+    let err = vm.interpret(compile("let i be 0\nwhile true then\n    i = i + 1")).unwrap_err();
+    match err.cause() {
+        LumaError::LimitExceeded { limit, span } => {
+            assert_eq!(*limit, Limit::Instructions(1_000));
+            assert_eq!(span.map(|s| s.line), Some(2));
+        }
+        other => panic!("expected limit error, got {:?}", other),
+    }
+    assert_eq!(err.to_string(), "Execution limit exceeded at line 2: ran more than 1000 instructions");
+
+    // Globals written before the limit hit are kept
+    assert!(vm.global_names().any(|name| name == "i"));
+}
+
+#[test]
+fn test_instruction_budget_allows_programs_within_it() {
+    let config = VmConfig::default().with_max_instructions(Some(10_000));
+    let mut vm = VM::with_config(config);
+    vm.interpret(compile("let i be 0\nwhile i < 10 then\n    i = i + 1")).unwrap();
+}
+
+#[test]
+fn test_timeout_stops_infinite_loop() {
+    let timeout = Duration::from_millis(20);
+    let mut vm = VM::with_config(VmConfig::default().with_timeout(Some(timeout)));
+
+    let start = Instant::now();
+    let err = vm.interpret(compile("let i be 0\nwhile true then\n    i = i + 1")).unwrap_err();
+    assert!(matches!(err.cause(), LumaError::LimitExceeded { limit: Limit::Timeout(t), .. } if *t == timeout), "{:?}", err);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+// === Memory Limit Tests ===
+
+#[test]
+fn test_memory_usage_tracks_runtime_strings() {
+    let mut vm = VM::new();
+    vm.interpret(compile("let s be \"ab\" + \"cd\"")).unwrap();
+    assert_eq!(vm.memory_usage(), MemoryUsage { current: 4, peak: 4 });
+
+    // Replacing the only reference frees the old string
+    vm.interpret(compile("s = s + s")).unwrap();
+    assert_eq!(vm.memory_usage(), MemoryUsage { current: 8, peak: 12 });
+
+    // Constants belong to the chunk and are not counted
+    vm.interpret(compile("s is \"x\"")).unwrap();
+    assert_eq!(vm.memory_usage().current, 0);
+}
+
+#[test]
+fn test_memory_limit_stops_string_doubling() {
+    let config = VmConfig::default().with_max_memory(Some(1024));
+    let mut vm = VM::with_config(config);
+    let source = "let s be \"x\"\nwhile true then\n    s = s + s";
+
+    let err = vm.interpret(compile(source)).unwrap_err();
+    match err.cause() {
+        LumaError::LimitExceeded { limit, span } => {
+            assert_eq!(*limit, Limit::Memory(1024));
+            assert_eq!(span.map(|s| s.line), Some(3));
+        }
+        other => panic!("expected limit error, got {:?}", other),
+    }
+    assert!(vm.memory_usage().peak <= 1024);
+}
+
+#[test]
+fn test_failed_runs_do_not_use_up_the_memory_limit() {
+    let config = VmConfig::default().with_max_memory(Some(100));
+    let mut vm = VM::with_config(config);
+    vm.interpret(compile("let x be 1")).unwrap();
+    for _ in 0..8 {
+        // The 20-byte string ends up in the error's trace
+        let source = "show (\"aaaaaaaaa\" + x + \"bbbbbbbbbb\") - 1";
+        assert!(vm.interpret(compile(source)).is_err());
+    }
+    vm.interpret(compile("let u be \"ab\" + \"cd\"")).unwrap();
+    assert_eq!(vm.memory_usage().current, 4);
+}
+
+#[test]
+fn test_runtime_error_releases_its_operands() {
+    let mut vm = VM::new();
+    vm.interpret(compile("let x be 1\nlet kept be \"ab\" + x")).unwrap();
+    let before = vm.memory_usage().current;
+
+    // Both a binary and a unary operator fail on a freshly built string
+    let error = vm.interpret(compile("show (\"aaaaaaaaa\" + x + \"bbbbbbbbbb\") - 1")).unwrap_err();
+    assert_eq!(error.trace().unwrap().values[0], Value::from("aaaaaaaaa1bbbbbbbbbb"));
+    assert_eq!(vm.memory_usage().current, before);
+
+    let _error = vm.interpret(compile("show -(\"aaaaaaaaa\" + x)")).unwrap_err();
+    assert_eq!(vm.memory_usage().current, before);
+}
+
+#[test]
+fn test_memory_usage_through_c_api() {
+    use luma::ffi::c_api::{luma_execute_source, luma_free_error_message, luma_vm_free, luma_vm_memory_usage, luma_vm_new};
+    use std::ffi::CString;
+
+    let vm = luma_vm_new();
+    let source = CString::new("let s be \"ab\" + \"cd\"\ns = s + s").unwrap();
+    assert!(luma_execute_source(vm, source.as_ptr()).success);
+
+    let (mut current, mut peak) = (0, 0);
+    assert!(luma_vm_memory_usage(vm, &mut current, &mut peak).success);
+    assert_eq!((current, peak), (8, 12));
+    let result = luma_vm_memory_usage(vm, std::ptr::null_mut(), &mut peak);
+    assert!(!result.success);
+    luma_free_error_message(result.error_message);
+    luma_vm_free(vm);
+}
+
+// === Interrupt Tests ===
+
+#[test]
+fn test_interrupt_stops_running_vm_and_keeps_globals() {
+    let mut vm = VM::new();
+    vm.interpret(compile("let before be 1")).unwrap();
+
+    let handle = vm.interrupt_handle();
+    let interrupter = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        handle.interrupt();
+    });
+
+    let err = vm.interpret(compile("let i be 0\nwhile true then\n    i = i + 1")).unwrap_err();
+    interrupter.join().unwrap();
+    match err.cause() {
+        LumaError::Interrupted { span } => assert_eq!(span.map(|s| s.line), Some(2)),
+        other => panic!("expected interrupt, got {:?}", other),
+    }
+
+    // The session is still usable and its variables survive
+    assert_eq!(run_on(&mut vm, "show before + 1"), Value::Number(2.0));
+    assert!(matches!(run_on(&mut vm, "show i > 0"), Value::Boolean(true)));
+}
+
+#[test]
+fn test_interrupt_is_consumed_and_can_be_cleared() {
+    let mut vm = VM::new();
+    let handle = vm.interrupt_handle();
+    let looping = "let i be 0\nwhile i < 3 then\n    i = i + 1";
+
+    handle.interrupt();
+    let err = vm.interpret(compile(looping)).unwrap_err();
+    assert!(matches!(err.cause(), LumaError::Interrupted { .. }));
+    // A request stops one run only
+    vm.interpret(compile(looping)).unwrap();
+
+    handle.interrupt();
+    handle.clear();
+    vm.interpret(compile(looping)).unwrap();
+}
+
+fn run_on(vm: &mut VM, source: &str) -> Value {
+    vm.interpret(compile(source)).unwrap()
+}
+
+// === Output Tests ===
+
+#[test]
+fn test_show_in_loop_writes_every_line() {
+    let source = "let i be 1\nwhile i <= 3 then\n    show \"line \" + i\n    i = i + 1";
+    let (result, output) = run_code(source).unwrap();
+    assert_eq!(result, Value::Nil);
+    assert_eq!(output, "line 1\nline 2\nline 3\n");
+}
+
+#[test]
+fn test_only_a_trailing_show_is_the_result() {
+    assert_eq!(run_code("show 1\nshow 2").unwrap(), (Value::Number(2.0), "1\n2\n".to_string()));
+    assert_eq!(run_code("show 1\nlet x be 2").unwrap(), (Value::Nil, "1\n".to_string()));
+}
+
+#[test]
+fn test_show_does_not_grow_the_stack() {
+    // Each `show` pops its value, so a long loop fits in a small stack
+    let mut vm = VM::with_config(VmConfig::default().with_max_stack_size(16));
+    vm.set_output(BufferOutput::new());
+    vm.interpret(compile("repeat 1000 times then\n    show 1")).unwrap();
+}
+
+#[test]
+fn test_callback_output_receives_lines() {
+    let lines = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let sink = lines.clone();
+
+    let mut vm = VM::new();
+    vm.set_output(CallbackOutput::new(move |line: &str| sink.borrow_mut().push(line.to_string())));
+    vm.interpret(compile("show 1 + 1\nshow \"two\"\nshow true")).unwrap();
+
+    assert_eq!(*lines.borrow(), vec!["2", "two", "true"]);
+}
+
+// === Conditional Tests ===
+
+#[test]
+fn test_else_if_branches_are_compiled() {
+    let grade = |score: u32| {
+        let source = format!(
+            "let score be {}\nif score >= 90 then\n    show \"A\"\nelse if score >= 70 then\n    show \"B\"\nelse if score >= 50 then\n    show \"C\"\nelse\n    show \"F\"",
+            score
+        );
+        run_code(&source).unwrap().1
+    };
+    assert_eq!(grade(95), "A\n");
+    assert_eq!(grade(75), "B\n");
+    assert_eq!(grade(55), "C\n");
+    assert_eq!(grade(10), "F\n");
+}
+
+// === Repeat Tests ===
+
+#[test]
+fn test_repeat_evaluates_its_count_once() {
+    let mut vm = VM::new();
+    vm.interpret(compile("let n be 3\nlet runs be 0\nrepeat n times then\n    n = n + 1\n    runs = runs + 1")).unwrap();
+    assert_eq!(vm.get_global("runs"), Some(&Value::Number(3.0)));
+    assert_eq!(vm.get_global("n"), Some(&Value::Number(6.0)));
+}
+
+#[test]
+fn test_nested_repeats_keep_separate_counters() {
+    let mut vm = VM::new();
+    vm.interpret(compile("let total be 0\nrepeat 2 times then\n    repeat 3 times then\n        total = total + 1")).unwrap();
+    assert_eq!(vm.get_global("total"), Some(&Value::Number(6.0)));
+}
+
+// === Runtime Trace Tests ===
+
+#[test]
+fn test_runtime_error_trace_records_operands_and_loops() {
+    let source = "let i be 0\nwhile i < 5 then\n    repeat 2 times then\n        i = i - \"x\"";
+    let error = run_code_err(source);
+    let trace = error.trace().expect("runtime errors carry a trace");
+
+    assert_eq!(trace.span, Span::new(4, 13, 7));
+    assert_eq!(trace.values, vec![Value::Number(0.0), Value::from("x")]);
+    assert_eq!(
+        trace.frames,
+        vec![
+            TraceFrame::Loop { span: Span::new(3, 5, 19) },
+            TraceFrame::Loop { span: Span::new(2, 1, 16) },
+        ]
+    );
+    assert_eq!(
+        trace.notes(),
+        vec![
+            "operands were 0 and \"x\"",
+            "inside the loop at line 3",
+            "inside the loop at line 2",
+        ]
+    );
+
+    // The trace does not change how the error itself reads
+    assert!(matches!(error.cause(), LumaError::TypeMismatch { .. }));
+    assert_eq!(error.to_string(), error.cause().to_string());
+}
+
+#[test]
+fn test_trace_shortens_long_strings() {
+    let error = run_code_err(&format!("show -\"{}\"", "a".repeat(100)));
+    let notes = error.trace().unwrap().notes();
+    assert_eq!(notes, vec![format!("operand was {:?}... (100 characters)", "a".repeat(32))]);
+}
+
+#[test]
+fn test_top_level_error_has_no_frames() {
+    let error = run_code_err("show missing");
+    let trace = error.trace().unwrap();
+    assert!(trace.frames.is_empty());
+    assert!(trace.values.is_empty());
+}
+
+// === Error Kind Tests ===
+
+#[test]
+fn test_type_mismatch_names_operator_and_types() {
+    let error = run_code_err("show 1 < \"abc\"");
+    match error.cause() {
+        LumaError::TypeMismatch { op, left, right, .. } => {
+            assert_eq!((*op, *left, *right), ("<", "number", "string"));
+        }
+        other => panic!("expected type mismatch, got {:?}", other),
+    }
+    assert_eq!(error.code(), "E0102");
+    assert_eq!(error.to_string(), "Runtime error at line 1: Cannot apply '<' to number and string");
+}
+
+#[test]
+fn test_numeric_strings_still_convert() {
+    let (result, _) = run_code("show \"12\" - 2").unwrap();
+    assert_eq!(result, Value::Number(10.0));
+}
+
+#[test]
+fn test_division_and_modulo_by_zero() {
+    let error = run_code_err("show 1 / 0");
+    assert!(matches!(error.cause(), LumaError::DivisionByZero { op: "/", .. }));
+    assert_eq!(error.message(), "Division by zero");
+
+    let error = run_code_err("show 1 % 0");
+    assert!(matches!(error.cause(), LumaError::DivisionByZero { op: "%", .. }));
+    assert_eq!(error.message(), "Modulo by zero");
+    assert_eq!(error.code(), "E0104");
+}
+
+#[test]
+fn test_invalid_unary_operand() {
+    let error = run_code_err("show -\"abc\"");
+    assert!(matches!(error.cause(), LumaError::InvalidOperand { op: "-", operand: "string", .. }));
+    assert_eq!(error.code(), "E0103");
+}
+
+#[test]
+fn test_error_codes_are_stable() {
+    assert_eq!(run_code_err("show missing").code(), "E0101");
+    assert_eq!(run_code_err("let x 1").code(), "E0002");
+    assert_eq!(run_code_err("show \"open").code(), "E0001");
+
+    let config = VmConfig::default().with_max_instructions(Some(10));
+    let mut vm = VM::with_config(config);
+    let error = vm.interpret(compile("while true then\n    let x be 1")).unwrap_err();
+    assert_eq!(error.code(), "E0301");
+}
+
+#[test]
+fn test_too_many_constants_is_a_compile_error() {
+    // 256 distinct numbers fit in the one-byte operand; one more does not
+    let fits: String = (0..256).map(|i| format!("show {}\n", i)).collect();
+    let (_, output) = run_code(&fits).unwrap();
+    assert_eq!(output.lines().count(), 256);
+    assert_eq!(output.lines().last(), Some("255"));
+
+    let error = run_code_err(&format!("{}show 256", fits));
+    assert!(matches!(error, LumaError::CompileError { .. }));
+    assert!(error.to_string().contains("Too many constants"), "{}", error);
+}
+
+// === Line Profile Tests ===
+
+#[test]
+fn test_line_profile_is_off_by_default() {
+    let mut vm = VM::new();
+    run_on(&mut vm, "let x be 1");
+    assert!(vm.line_profile().is_none());
+}
+
+#[test]
+fn test_line_profile_counts_instructions_per_line() {
+    let mut vm = VM::with_config(VmConfig::default().with_line_profiling(true));
+    run_on(&mut vm, "let i be 0\nwhile i < 3 then\n    i = i + 1");
+    let profile = vm.line_profile().unwrap();
+
+    // Get, constant, add, set and pop, three times over
+    let body = profile.lines.iter().find(|stats| stats.line == 3).unwrap();
+    assert_eq!(body.instructions, 15);
+    assert_eq!(profile.lines.len(), 3);
+    assert!(profile.lines.windows(2).all(|pair| pair[0].instructions >= pair[1].instructions));
+    assert!(profile.total_time() > Duration::ZERO);
+
+    let report = profile.report("let i be 0\nwhile i < 3 then\n    i = i + 1");
+    assert!(report.starts_with(&format!("Line profile: {} instructions", profile.total_instructions())));
+    assert!(report.lines().any(|row| row.trim_start().starts_with("3 ") && row.ends_with("  i = i + 1")), "{}", report);
+}
+
+#[test]
+fn test_line_profile_folded_stacks_nest_loops() {
+    let mut vm = VM::with_config(VmConfig::default().with_line_profiling(true));
+    run_on(&mut vm, "let i be 0\nwhile i < 2 then\n    repeat 2 times then\n        i = i + 0.5");
+    let folded = vm.line_profile().unwrap().folded("script.luma");
+
+    assert!(folded.starts_with("script.luma;line 1 3\n"), "{}", folded);
+    assert!(folded.contains("script.luma;loop at line 2;loop at line 3;line 4 20\n"), "{}", folded);
+    for row in folded.lines() {
+        let (_, count) = row.rsplit_once(' ').unwrap();
+        assert!(count.parse::<u64>().unwrap() > 0);
+    }
+}
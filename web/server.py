@@ -69,7 +69,11 @@ class LumaWebHandler(http.server.SimpleHTTPRequestHandler):
                 
                 # Run the Luma interpreter on the temporary file
                 result = subprocess.run(
-                    ['../target/release/luma', temp_file],
+                    ['../target/release/luma',
+                     '--max-instructions', '50000000',
+                     '--timeout-ms', '5000',
+                     '--max-memory', '67108864',
+                     temp_file],
                     capture_output=True,
                     text=True,
                     timeout=30  # 30 second timeout
@@ -86,7 +86,11 @@ class LumaHandler(http.server.BaseHTTPRequestHandler):
                 
                 # Run Luma interpreter with performance measurement
                 result = subprocess.run(
-                    ['/home/runner/workspace/target/release/luma', temp_file],
+                    ['/home/runner/workspace/target/release/luma',
+                     '--max-instructions', '50000000',
+                     '--timeout-ms', '5000',
+                     '--max-memory', '67108864',
+                     temp_file],
                     capture_output=True,
                     text=True,
                     timeout=15,  # Reduced to 15 seconds with infinite loop protection